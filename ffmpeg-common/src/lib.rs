@@ -137,6 +137,8 @@ pub struct Capabilities {
     pub formats: Vec<String>,
     /// Available filters
     pub filters: Vec<String>,
+    /// Filters that support timeline editing (`enable=`)
+    pub timeline_filters: Vec<String>,
     /// Available protocols
     pub protocols: Vec<String>,
     /// Available pixel formats
@@ -148,10 +150,24 @@ pub struct Capabilities {
 impl Capabilities {
     /// Detect capabilities by running FFmpeg with various list options
     pub async fn detect(executable: &str) -> Result<Self> {
-        let caps = Self::default();
+        let mut caps = Self::default();
 
-        // This is a simplified version - in a real implementation,
-        // we would parse the output of ffmpeg -codecs, -formats, etc.
+        // Codecs, formats and the other lists are not parsed yet
+        let path = process::find_executable(executable)?;
+        let config = ProcessConfig::new(path)
+            .capture_stdout(true)
+            .capture_stderr(false);
+
+        let args = vec!["-hide_banner".to_string(), "-filters".to_string()];
+        let output = Process::spawn(config, args)
+            .await?
+            .wait()
+            .await?
+            .into_result()?;
+
+        let filters = output.stdout_str()
+            .ok_or_else(|| Error::ParseError("No filter list output".to_string()))?;
+        caps.parse_filters(&filters);
 
         Ok(caps)
     }
@@ -170,6 +186,35 @@ impl Capabilities {
     pub fn has_filter(&self, filter: &str) -> bool {
         self.filters.iter().any(|f| f == filter)
     }
+
+    /// Check if a filter supports timeline editing
+    ///
+    /// Returns `None` when no filter data has been loaded.
+    pub fn supports_timeline(&self, filter: &str) -> Option<bool> {
+        if self.filters.is_empty() {
+            return None;
+        }
+        Some(self.timeline_filters.iter().any(|f| f == filter))
+    }
+
+    /// Load filters and their flags from the output of `ffmpeg -filters`
+    pub fn parse_filters(&mut self, output: &str) {
+        for line in output.lines() {
+            let mut fields = line.split_whitespace();
+            let (Some(flags), Some(name), Some(io)) = (fields.next(), fields.next(), fields.next())
+            else {
+                continue;
+            };
+            // Skip the legend, e.g. "T.. = Timeline support"
+            if flags.len() != 3 || !io.contains("->") {
+                continue;
+            }
+            self.filters.push(name.to_string());
+            if flags.starts_with('T') {
+                self.timeline_filters.push(name.to_string());
+            }
+        }
+    }
 }
 
 #[cfg(test)]
@@ -190,6 +235,29 @@ configuration: --prefix=/usr --extra-version=0ubuntu0.22.04.1 --toolchain=harden
         assert!(!version.configuration.is_empty());
     }
 
+    #[test]
+    fn test_parse_filters() {
+        let output = "Filters:
+  T.. = Timeline support
+  .S. = Slice threading
+  ..C = Command support
+  A = Audio input/output
+  V = Video input/output
+ TSC boxblur           V->V       Blur the input.
+ ... fps               V->V       Force constant framerate.
+ TSC volume            A->A       Change input volume.
+ ... amix              N->A       Audio mixing.";
+
+        let mut caps = Capabilities::default();
+        assert_eq!(caps.supports_timeline("boxblur"), None);
+
+        caps.parse_filters(output);
+        assert_eq!(caps.filters, vec!["boxblur", "fps", "volume", "amix"]);
+        assert_eq!(caps.supports_timeline("boxblur"), Some(true));
+        assert_eq!(caps.supports_timeline("fps"), Some(false));
+        assert!(caps.has_filter("amix"));
+    }
+
     #[test]
     fn test_version_comparison() {
         let version = Version {
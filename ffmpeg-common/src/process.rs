@@ -289,7 +289,8 @@ pub struct Progress {
 impl Progress {
     /// Parse progress from FFmpeg stderr line
     pub fn parse_line(line: &str) -> Option<Self> {
-        if !line.contains("frame=") {
+        // Audio-only runs report no frames, only size, time and speed
+        if !(line.contains("frame=") || line.contains("time=") && line.contains("speed=")) {
             return None;
         }
 
@@ -499,5 +500,13 @@ mod tests {
         assert_eq!(progress.time, Some(Duration::from_secs(4)));
         assert_eq!(progress.bitrate, Some(2_097_200.0));
         assert_eq!(progress.speed, Some(1.0));
+
+        let line = "size=N/A time=00:01:30.50 bitrate=N/A speed= 412x";
+        let progress = Progress::parse_line(line).unwrap();
+        assert_eq!(progress.frame, None);
+        assert_eq!(progress.time, Some(Duration::from_millis(90_500)));
+        assert_eq!(progress.speed, Some(412.0));
+
+        assert!(Progress::parse_line("[silencedetect @ 0x1] silence_end: 4.2").is_none());
     }
 }
@@ -68,8 +68,15 @@ impl Duration {
             let sec_parts: Vec<&str> = parts[2].split('.').collect();
             let secs: u64 = sec_parts[0].parse()
                 .map_err(|_| Error::ParseError(format!("Invalid seconds: {}", sec_parts[0])))?;
-            let ms: u64 = sec_parts[1].parse()
-                .map_err(|_| Error::ParseError(format!("Invalid milliseconds: {}", sec_parts[1])))?;
+            let digits = sec_parts[1];
+            if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+                return Err(Error::ParseError(format!("Invalid milliseconds: {digits}")));
+            }
+            // The fraction is decimal: ".5" is 500 ms, not 5 ms
+            let fraction = format!("{:0<3}", &digits[..digits.len().min(3)]);
+            let ms: u64 = fraction
+                .parse()
+                .map_err(|_| Error::ParseError(format!("Invalid milliseconds: {digits}")))?;
             (secs, ms)
         } else {
             let secs: u64 = parts[2].parse()
@@ -603,8 +610,24 @@ mod tests {
     #[test]
     fn test_duration_parsing() {
         assert_eq!(Duration::from_ffmpeg_format("10").unwrap().as_secs(), 10);
-        assert_eq!(Duration::from_ffmpeg_format("01:30:00").unwrap().as_secs(), 5400);
-        assert_eq!(Duration::from_ffmpeg_format("00:00:30.500").unwrap().as_millis(), 30500);
+        assert_eq!(
+            Duration::from_ffmpeg_format("01:30:00").unwrap().as_secs(),
+            5400
+        );
+        assert_eq!(
+            Duration::from_ffmpeg_format("00:00:30.500")
+                .unwrap()
+                .as_millis(),
+            30500
+        );
+        assert_eq!(
+            Duration::from_ffmpeg_format("00:00:30.50")
+                .unwrap()
+                .as_millis(),
+            30500
+        );
+        assert!(Duration::from_ffmpeg_format("00:00:30.\u{e9}5").is_err());
+        assert!(Duration::from_ffmpeg_format("00:00:30.").is_err());
     }
 
     #[test]
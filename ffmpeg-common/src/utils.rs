@@ -161,7 +161,7 @@ pub fn guess_format_from_extension(path: &Path) -> Option<&'static str> {
         // Video formats
         "mp4" => Some("mp4"),
         "m4v" => Some("mp4"),
-        "mkv" => Some("matroska"),
+        "mkv" | "mka" => Some("matroska"),
         "webm" => Some("webm"),
         "avi" => Some("avi"),
         "mov" => Some("mov"),
@@ -0,0 +1,304 @@
+//! Atomic output writes
+//!
+//! An output marked with [`Output::atomic`] is written under a hidden name
+//! next to its destination and renamed into place only after FFmpeg exits
+//! successfully. When the run fails, times out, is killed with
+//! [`FFmpegProcess::kill`](crate::builder::FFmpegProcess::kill) or its handle
+//! is dropped, the partial file is removed instead.
+//!
+//! Playlist outputs stage the file watchers key on and publish it after
+//! everything it references is complete:
+//!
+//! - HLS: the master playlist, or the media playlist when there is none.
+//!   Segments and variant playlists are written with `hls_flags temp_file`,
+//!   so they only appear once complete.
+//! - DASH: the manifest.
+//! - Segment muxer: the segment list, which is required.
+//!
+//! Segments written by a failed run are left in place.
+
+use ffmpeg_common::{Error, MediaPath, Result};
+use std::path::{Path, PathBuf};
+use tempfile::TempPath;
+
+use crate::output::Output;
+
+/// Hidden file FFmpeg writes instead of `destination`
+#[derive(Debug)]
+struct StagedFile {
+    temp: TempPath,
+    destination: PathBuf,
+    playlist: bool,
+}
+
+/// Staged files of one run, removed on drop unless committed
+#[derive(Debug, Default)]
+pub(crate) struct AtomicOutputs {
+    files: Vec<StagedFile>,
+    /// Whether committing may replace existing destinations
+    overwrite: bool,
+}
+
+impl AtomicOutputs {
+    pub(crate) fn new(overwrite: bool) -> Self {
+        Self {
+            files: Vec::new(),
+            overwrite,
+        }
+    }
+
+    /// Redirect `output` to a hidden sibling of the file it publishes
+    pub(crate) fn stage(&mut self, output: Output) -> Result<Output> {
+        let destination = output.destination();
+        if destination.is_url() || is_pipe(destination) {
+            return Err(Error::InvalidArgument(format!(
+                "Atomic writes need a local file destination: {destination}"
+            )));
+        }
+        let destination = destination.path().clone();
+
+        match output.container_format().as_deref() {
+            Some("hls") => {
+                let flags = match output.option_value("hls_flags") {
+                    Some(flags) if flags.split('+').any(|f| f == "temp_file") => flags.to_string(),
+                    Some(flags) => format!("{flags}+temp_file"),
+                    None => "temp_file".to_string(),
+                };
+                let output = output.option("hls_flags", flags);
+                let Some(master) = output.option_value("master_pl_name").map(PathBuf::from) else {
+                    return self.stage_destination(output, true);
+                };
+
+                let target = master_dir(&destination).join(master);
+                let temp = reserve(&target)?;
+                let name = temp
+                    .file_name()
+                    .map(|name| name.to_string_lossy().into_owned())
+                    .unwrap_or_default();
+                self.push(temp, target, true);
+                Ok(output.option("master_pl_name", name))
+            }
+            Some("dash") => self.stage_destination(output, true),
+            Some("segment" | "stream_segment") => {
+                let Some(list) = output.option_value("segment_list").map(PathBuf::from) else {
+                    return Err(Error::InvalidArgument(format!(
+                        "Atomic segment output needs a segment list to publish: {}",
+                        destination.display()
+                    )));
+                };
+                let temp = reserve(&list)?;
+                let staged = temp.to_string_lossy().into_owned();
+                self.push(temp, list, true);
+                Ok(output.option("segment_list", staged))
+            }
+            _ => self.stage_destination(output, false),
+        }
+    }
+
+    fn stage_destination(&mut self, output: Output, playlist: bool) -> Result<Output> {
+        let destination = output.destination().path().clone();
+        if destination.to_string_lossy().contains('%') {
+            return Err(Error::InvalidArgument(format!(
+                "Atomic writes need a single file or playlist, not a pattern: {}",
+                destination.display()
+            )));
+        }
+        let temp = reserve(&destination)?;
+        let staged = MediaPath::from_path(temp.to_path_buf());
+        self.push(temp, destination, playlist);
+        Ok(output.with_destination(staged))
+    }
+
+    fn push(&mut self, temp: TempPath, destination: PathBuf, playlist: bool) {
+        self.files.push(StagedFile {
+            temp,
+            destination,
+            playlist,
+        });
+    }
+
+    /// Paths the staged files will be published to
+    pub(crate) fn destinations(&self) -> impl Iterator<Item = &Path> {
+        self.files.iter().map(|file| file.destination.as_path())
+    }
+
+    /// Rename every staged file into place, playlists last
+    ///
+    /// Unless created with `overwrite`, an existing destination is left
+    /// alone and reported as an error.
+    pub(crate) fn commit(mut self) -> Result<()> {
+        self.files.sort_by_key(|file| file.playlist);
+        for file in self.files {
+            let persisted = if self.overwrite {
+                file.temp.persist(&file.destination)
+            } else {
+                file.temp.persist_noclobber(&file.destination)
+            };
+            persisted.map_err(|e| Error::Io(e.error))?;
+        }
+        Ok(())
+    }
+}
+
+fn is_pipe(path: &MediaPath) -> bool {
+    let path = path.as_str();
+    path == "-" || path.starts_with("pipe:")
+}
+
+/// Directory the HLS muxer writes the master playlist to: the playlist's
+/// directory, or its parent when that is a `%v` directory
+fn master_dir(playlist: &Path) -> PathBuf {
+    let dir = playlist.parent().unwrap_or(Path::new(""));
+    if dir
+        .file_name()
+        .is_some_and(|name| name.to_string_lossy().contains("%v"))
+    {
+        dir.parent().unwrap_or(Path::new("")).to_path_buf()
+    } else {
+        dir.to_path_buf()
+    }
+}
+
+/// Pick an unused hidden name next to `destination`, keeping its extension
+fn reserve(destination: &Path) -> Result<TempPath> {
+    let dir = match destination.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    std::fs::create_dir_all(dir)?;
+
+    let stem = destination
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let suffix = destination
+        .extension()
+        .map(|ext| format!(".{}", ext.to_string_lossy()))
+        .unwrap_or_default();
+    let path = tempfile::Builder::new()
+        .prefix(&format!(".{stem}.partial-"))
+        .suffix(&suffix)
+        .tempfile_in(dir)?
+        .into_temp_path();
+    // FFmpeg creates the file itself; an existing one would need `-y`
+    std::fs::remove_file(&path)?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hls::{HlsPackage, HlsVariant};
+    use crate::segment::{SegmentListType, SegmentOutput};
+    use crate::stream::StreamMap;
+    use ffmpeg_common::Duration;
+
+    #[test]
+    fn test_commit_and_discard() {
+        let dir = tempfile::tempdir().unwrap();
+        let target = dir.path().join("movie.mp4");
+
+        let mut atomic = AtomicOutputs::default();
+        let output = atomic.stage(Output::new(target.clone())).unwrap();
+        let staged = output.destination().path().clone();
+        assert_eq!(staged.parent(), Some(dir.path()));
+        assert_eq!(staged.extension().unwrap(), "mp4");
+        assert!(
+            staged
+                .file_name()
+                .unwrap()
+                .to_string_lossy()
+                .starts_with(".movie.partial-")
+        );
+        assert!(!staged.exists());
+
+        std::fs::write(&staged, b"data").unwrap();
+        atomic.commit().unwrap();
+        assert_eq!(std::fs::read(&target).unwrap(), b"data");
+        assert!(!staged.exists());
+
+        // An existing destination is only replaced when overwriting
+        let mut atomic = AtomicOutputs::default();
+        let staged = atomic.stage(Output::new(target.clone())).unwrap();
+        std::fs::write(staged.destination().path(), b"new").unwrap();
+        assert!(atomic.commit().is_err());
+        assert_eq!(std::fs::read(&target).unwrap(), b"data");
+
+        let mut atomic = AtomicOutputs::new(true);
+        let staged = atomic.stage(Output::new(target.clone())).unwrap();
+        std::fs::write(staged.destination().path(), b"new").unwrap();
+        atomic.commit().unwrap();
+        assert_eq!(std::fs::read(&target).unwrap(), b"new");
+
+        let mut atomic = AtomicOutputs::default();
+        let staged = atomic
+            .stage(Output::new(dir.path().join("other.mkv")))
+            .unwrap()
+            .destination()
+            .path()
+            .clone();
+        std::fs::write(&staged, b"partial").unwrap();
+        drop(atomic);
+        assert!(!staged.exists());
+        assert!(!dir.path().join("other.mkv").exists());
+    }
+
+    #[test]
+    fn test_playlists() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut atomic = AtomicOutputs::default();
+
+        let hls = HlsPackage::new(dir.path())
+            .variant(HlsVariant::new("720p", StreamMap::video_from(0)))
+            .variant(HlsVariant::new("480p", StreamMap::video_from(0)))
+            .into_output()
+            .unwrap();
+        let hls = atomic.stage(hls).unwrap();
+        let master = hls.option_value("master_pl_name").unwrap();
+        assert!(master.starts_with(".master.partial-"));
+        assert!(
+            hls.option_value("hls_flags")
+                .unwrap()
+                .ends_with("temp_file")
+        );
+
+        let segments = SegmentOutput::new(Output::new(dir.path().join("part_%03d.ts")))
+            .segment_time(Duration::from_secs(10))
+            .list(dir.path().join("parts.m3u8"), SegmentListType::M3u8)
+            .into_output()
+            .unwrap();
+        let segments = atomic.stage(segments).unwrap();
+        assert_ne!(
+            segments.option_value("segment_list").map(PathBuf::from),
+            Some(dir.path().join("parts.m3u8"))
+        );
+
+        let destinations: Vec<_> = atomic.destinations().collect();
+        assert_eq!(
+            destinations,
+            [
+                dir.path().join("master.m3u8"),
+                dir.path().join("parts.m3u8")
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unsupported_destinations() {
+        let mut atomic = AtomicOutputs::default();
+        assert!(atomic.stage(Output::new("rtmp://live/app")).is_err());
+        assert!(
+            atomic
+                .stage(Output::new("pipe:1").format("mpegts"))
+                .is_err()
+        );
+        assert!(atomic.stage(Output::new("frames/%04d.png")).is_err());
+
+        let segments = SegmentOutput::new(Output::new("part_%03d.ts"))
+            .segment_time(Duration::from_secs(10))
+            .into_output()
+            .unwrap();
+        assert!(atomic.stage(segments).is_err());
+        assert_eq!(atomic.destinations().count(), 0);
+    }
+}
@@ -1,15 +1,19 @@
 use ffmpeg_common::{
-    process::stream_progress, CommandBuilder, Duration, Error, LogLevel, MediaPath, Process,
-    ProcessConfig, ProcessOutput, Progress, Result, StreamSpecifier,
+    CommandBuilder, Duration, Error, LogLevel, MediaPath, Process, ProcessConfig, ProcessOutput,
+    Progress, Result, StreamSpecifier, error::ErrorBuilder, process::stream_progress,
 };
 use std::fmt::Debug;
 use std::path::PathBuf;
 use std::sync::Arc;
 use std::time::Duration as StdDuration;
+use tempfile::TempPath;
 use tracing::info;
 
-use crate::filter::{AudioFilter, VideoFilter};
-use crate::input::Input;
+use crate::atomic::AtomicOutputs;
+use crate::compat;
+use crate::filter::{AudioFilter, FilterGraph, VideoFilter, graph_output_labels};
+use crate::input::{ConcatInput, Input};
+use crate::metadata::{FfMetadata, MetadataSource};
 use crate::output::Output;
 use crate::stream::StreamMap;
 
@@ -23,20 +27,26 @@ pub struct FFmpegBuilder {
     inputs: Vec<Input>,
     /// Output specifications
     outputs: Vec<Output>,
-    /// Stream mappings
+    /// Stream mappings for outputs without their own
     stream_maps: Vec<StreamMap>,
-    /// Video filters
+    /// FFMETADATA input for outputs without their own metadata source
+    metadata_input: Option<usize>,
+    /// Video filters shared by every output
     video_filters: Vec<VideoFilter>,
-    /// Audio filters
+    /// Audio filters shared by every output
     audio_filters: Vec<AudioFilter>,
     /// Complex filter graph
     filter_complex: Option<String>,
+    /// Typed complex filter graph, validated before running
+    filter_graph: Option<FilterGraph>,
     /// Log level
     log_level: Option<LogLevel>,
     /// Whether to overwrite output files
     overwrite: bool,
     /// Whether to never overwrite output files
     no_overwrite: bool,
+    /// Whether to skip checking output codecs against their containers
+    skip_compat_check: bool,
     /// Time limit for encoding
     time_limit: Option<Duration>,
     /// File size limit
@@ -62,12 +72,15 @@ impl Debug for FFmpegBuilder {
             .field("inputs", &self.inputs)
             .field("outputs", &self.outputs)
             .field("stream_maps", &self.stream_maps)
+            .field("metadata_input", &self.metadata_input)
             .field("video_filters", &self.video_filters)
             .field("audio_filters", &self.audio_filters)
             .field("filter_complex", &self.filter_complex)
+            .field("filter_graph", &self.filter_graph)
             .field("log_level", &self.log_level)
             .field("overwrite", &self.overwrite)
             .field("no_overwrite", &self.no_overwrite)
+            .field("skip_compat_check", &self.skip_compat_check)
             .field("time_limit", &self.time_limit)
             .field("file_size_limit", &self.file_size_limit)
             .field("threads", &self.threads)
@@ -92,12 +105,15 @@ impl Clone for FFmpegBuilder {
             inputs: self.inputs.clone(),
             outputs: self.outputs.clone(),
             stream_maps: self.stream_maps.clone(),
+            metadata_input: self.metadata_input,
             video_filters: self.video_filters.clone(),
             audio_filters: self.audio_filters.clone(),
             filter_complex: self.filter_complex.clone(),
+            filter_graph: self.filter_graph.clone(),
             log_level: self.log_level,
             overwrite: self.overwrite,
             no_overwrite: self.no_overwrite,
+            skip_compat_check: self.skip_compat_check,
             time_limit: self.time_limit,
             file_size_limit: self.file_size_limit,
             threads: self.threads,
@@ -120,12 +136,15 @@ impl FFmpegBuilder {
             inputs: Vec::new(),
             outputs: Vec::new(),
             stream_maps: Vec::new(),
+            metadata_input: None,
             video_filters: Vec::new(),
             audio_filters: Vec::new(),
             filter_complex: None,
+            filter_graph: None,
             log_level: None,
             overwrite: false,
             no_overwrite: false,
+            skip_compat_check: false,
             time_limit: None,
             file_size_limit: None,
             threads: None,
@@ -144,12 +163,15 @@ impl FFmpegBuilder {
             inputs: Vec::new(),
             outputs: Vec::new(),
             stream_maps: Vec::new(),
+            metadata_input: None,
             video_filters: Vec::new(),
             audio_filters: Vec::new(),
             filter_complex: None,
+            filter_graph: None,
             log_level: None,
             overwrite: false,
             no_overwrite: false,
+            skip_compat_check: false,
             time_limit: None,
             file_size_limit: None,
             threads: None,
@@ -166,11 +188,59 @@ impl FFmpegBuilder {
         self
     }
 
+    /// Number of inputs added so far, which is the index of the next one
+    pub fn input_count(&self) -> usize {
+        self.inputs.len()
+    }
+
     /// Add an input from a path
     pub fn input_path(self, path: impl Into<MediaPath>) -> Self {
         self.input(Input::new(path))
     }
 
+    /// Add clips to be joined
+    ///
+    /// In demuxer mode this adds the list file input. In filter mode it adds
+    /// one input per clip plus the `concat` filter graph, whose outputs are
+    /// mapped after any maps already set, for every output without maps of
+    /// its own. When several outputs share them, they are split into one
+    /// copy per output.
+    pub fn concat(mut self, concat: ConcatInput) -> Result<Self> {
+        if self.filter_graph.is_some() || self.filter_complex.is_some() {
+            return Err(Error::InvalidArgument(
+                "Concatenation with the concat filter needs the filter graph, \
+                 which is already set"
+                    .to_string(),
+            ));
+        }
+
+        let first_input = self.inputs.len();
+        let graph = if concat.is_demuxer() {
+            None
+        } else {
+            Some(concat.filter_graph(first_input)?)
+        };
+        self.inputs.extend(concat.into_inputs()?);
+
+        if let Some((graph, outputs)) = graph {
+            self.stream_maps.extend(outputs.iter().map(StreamMap::from));
+            self.filter_graph = Some(graph);
+        }
+        Ok(self)
+    }
+
+    /// Add global tags and chapters from an FFMETADATA file
+    ///
+    /// The file becomes an extra input whose tags and chapters are mapped to
+    /// every output that does not set its own with [`Output::map_metadata`]
+    /// or [`Output::map_chapters`]. Tags set with [`Output::metadata`] still
+    /// take precedence.
+    pub fn ffmetadata(mut self, metadata: FfMetadata) -> Result<Self> {
+        self.metadata_input = Some(self.inputs.len());
+        self.inputs.push(metadata.into_input()?);
+        Ok(self)
+    }
+
     /// Add an output
     pub fn output(mut self, output: Output) -> Self {
         self.outputs.push(output);
@@ -183,6 +253,9 @@ impl FFmpegBuilder {
     }
 
     /// Map streams from input to output
+    ///
+    /// These maps apply to every output that does not declare its own with
+    /// [`Output::map`].
     pub fn map(mut self, map: StreamMap) -> Self {
         self.stream_maps.push(map);
         self
@@ -198,13 +271,17 @@ impl FFmpegBuilder {
         self.map(StreamMap::specific(input_index, stream_spec))
     }
 
-    /// Add a video filter
+    /// Add a video filter applied to every output
+    ///
+    /// Use [`Output::video_filter`] for a chain that only applies to one output.
     pub fn video_filter(mut self, filter: VideoFilter) -> Self {
         self.video_filters.push(filter);
         self
     }
 
-    /// Add an audio filter
+    /// Add an audio filter applied to every output
+    ///
+    /// Use [`Output::audio_filter`] for a chain that only applies to one output.
     pub fn audio_filter(mut self, filter: AudioFilter) -> Self {
         self.audio_filters.push(filter);
         self
@@ -213,6 +290,17 @@ impl FFmpegBuilder {
     /// Set complex filter graph
     pub fn filter_complex(mut self, graph: impl Into<String>) -> Self {
         self.filter_complex = Some(graph.into());
+        self.filter_graph = None;
+        self
+    }
+
+    /// Set a typed complex filter graph
+    ///
+    /// The graph is validated, together with the inputs it references, when
+    /// the command is built.
+    pub fn filter_graph(mut self, graph: FilterGraph) -> Self {
+        self.filter_graph = Some(graph);
+        self.filter_complex = None;
         self
     }
 
@@ -236,6 +324,14 @@ impl FFmpegBuilder {
         self
     }
 
+    /// Skip checking output codecs against their containers
+    ///
+    /// For pairings FFmpeg accepts that [`crate::compat`] does not know.
+    pub fn skip_compat_check(mut self) -> Self {
+        self.skip_compat_check = true;
+        self
+    }
+
     /// Set time limit for encoding
     pub fn time_limit(mut self, duration: Duration) -> Self {
         self.time_limit = Some(duration);
@@ -281,15 +377,169 @@ impl FFmpegBuilder {
         self
     }
 
-    /// Validate the command
-    fn validate(&self) -> Result<()> {
+    /// Check the command without running it
+    ///
+    /// Besides inputs, outputs, the filter graph and maps, this checks each
+    /// output's codecs against its container (see [`crate::compat`]), so a
+    /// mismatch such as PCM audio in MP4 fails here rather than partway
+    /// through a job, unless [`FFmpegBuilder::skip_compat_check`] is set.
+    /// [`FFmpegBuilder::build_args`] and the run methods call this first.
+    pub fn validate(&self) -> Result<()> {
         if self.inputs.is_empty() {
             return Err(Error::InvalidArgument("No inputs specified".to_string()));
         }
         if self.outputs.is_empty() {
             return Err(Error::InvalidArgument("No outputs specified".to_string()));
         }
-        Ok(())
+        if let Some(ref graph) = self.filter_graph {
+            graph.validate()?;
+            if let Some(&index) = graph
+                .input_indices()
+                .iter()
+                .find(|&&i| i >= self.inputs.len())
+            {
+                return Err(Error::InvalidArgument(format!(
+                    "Filter graph reads input #{index} but there are only {} input(s)",
+                    self.inputs.len()
+                )));
+            }
+        }
+        for output in &self.outputs {
+            output.validate_attachments()?;
+        }
+        self.validate_maps()?;
+        if self.skip_compat_check {
+            return Ok(());
+        }
+        self.validate_codecs()
+    }
+
+    /// Check every output's codecs against its container
+    fn validate_codecs(&self) -> Result<()> {
+        let problems: Vec<_> = self
+            .outputs
+            .iter()
+            .flat_map(compat::output_problems)
+            .collect();
+        if problems.is_empty() {
+            return Ok(());
+        }
+
+        let mut error = ErrorBuilder::new("Codecs not supported by the output container");
+        for problem in problems {
+            error = error.detail(problem);
+        }
+        Err(error.build())
+    }
+
+    /// The filter graph and each output's maps, falling back to the shared
+    /// maps
+    ///
+    /// A graph output that the shared maps send to several outputs can only
+    /// be mapped once, so it is split into one copy per output.
+    fn output_maps(&self) -> (Option<FilterGraph>, Vec<Vec<StreamMap>>) {
+        let mut graph = self.filter_graph.clone();
+        let mut maps: Vec<Vec<StreamMap>> = self
+            .outputs
+            .iter()
+            .map(|output| {
+                if output.maps().is_empty() {
+                    self.stream_maps.clone()
+                } else {
+                    output.maps().to_vec()
+                }
+            })
+            .collect();
+
+        let sharing: Vec<usize> = (0..self.outputs.len())
+            .filter(|&i| self.outputs[i].maps().is_empty())
+            .collect();
+        if let Some(graph) = graph.as_mut()
+            && sharing.len() > 1
+        {
+            for (position, map) in self.stream_maps.iter().enumerate() {
+                let Some(label) = map.filter_label().filter(|_| !map.is_negative()) else {
+                    continue;
+                };
+                let Some(copies) = graph.split_output(label, sharing.len()) else {
+                    continue;
+                };
+                for (&output, copy) in sharing.iter().zip(copies) {
+                    maps[output][position] = StreamMap::from(copy);
+                }
+            }
+        }
+        (graph, maps)
+    }
+
+    /// Check that every map references an existing input or filter graph
+    /// output, that each filter graph output is mapped only once, and that
+    /// metadata is copied from existing inputs
+    fn validate_maps(&self) -> Result<()> {
+        let (graph, output_maps) = self.output_maps();
+        let labels = match (&graph, &self.filter_complex) {
+            (Some(graph), _) => graph.output_labels(),
+            (None, Some(complex)) => graph_output_labels(complex),
+            (None, None) => Vec::new(),
+        };
+        let mut used_labels: Vec<&str> = Vec::new();
+        let mut problems = Vec::new();
+
+        for (index, (output, maps)) in self.outputs.iter().zip(&output_maps).enumerate() {
+            for map in maps {
+                if let Some(input) = map.input_index() {
+                    if input >= self.inputs.len() {
+                        problems.push(format!(
+                            "output #{} maps '{}' but there are only {} input(s)",
+                            index,
+                            map,
+                            self.inputs.len()
+                        ));
+                    }
+                } else if let Some(label) = map.filter_label() {
+                    if map.is_negative() {
+                        problems.push(format!(
+                            "output #{index} excludes '[{label}]', but filter graph outputs cannot be excluded"
+                        ));
+                    } else if !labels.iter().any(|l| l == label) {
+                        problems.push(format!(
+                            "output #{index} maps '[{label}]' which is not an output of the filter graph"
+                        ));
+                    } else if used_labels.contains(&label) {
+                        problems.push(format!(
+                            "output #{index} maps '[{label}]' which is already mapped to another output"
+                        ));
+                    } else {
+                        used_labels.push(label);
+                    }
+                }
+            }
+
+            if output.cover_art_input().is_some() && maps.is_empty() {
+                problems.push(format!(
+                    "output #{index} embeds cover art from an extra input, so its other streams must be mapped explicitly"
+                ));
+            }
+
+            for input in output.metadata_inputs() {
+                if input >= self.inputs.len() {
+                    problems.push(format!(
+                        "output #{index} copies metadata from input #{input} but there are only {} input(s)",
+                        self.inputs.len()
+                    ));
+                }
+            }
+        }
+
+        if problems.is_empty() {
+            return Ok(());
+        }
+
+        let mut error = ErrorBuilder::new("Invalid stream mapping");
+        for problem in problems {
+            error = error.detail(problem);
+        }
+        Err(error.build())
     }
 
     /// Build the command line arguments
@@ -320,41 +570,31 @@ impl FFmpegBuilder {
         // Add global options
         cmd = cmd.args(self.global_options.clone().build());
 
-        // Input files
+        // Input files, then the cover art images of the outputs
         for input in &self.inputs {
             cmd = cmd.args(input.build_args());
         }
 
-        // Filters
-        if !self.video_filters.is_empty() {
-            let filter_str = self
-                .video_filters
-                .iter()
-                .map(|f| f.to_string())
-                .collect::<Vec<_>>()
-                .join(",");
-            cmd = cmd.option("-vf", filter_str);
-        }
-
-        if !self.audio_filters.is_empty() {
-            let filter_str = self
-                .audio_filters
-                .iter()
-                .map(|f| f.to_string())
-                .collect::<Vec<_>>()
-                .join(",");
-            cmd = cmd.option("-af", filter_str);
+        let mut cover_inputs = Vec::with_capacity(self.outputs.len());
+        let mut next_input = self.inputs.len();
+        for output in &self.outputs {
+            if let Some(image) = output.cover_art_input() {
+                cmd = cmd.args(Input::new(image.to_path_buf()).build_args());
+                cover_inputs.push(Some(next_input));
+                next_input += 1;
+            } else {
+                cover_inputs.push(None);
+            }
         }
 
-        if let Some(ref complex) = self.filter_complex {
+        // Filters
+        let (graph, output_maps) = self.output_maps();
+        if let Some(ref graph) = graph {
+            cmd = cmd.option("-filter_complex", graph.build());
+        } else if let Some(ref complex) = self.filter_complex {
             cmd = cmd.option("-filter_complex", complex);
         }
 
-        // Stream mappings
-        for map in &self.stream_maps {
-            cmd = cmd.option("-map", map.to_string());
-        }
-
         // Time and size limits
         if let Some(duration) = self.time_limit {
             cmd = cmd.option("-t", duration.to_ffmpeg_format());
@@ -367,17 +607,64 @@ impl FFmpegBuilder {
         // Raw arguments
         cmd = cmd.args(&self.raw_args);
 
-        // Output files
-        for output in &self.outputs {
+        // Output files, each carrying the shared maps and filter chains since
+        // FFmpeg only applies -map/-vf/-af to the output that follows them
+        for ((output, maps), cover_input) in self.outputs.iter().zip(&output_maps).zip(cover_inputs)
+        {
+            let mut output = output
+                .clone()
+                .with_default_maps(maps)
+                .with_default_metadata(self.metadata_input.map(MetadataSource::Input))
+                .with_shared_filters(&self.video_filters, &self.audio_filters);
+            if let Some(index) = cover_input {
+                output = output.with_cover_input(index);
+            }
             cmd = cmd.args(output.build_args());
         }
 
         Ok(cmd.build())
     }
 
+    /// Redirect atomic outputs to hidden files, to be published after a
+    /// successful run
+    fn stage_atomic_outputs(&mut self) -> Result<AtomicOutputs> {
+        self.validate()?;
+        let mut atomic = AtomicOutputs::new(self.overwrite);
+        for output in &mut self.outputs {
+            if output.is_atomic() {
+                *output = atomic.stage(output.clone())?;
+            }
+        }
+        // Without `-y` FFmpeg refuses to replace a file, and so does commit
+        if !self.overwrite
+            && let Some(path) = atomic.destinations().find(|path| path.exists())
+        {
+            return Err(Error::InvalidArgument(format!(
+                "Output already exists: {}",
+                path.display()
+            )));
+        }
+        Ok(atomic)
+    }
+
+    /// Write the files outputs need before FFmpeg opens them (e.g. HLS keys)
+    fn write_startup_files(&self) -> Result<()> {
+        for output in &self.outputs {
+            for file in output.startup_files() {
+                file.write()?;
+            }
+        }
+        Ok(())
+    }
+
     /// Run the FFmpeg command
-    pub async fn run(self) -> Result<ProcessOutput> {
+    ///
+    /// Atomic outputs are renamed into place once FFmpeg succeeds, and
+    /// removed otherwise.
+    pub async fn run(mut self) -> Result<ProcessOutput> {
+        let atomic = self.stage_atomic_outputs()?;
         let args = self.build_args()?;
+        self.write_startup_files()?;
         info!("Running FFmpeg with args: {:?}", args);
 
         let mut config = ProcessConfig::new(&self.executable)
@@ -400,12 +687,19 @@ impl FFmpegBuilder {
             }
         }
 
-        process.wait().await?.into_result()
+        let output = process.wait().await?.into_result()?;
+        atomic.commit()?;
+        Ok(output)
     }
 
     /// Run the command and return immediately with a process handle
-    pub async fn spawn(self) -> Result<FFmpegProcess> {
+    ///
+    /// Atomic outputs are published by [`FFmpegProcess::wait`] and removed
+    /// if the process fails, is killed or the handle is dropped.
+    pub async fn spawn(mut self) -> Result<FFmpegProcess> {
+        let atomic = self.stage_atomic_outputs()?;
         let args = self.build_args()?;
+        self.write_startup_files()?;
         info!("Spawning FFmpeg with args: {:?}", args);
 
         let mut config = ProcessConfig::new(&self.executable)
@@ -422,6 +716,17 @@ impl FFmpegBuilder {
         Ok(FFmpegProcess {
             process,
             progress_callback: self.progress_callback,
+            atomic,
+            _temp_files: self
+                .inputs
+                .iter()
+                .flat_map(|input| input.temp_files().iter().cloned())
+                .chain(
+                    self.outputs
+                        .iter()
+                        .flat_map(|output| output.temp_files().iter().cloned()),
+                )
+                .collect(),
         })
     }
 
@@ -446,6 +751,10 @@ impl Default for FFmpegBuilder {
 pub struct FFmpegProcess {
     process: Process,
     progress_callback: Option<Arc<dyn Fn(Progress) + Send + Sync>>,
+    /// Staged atomic outputs, published once the process succeeds
+    atomic: AtomicOutputs,
+    /// Temporary input and output files that must outlive the process
+    _temp_files: Vec<Arc<TempPath>>,
 }
 
 impl FFmpegProcess {
@@ -461,12 +770,16 @@ impl FFmpegProcess {
             }
         }
 
-        self.process.wait().await?.into_result()
+        let output = self.process.wait().await?.into_result()?;
+        self.atomic.commit()?;
+        Ok(output)
     }
 
-    /// Kill the process
+    /// Kill the process, removing the partial files of atomic outputs
     pub async fn kill(&mut self) -> Result<()> {
-        self.process.kill().await
+        self.process.kill().await?;
+        self.atomic = AtomicOutputs::default();
+        Ok(())
     }
 
     /// Get stdin handle for piping data
@@ -479,6 +792,13 @@ impl FFmpegProcess {
         self.process.stdout()
     }
 
+    /// Get stderr handle, e.g. to read log output as it is written
+    ///
+    /// Progress callbacks are not called once stderr has been taken.
+    pub fn stderr(&mut self) -> Option<tokio::process::ChildStderr> {
+        self.process.stderr()
+    }
+
     /// Try to wait without blocking
     pub fn try_wait(&mut self) -> Result<Option<std::process::ExitStatus>> {
         self.process.try_wait()
@@ -565,6 +885,287 @@ mod tests {
         assert!(args.contains(&"0:a".to_string()));
     }
 
+    #[test]
+    fn test_filters_per_output() {
+        let builder = FFmpegBuilder::with_executable("ffmpeg")
+            .input_path("input.mp4")
+            .output(Output::new("small.mp4").video_filter(VideoFilter::scale(640, 360)))
+            .output(Output::new("large.mp4"))
+            .video_filter(VideoFilter::deinterlace());
+
+        let args = builder.build_args().unwrap();
+        let chains: Vec<&String> = args
+            .iter()
+            .zip(args.iter().skip(1))
+            .filter(|(flag, _)| *flag == "-vf")
+            .map(|(_, chain)| chain)
+            .collect();
+        assert_eq!(chains, vec!["yadif,scale=w=640:h=360", "yadif"]);
+
+        let small = args.iter().position(|a| a == "small.mp4").unwrap();
+        let second_vf = args.iter().rposition(|a| a == "-vf").unwrap();
+        assert!(second_vf > small);
+    }
+
+    #[test]
+    fn test_maps_per_output() {
+        let builder = FFmpegBuilder::with_executable("ffmpeg")
+            .input_path("input.mov")
+            .filter_complex("[0:v]split=2[a][b];[a]scale=640:-2[proxy];[b]null[master]")
+            .output(
+                Output::new("proxy.mp4")
+                    .map_label("proxy")
+                    .map(StreamMap::audio_from(0)),
+            )
+            .output(
+                Output::new("master.mov")
+                    .map_label("[master]")
+                    .map(StreamMap::audio_from(0)),
+            )
+            .output(Output::new("audio.wav"))
+            .map(StreamMap::audio_from(0));
+
+        let args = builder.build_args().unwrap();
+        let maps: Vec<&String> = args
+            .iter()
+            .zip(args.iter().skip(1))
+            .filter(|(flag, _)| *flag == "-map")
+            .map(|(_, map)| map)
+            .collect();
+        assert_eq!(maps, vec!["[proxy]", "0:a", "[master]", "0:a", "0:a"]);
+
+        let proxy = args.iter().position(|a| a == "proxy.mp4").unwrap();
+        let master_map = args.iter().position(|a| a == "[master]").unwrap();
+        assert!(master_map > proxy);
+    }
+
+    #[test]
+    fn test_map_validation() {
+        let missing_input = FFmpegBuilder::with_executable("ffmpeg")
+            .input_path("input.mp4")
+            .output(Output::new("out.mp4").map(StreamMap::video_from(1)));
+        assert!(missing_input.build_args().is_err());
+
+        let missing_label = FFmpegBuilder::with_executable("ffmpeg")
+            .input_path("input.mp4")
+            .filter_complex("[0:v]scale=640:-2[small]")
+            .output(Output::new("out.mp4").map_label("big"));
+        assert!(missing_label.build_args().is_err());
+
+        let consumed_label = FFmpegBuilder::with_executable("ffmpeg")
+            .input_path("input.mp4")
+            .filter_complex("[0:v]scale=640:-2[small];[small]hflip[out]")
+            .output(Output::new("out.mp4").map_label("small"));
+        assert!(consumed_label.build_args().is_err());
+
+        let excluded_label = FFmpegBuilder::with_executable("ffmpeg")
+            .input_path("input.mp4")
+            .filter_complex("[0:v]scale=640:-2[small]")
+            .output(Output::new("out.mp4").map(StreamMap::label("small").exclude()));
+        assert!(excluded_label.build_args().is_err());
+
+        let twice = FFmpegBuilder::with_executable("ffmpeg")
+            .input_path("input.mp4")
+            .filter_complex("[0:v]scale=640:-2[small]")
+            .output(Output::new("a.mp4").map_label("small"))
+            .output(Output::new("b.mp4").map_label("small"));
+        assert!(twice.build_args().is_err());
+    }
+
+    #[test]
+    fn test_typed_filter_graph() {
+        use crate::filter::InputStream;
+
+        let mut graph = FilterGraph::new();
+        let scale = graph.chain(InputStream::video(0), VideoFilter::scale(1280, 720));
+        let vout = graph.output(scale.output(0), "vout");
+
+        let builder = FFmpegBuilder::with_executable("ffmpeg")
+            .input_path("input.mp4")
+            .filter_graph(graph.clone())
+            .output(
+                Output::new("out.mp4")
+                    .map(vout.into())
+                    .map(StreamMap::audio_from(0)),
+            );
+        let args = builder.build_args().unwrap();
+        let fc = args.iter().position(|a| a == "-filter_complex").unwrap();
+        assert_eq!(args[fc + 1], "[0:v]scale=w=1280:h=720[vout]");
+
+        let missing_input = FFmpegBuilder::with_executable("ffmpeg")
+            .input_path("input.mp4")
+            .filter_graph({
+                let mut graph = graph;
+                let extra = graph.chain(InputStream::video(1), VideoFilter::hflip());
+                graph.output(extra.output(0), "flipped");
+                graph
+            })
+            .output(Output::new("out.mp4").map_label("vout"));
+        assert!(missing_input.build_args().is_err());
+    }
+
+    #[test]
+    fn test_concat() {
+        let clips = ConcatInput::new().add_inputs(["intro.mp4", "main.mov"]);
+
+        let builder = FFmpegBuilder::with_executable("ffmpeg")
+            .input_path("music.mp3")
+            .concat(clips.clone().normalize_size(1920, 1080))
+            .unwrap()
+            .output_path("joined.mp4");
+        let args = builder.build_args().unwrap();
+        let fc = args.iter().position(|a| a == "-filter_complex").unwrap();
+        assert!(args[fc + 1].contains("[1:v]scale="));
+        assert!(args[fc + 1].ends_with("concat=n=2:v=1:a=1[concat_v][concat_a]"));
+        assert!(args.windows(2).any(|w| w == ["-map", "[concat_v]"]));
+        assert!(args.windows(2).any(|w| w == ["-map", "[concat_a]"]));
+
+        // Outputs sharing the joined streams each get their own copy
+        let builder = FFmpegBuilder::with_executable("ffmpeg")
+            .concat(clips.clone())
+            .unwrap()
+            .output_path("joined.mp4")
+            .output_path("joined.webm");
+        let args = builder.build_args().unwrap();
+        let fc = args.iter().position(|a| a == "-filter_complex").unwrap();
+        assert!(args[fc + 1].contains("split=outputs=2[concat_v_0][concat_v_1]"));
+        assert!(args[fc + 1].contains("asplit=outputs=2[concat_a_0][concat_a_1]"));
+        let mp4 = args.iter().position(|a| a == "joined.mp4").unwrap();
+        let maps = |args: &[String]| -> Vec<String> {
+            args.windows(2)
+                .filter(|w| w[0] == "-map")
+                .map(|w| w[1].clone())
+                .collect()
+        };
+        assert_eq!(maps(&args[..mp4]), ["[concat_v_0]", "[concat_a_0]"]);
+        assert_eq!(maps(&args[mp4..]), ["[concat_v_1]", "[concat_a_1]"]);
+
+        // Maps set before the clips are kept
+        let builder = FFmpegBuilder::with_executable("ffmpeg")
+            .input_path("subs.srt")
+            .map(StreamMap::subtitle_from(0))
+            .concat(clips.clone())
+            .unwrap()
+            .output_path("joined.mkv");
+        let args = builder.build_args().unwrap();
+        assert!(args.windows(2).any(|w| w == ["-map", "0:s"]));
+        assert!(args.windows(2).any(|w| w == ["-map", "[concat_v]"]));
+
+        let builder = FFmpegBuilder::with_executable("ffmpeg")
+            .concat(clips.clone().use_demuxer(true))
+            .unwrap()
+            .output(Output::new("joined.mp4").copy_codecs());
+        let args = builder.build_args().unwrap();
+        assert!(args.windows(2).any(|w| w == ["-f", "concat"]));
+        assert!(!args.contains(&"-filter_complex".to_string()));
+
+        assert!(
+            FFmpegBuilder::with_executable("ffmpeg")
+                .filter_complex("[0:v]null[v]")
+                .concat(clips)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_ffmetadata() {
+        use crate::metadata::{Chapter, FfMetadata, MetadataSource};
+
+        let metadata = FfMetadata::new().tag("title", "Book").chapter(Chapter::new(
+            Duration::from_secs(0),
+            Duration::from_secs(60),
+        ));
+        let builder = FFmpegBuilder::with_executable("ffmpeg")
+            .input_path("book.wav")
+            .ffmetadata(metadata)
+            .unwrap()
+            .output_path("book.m4a")
+            .output(
+                Output::new("sample.m4a")
+                    .map_metadata(MetadataSource::Input(0))
+                    .strip_chapters(),
+            );
+        let args = builder.build_args().unwrap();
+        assert!(args.windows(2).any(|w| w == ["-f", "ffmetadata"]));
+
+        let book = args.iter().position(|a| a == "book.m4a").unwrap();
+        assert!(args[..book].windows(2).any(|w| w == ["-map_metadata", "1"]));
+        assert!(args[..book].windows(2).any(|w| w == ["-map_chapters", "1"]));
+        assert!(args[book..].windows(2).any(|w| w == ["-map_metadata", "0"]));
+        assert!(
+            args[book..]
+                .windows(2)
+                .any(|w| w == ["-map_chapters", "-1"])
+        );
+
+        let builder = FFmpegBuilder::with_executable("ffmpeg")
+            .input_path("in.mp4")
+            .output(Output::new("out.mp4").map_chapters(MetadataSource::Input(3)));
+        assert!(builder.build_args().is_err());
+    }
+
+    #[test]
+    fn test_cover_art_input() {
+        let builder = FFmpegBuilder::with_executable("ffmpeg")
+            .input_path("master.wav")
+            .output(
+                Output::new("album.m4a")
+                    .map(StreamMap::audio_from(0))
+                    .cover_art("front.jpg"),
+            )
+            .output(Output::new("album.flac").cover_art("front.png"))
+            .output(Output::new("album.mka").cover_art("front.jpg"))
+            .map(StreamMap::audio_from(0));
+        let args = builder.build_args().unwrap();
+        let inputs: Vec<_> = args
+            .windows(2)
+            .filter(|w| w[0] == "-i")
+            .map(|w| w[1].as_str())
+            .collect();
+        assert_eq!(inputs, ["master.wav", "front.jpg", "front.png"]);
+
+        let m4a = args.iter().position(|a| a == "album.m4a").unwrap();
+        assert!(args[..m4a].windows(2).any(|w| w == ["-map", "1:v"]));
+        assert!(args[m4a..].windows(2).any(|w| w == ["-map", "2:v"]));
+        assert!(args.windows(2).any(|w| w == ["-attach", "front.jpg"]));
+
+        // Input 0 is not assumed to hold the audio
+        let unmapped = FFmpegBuilder::with_executable("ffmpeg")
+            .input_path("master.wav")
+            .output(Output::new("single.mp3").cover_art("front.jpg"));
+        let message = unmapped.build_args().unwrap_err().to_string();
+        assert!(message.contains("must be mapped explicitly"));
+    }
+
+    #[test]
+    fn test_codec_validation() {
+        use ffmpeg_common::Codec;
+
+        let builder = FFmpegBuilder::with_executable("ffmpeg")
+            .input_path("master.mov")
+            .output(
+                Output::new("web.mp4")
+                    .video_codec(Codec::h264())
+                    .audio_codec(Codec::pcm_s16le()),
+            )
+            .output(
+                Output::new("live.flv")
+                    .format("flv")
+                    .video_codec(Codec::vp9()),
+            )
+            .output(Output::new("archive.mkv").audio_codec(Codec::pcm_s16le()));
+        let message = builder.build_args().unwrap_err().to_string();
+        assert!(message.contains("web.mp4: mp4 cannot hold pcm_s16le audio; use aac"));
+        assert!(message.contains("live.flv: flv cannot hold vp9 video"));
+        assert!(!message.contains("archive.mkv"));
+        assert!(builder.skip_compat_check().build_args().is_ok());
+
+        let builder = FFmpegBuilder::with_executable("ffmpeg")
+            .input_path("master.mov")
+            .output(Output::new("web.mp4").copy_codecs());
+        assert!(builder.validate().is_ok());
+    }
+
     #[test]
     fn test_validation() {
         let builder = FFmpegBuilder::new().unwrap();
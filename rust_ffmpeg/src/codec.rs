@@ -152,11 +152,28 @@ impl CodecOptions {
     }
 
     /// Build command line arguments
-    pub fn build_args(&self, stream_type: &str) -> Vec<String> {
+    ///
+    /// `stream_spec` is the output stream specifier the options apply to. It
+    /// is either a bare stream type (`v`, `a`, `s`) or a full specifier such as
+    /// `a:1`, in which case every option is addressed to that stream only
+    /// (`-c:a:1`, `-b:a:1`, `-ar:a:1`, ...).
+    pub fn build_args(&self, stream_spec: &str) -> Vec<String> {
         let mut cmd = CommandBuilder::new();
 
+        let stream_type = stream_spec.split(':').next().unwrap_or_default();
+        // Options that FFmpeg applies to the whole output are only qualified
+        // when a specific stream is targeted, so bare `v`/`a` keep the
+        // familiar `-pix_fmt`/`-ar` forms.
+        let per_stream = |option: &str| {
+            if stream_spec == stream_type {
+                option.to_string()
+            } else {
+                format!("{option}:{stream_spec}")
+            }
+        };
+
         // Codec
-        cmd = cmd.option(format!("-c:{}", stream_type), self.codec.as_str());
+        cmd = cmd.option(format!("-c:{stream_spec}"), self.codec.as_str());
 
         // Skip other options for copy codec
         if self.codec.as_str() == "copy" {
@@ -165,17 +182,17 @@ impl CodecOptions {
 
         // Bitrate
         if let Some(ref bitrate) = self.bitrate {
-            cmd = cmd.option(format!("-b:{}", stream_type), bitrate);
+            cmd = cmd.option(format!("-b:{stream_spec}"), bitrate);
         }
 
         // Quality
         if let Some(quality) = self.quality {
             match self.codec.as_str() {
                 "libx264" | "h264" | "libx265" | "hevc" | "libvpx" | "libvpx-vp9" => {
-                    cmd = cmd.option("-crf", quality);
+                    cmd = cmd.option(per_stream("-crf"), quality);
                 }
                 _ => {
-                    cmd = cmd.option(format!("-q:{}", stream_type), quality);
+                    cmd = cmd.option(format!("-q:{stream_spec}"), quality);
                 }
             }
         }
@@ -183,65 +200,74 @@ impl CodecOptions {
         // Video options
         if stream_type == "v" {
             if let Some(ref pix_fmt) = self.pixel_format {
-                cmd = cmd.option("-pix_fmt", pix_fmt.as_str());
+                cmd = cmd.option(per_stream("-pix_fmt"), pix_fmt.as_str());
             }
 
             if let Some(fps) = self.framerate {
-                cmd = cmd.option("-r", fps);
+                cmd = cmd.option(per_stream("-r"), fps);
             }
 
             if let Some((width, height)) = self.size {
-                cmd = cmd.option("-s", format!("{}x{}", width, height));
+                cmd = cmd.option(per_stream("-s"), format!("{}x{}", width, height));
             }
 
             if let Some(gop) = self.gop_size {
-                cmd = cmd.option("-g", gop);
+                cmd = cmd.option(per_stream("-g"), gop);
             }
 
             if let Some(bf) = self.b_frames {
-                cmd = cmd.option("-bf", bf);
+                cmd = cmd.option(per_stream("-bf"), bf);
             }
 
             if let Some(refs) = self.ref_frames {
-                cmd = cmd.option("-refs", refs);
+                cmd = cmd.option(per_stream("-refs"), refs);
             }
         }
 
         // Audio options
         if stream_type == "a" {
             if let Some(ref sample_fmt) = self.sample_format {
-                cmd = cmd.option("-sample_fmt", sample_fmt.as_str());
+                cmd = cmd.option(per_stream("-sample_fmt"), sample_fmt.as_str());
             }
 
             if let Some(rate) = self.sample_rate {
-                cmd = cmd.option("-ar", rate);
+                cmd = cmd.option(per_stream("-ar"), rate);
             }
 
             if let Some(channels) = self.channels {
-                cmd = cmd.option("-ac", channels);
+                cmd = cmd.option(per_stream("-ac"), channels);
             }
 
             if let Some(ref layout) = self.channel_layout {
-                cmd = cmd.option("-channel_layout", layout);
+                cmd = cmd.option(per_stream("-channel_layout"), layout);
             }
         }
 
         // Profile and level
         if let Some(ref profile) = self.profile {
-            cmd = cmd.option("-profile:v", profile);
+            cmd = cmd.option(format!("-profile:{stream_spec}"), profile);
         }
 
         if let Some(ref level) = self.level {
-            cmd = cmd.option("-level", level);
+            cmd = cmd.option(per_stream("-level"), level);
         }
 
-        // Custom options
+        // Custom options (keys that already carry a specifier are kept as-is)
         for (key, value) in &self.options {
-            cmd = cmd.option(format!("-{}", key), value);
+            if key.contains(':') {
+                cmd = cmd.option(format!("-{}", key), value);
+            } else {
+                cmd = cmd.option(per_stream(&format!("-{}", key)), value);
+            }
         }
 
         cmd.build()
     }
+
+    /// Get the codec
+    pub fn codec(&self) -> &Codec {
+        &self.codec
+    }
 }
 
 /// Preset codec configurations
@@ -532,4 +558,22 @@ mod tests {
         assert!(args.contains(&"-preset".to_string()));
         assert!(args.contains(&"p4".to_string()));
     }
+
+    #[test]
+    fn test_per_stream_specifier() {
+        let aac = audio::aac_high_quality();
+        let args = aac.build_args("a:1");
+        assert!(args.contains(&"-c:a:1".to_string()));
+        assert!(args.contains(&"-b:a:1".to_string()));
+        assert!(args.contains(&"-ar:a:1".to_string()));
+        assert!(args.contains(&"-profile:a:1".to_string()));
+        assert!(!args.contains(&"-profile:v".to_string()));
+
+        let args = aac.build_args("a");
+        assert!(args.contains(&"-ar".to_string()));
+        assert!(args.contains(&"-profile:a".to_string()));
+
+        let copy = CodecOptions::new(Codec::copy()).bitrate("1M");
+        assert_eq!(copy.build_args("s:0"), vec!["-c:s:0", "copy"]);
+    }
 }
@@ -0,0 +1,407 @@
+//! Codec and container compatibility checks
+//!
+//! Muxers reject some codecs only once the first packets are written, which
+//! can be well into a job. [`check_codec`] compares an encoder against a
+//! table of common containers so [`FFmpegBuilder::validate`](crate::FFmpegBuilder::validate)
+//! can fail before FFmpeg is started, with a suggested fix. Containers and
+//! encoders missing from the table are not checked, and neither are streams
+//! that are copied.
+//!
+//! ```
+//! use rust_ffmpeg::compat;
+//! use ffmpeg_common::StreamType;
+//!
+//! assert!(compat::check_codec("mp4", StreamType::Audio, "libfdk_aac").is_ok());
+//!
+//! let error = compat::check_codec("mp4", StreamType::Subtitle, "srt").unwrap_err();
+//! assert!(error.to_string().contains("use mov_text for subtitles in mp4"));
+//! ```
+
+use ffmpeg_common::{Error, Result, StreamType};
+
+use crate::output::Output;
+
+/// Codecs a stream type may use in a container, with the fix to suggest
+struct Allowed {
+    codecs: &'static [&'static str],
+    suggestion: &'static str,
+}
+
+/// Nothing of this type can be muxed
+const NONE: Allowed = Allowed {
+    codecs: &[],
+    suggestion: "",
+};
+
+/// Compatibility of one or more muxers; `None` leaves a type unchecked
+struct Container {
+    formats: &'static [&'static str],
+    video: Option<Allowed>,
+    audio: Option<Allowed>,
+    subtitle: Option<Allowed>,
+}
+
+/// Cover art codecs accepted by audio-only containers
+const COVER_ART: Allowed = Allowed {
+    codecs: &["mjpeg", "png"],
+    suggestion: "only JPEG or PNG cover art can be embedded",
+};
+
+const MP4_VIDEO: &[&str] = &[
+    "h264",
+    "hevc",
+    "av1",
+    "vp9",
+    "mpeg4",
+    "mpeg2video",
+    "mpeg1video",
+    "vc1",
+    "mjpeg",
+    "png",
+];
+const MP4_AUDIO: &[&str] = &[
+    "aac", "mp3", "mp2", "ac3", "eac3", "dts", "truehd", "opus", "flac", "alac",
+];
+
+const CONTAINERS: &[Container] = &[
+    Container {
+        formats: &["mp4", "ipod", "3gp"],
+        video: Some(Allowed {
+            codecs: MP4_VIDEO,
+            suggestion: "use h264 (libx264) or hevc (libx265)",
+        }),
+        audio: Some(Allowed {
+            codecs: MP4_AUDIO,
+            suggestion: "use aac",
+        }),
+        subtitle: Some(Allowed {
+            codecs: &["mov_text"],
+            suggestion: "use mov_text",
+        }),
+    },
+    Container {
+        formats: &["mov"],
+        video: Some(Allowed {
+            codecs: &[
+                "h264",
+                "hevc",
+                "av1",
+                "vp9",
+                "mpeg4",
+                "mpeg2video",
+                "mjpeg",
+                "png",
+                "prores",
+                "dnxhd",
+                "qtrle",
+            ],
+            suggestion: "use h264 (libx264) or prores (prores_ks)",
+        }),
+        audio: None,
+        subtitle: Some(Allowed {
+            codecs: &["mov_text"],
+            suggestion: "use mov_text",
+        }),
+    },
+    Container {
+        formats: &["webm"],
+        video: Some(Allowed {
+            codecs: &["vp8", "vp9", "av1"],
+            suggestion: "use vp9 (libvpx-vp9) or av1 (libsvtav1)",
+        }),
+        audio: Some(Allowed {
+            codecs: &["opus", "vorbis"],
+            suggestion: "use opus (libopus)",
+        }),
+        subtitle: Some(Allowed {
+            codecs: &["webvtt"],
+            suggestion: "use webvtt",
+        }),
+    },
+    Container {
+        formats: &["matroska"],
+        video: None,
+        audio: None,
+        subtitle: Some(Allowed {
+            codecs: &[
+                "subrip",
+                "ass",
+                "webvtt",
+                "hdmv_pgs_subtitle",
+                "dvd_subtitle",
+                "dvb_subtitle",
+            ],
+            suggestion: "use srt or ass",
+        }),
+    },
+    Container {
+        formats: &["flv"],
+        video: Some(Allowed {
+            codecs: &["h264", "flv1"],
+            suggestion: "use h264 (libx264)",
+        }),
+        audio: Some(Allowed {
+            codecs: &["aac", "mp3"],
+            suggestion: "use aac",
+        }),
+        subtitle: Some(NONE),
+    },
+    Container {
+        formats: &["mpegts"],
+        video: Some(Allowed {
+            codecs: &[
+                "h264",
+                "hevc",
+                "av1",
+                "mpeg4",
+                "mpeg2video",
+                "mpeg1video",
+                "vc1",
+            ],
+            suggestion: "use h264 (libx264) or hevc (libx265)",
+        }),
+        audio: Some(Allowed {
+            codecs: &["aac", "mp3", "mp2", "ac3", "eac3", "dts", "truehd", "opus"],
+            suggestion: "use aac",
+        }),
+        subtitle: Some(Allowed {
+            codecs: &["dvb_subtitle", "dvb_teletext"],
+            suggestion: "burn text subtitles in or use dvbsub",
+        }),
+    },
+    Container {
+        formats: &["mp3"],
+        video: Some(COVER_ART),
+        audio: Some(Allowed {
+            codecs: &["mp3"],
+            suggestion: "use mp3 (libmp3lame)",
+        }),
+        subtitle: Some(NONE),
+    },
+    Container {
+        formats: &["flac"],
+        video: Some(COVER_ART),
+        audio: Some(Allowed {
+            codecs: &["flac"],
+            suggestion: "use flac",
+        }),
+        subtitle: Some(NONE),
+    },
+    Container {
+        formats: &["ogg"],
+        video: Some(Allowed {
+            codecs: &["theora", "vp8"],
+            suggestion: "use theora (libtheora) or webm",
+        }),
+        audio: Some(Allowed {
+            codecs: &["vorbis", "opus", "flac", "speex"],
+            suggestion: "use vorbis (libvorbis) or opus (libopus)",
+        }),
+        subtitle: Some(NONE),
+    },
+    Container {
+        formats: &["opus"],
+        video: Some(NONE),
+        audio: Some(Allowed {
+            codecs: &["opus"],
+            suggestion: "use opus (libopus)",
+        }),
+        subtitle: Some(NONE),
+    },
+    Container {
+        formats: &["aac", "adts"],
+        video: Some(NONE),
+        audio: Some(Allowed {
+            codecs: &["aac"],
+            suggestion: "use aac",
+        }),
+        subtitle: Some(NONE),
+    },
+    Container {
+        formats: &["wav"],
+        video: Some(NONE),
+        audio: Some(Allowed {
+            codecs: &[
+                "pcm_s16le",
+                "pcm_s24le",
+                "pcm_s32le",
+                "pcm_f32le",
+                "pcm_f64le",
+                "pcm_u8",
+                "pcm_alaw",
+                "pcm_mulaw",
+            ],
+            suggestion: "use pcm_s16le or pcm_s24le",
+        }),
+        subtitle: Some(NONE),
+    },
+];
+
+/// Suffixes of hardware encoders, which are named `<codec>_<api>`
+const HARDWARE_APIS: [&str; 8] = [
+    "nvenc",
+    "qsv",
+    "vaapi",
+    "videotoolbox",
+    "amf",
+    "v4l2m2m",
+    "mf",
+    "vulkan",
+];
+
+/// Codec produced by an encoder, e.g. `hevc` for `libx265` or `hevc_nvenc`
+pub fn codec_id(encoder: &str) -> &str {
+    let name = HARDWARE_APIS
+        .iter()
+        .find_map(|api| encoder.strip_suffix(api)?.strip_suffix('_'))
+        .unwrap_or(encoder);
+    match name {
+        "libx264" | "libopenh264" => "h264",
+        "h265" | "libx265" => "hevc",
+        "libvpx" => "vp8",
+        "libvpx-vp9" => "vp9",
+        "libaom-av1" | "libsvtav1" | "librav1e" => "av1",
+        "libxvid" => "mpeg4",
+        "libtheora" => "theora",
+        "prores_ks" | "prores_aw" => "prores",
+        "libfdk_aac" | "aac_at" => "aac",
+        "libmp3lame" | "libshine" => "mp3",
+        "libopus" => "opus",
+        "libvorbis" => "vorbis",
+        "srt" => "subrip",
+        "ssa" => "ass",
+        "dvdsub" => "dvd_subtitle",
+        "dvbsub" => "dvb_subtitle",
+        other => other,
+    }
+}
+
+/// Codecs a muxer accepts for a stream type, or `None` when the pair is not
+/// in the table
+fn allowed(format: &str, stream_type: StreamType) -> Option<&'static Allowed> {
+    let container = CONTAINERS.iter().find(|c| c.formats.contains(&format))?;
+    match stream_type {
+        StreamType::Video | StreamType::VideoNoAttached => container.video.as_ref(),
+        StreamType::Audio => container.audio.as_ref(),
+        StreamType::Subtitle => container.subtitle.as_ref(),
+        StreamType::Data | StreamType::Attachment => None,
+    }
+}
+
+fn type_name(stream_type: StreamType) -> &'static str {
+    match stream_type {
+        StreamType::Video | StreamType::VideoNoAttached => "video",
+        StreamType::Audio => "audio",
+        StreamType::Subtitle => "subtitles",
+        StreamType::Data => "data",
+        StreamType::Attachment => "attachments",
+    }
+}
+
+/// Check that muxer `format` accepts what `encoder` produces for a stream
+/// of `stream_type`
+///
+/// `copy` and pairs missing from the table are accepted.
+pub fn check_codec(format: &str, stream_type: StreamType, encoder: &str) -> Result<()> {
+    if encoder == "copy" {
+        return Ok(());
+    }
+    let Some(allowed) = allowed(format, stream_type) else {
+        return Ok(());
+    };
+    let codec = codec_id(encoder);
+    if allowed.codecs.contains(&codec) {
+        return Ok(());
+    }
+
+    let kind = type_name(stream_type);
+    Err(Error::Unsupported(if allowed.codecs.is_empty() {
+        format!("{format} cannot hold {kind}; remove the {kind} stream or use mkv")
+    } else {
+        format!(
+            "{format} cannot hold {codec} {kind}; {} for {kind} in {format}",
+            allowed.suggestion
+        )
+    }))
+}
+
+/// Problems with the codecs chosen for `output`, one line each
+///
+/// Outputs whose container cannot be determined are not checked.
+pub fn output_problems(output: &Output) -> Vec<String> {
+    let Some(format) = output.container_format() else {
+        return Vec::new();
+    };
+    output
+        .codecs()
+        .into_iter()
+        .filter_map(|(stream_type, codec)| check_codec(&format, stream_type, codec.as_str()).err())
+        .map(|error| match error {
+            Error::Unsupported(message) => format!("{}: {message}", output.destination()),
+            other => other.to_string(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ffmpeg_common::{Codec, StreamSpecifier};
+
+    use crate::codec::CodecOptions;
+
+    #[test]
+    fn test_codec_id() {
+        assert_eq!(codec_id("libx264"), "h264");
+        assert_eq!(codec_id("hevc_nvenc"), "hevc");
+        assert_eq!(codec_id("av1_qsv"), "av1");
+        assert_eq!(codec_id("libsvtav1"), "av1");
+        assert_eq!(codec_id("srt"), "subrip");
+        assert_eq!(codec_id("pcm_s24le"), "pcm_s24le");
+    }
+
+    #[test]
+    fn test_check_codec() {
+        assert!(check_codec("mp4", StreamType::Video, "h264_videotoolbox").is_ok());
+        assert!(check_codec("matroska", StreamType::Audio, "pcm_s16le").is_ok());
+        assert!(check_codec("mov", StreamType::Audio, "pcm_s24le").is_ok());
+        assert!(check_codec("flv", StreamType::Video, "copy").is_ok());
+        assert!(check_codec("avi", StreamType::Video, "vp9").is_ok());
+        assert!(check_codec("mpegts", StreamType::Video, "libxvid").is_ok());
+        assert!(check_codec("mpegts", StreamType::Audio, "truehd").is_ok());
+        assert!(check_codec("mp4", StreamType::Audio, "mp2").is_ok());
+        assert!(check_codec("mp4", StreamType::Audio, "dts").is_ok());
+
+        let message = check_codec("mp4", StreamType::Audio, "pcm_s16le")
+            .unwrap_err()
+            .to_string();
+        assert!(message.contains("mp4 cannot hold pcm_s16le audio; use aac"));
+        let message = check_codec("flv", StreamType::Video, "libvpx-vp9")
+            .unwrap_err()
+            .to_string();
+        assert!(message.contains("use h264 (libx264) for video in flv"));
+        let message = check_codec("wav", StreamType::Video, "mjpeg")
+            .unwrap_err()
+            .to_string();
+        assert!(message.contains("wav cannot hold video"));
+        assert!(check_codec("matroska", StreamType::Subtitle, "mov_text").is_err());
+    }
+
+    #[test]
+    fn test_output_problems() {
+        let output = Output::new("clip.mp4")
+            .video_codec(Codec::new("libx264"))
+            .audio_codec(Codec::pcm_s16le())
+            .subtitle_codec(Codec::new("srt"))
+            .stream_codec_opts(
+                StreamSpecifier::TypeIndex(StreamType::Audio, 1),
+                CodecOptions::new(Codec::new("libopus")),
+            );
+        let problems = output_problems(&output);
+        assert_eq!(problems.len(), 2);
+        assert!(problems[0].starts_with("clip.mp4: "));
+        assert!(problems[1].contains("use mov_text for subtitles in mp4"));
+
+        assert!(output_problems(&Output::new("pipe:1").audio_codec(Codec::pcm_s16le())).is_empty());
+    }
+}
@@ -0,0 +1,783 @@
+//! DASH packaging with explicit adaptation sets
+//!
+//! A [`DashPackage`] maps its representations into a single `dash` muxer
+//! output, in the order they were added, and groups them into adaptation
+//! sets by media type or by representation index.
+//!
+//! ```no_run
+//! use rust_ffmpeg::dash::{AdaptationSet, DashPackage, DashStream};
+//! use rust_ffmpeg::{CodecOptions, FFmpegBuilder, Input, StreamMap};
+//! use ffmpeg_common::Codec;
+//!
+//! # async fn example() -> ffmpeg_common::Result<()> {
+//! let output = DashPackage::new("out/manifest.mpd")
+//!     .stream(DashStream::video(StreamMap::video_from(0))
+//!         .codec(CodecOptions::new(Codec::h264()).bitrate("5000k").size(1920, 1080)))
+//!     .stream(DashStream::video(StreamMap::video_from(0))
+//!         .codec(CodecOptions::new(Codec::h264()).bitrate("2800k").size(1280, 720)))
+//!     .stream(DashStream::audio(StreamMap::audio_from(0)).language("en"))
+//!     .adaptation_set(AdaptationSet::video(0))
+//!     .adaptation_set(AdaptationSet::audio(1))
+//!     .segment_duration(4.0)
+//!     .into_output()?;
+//!
+//! FFmpegBuilder::new()?
+//!     .input(Input::new("master.mov"))
+//!     .output(output)
+//!     .run()
+//!     .await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use ffmpeg_common::{Error, MediaPath, Result, StreamSpecifier, StreamType};
+use std::collections::HashSet;
+use std::fmt::Write as _;
+use std::path::PathBuf;
+
+use crate::codec::CodecOptions;
+use crate::output::Output;
+use crate::stream::StreamMap;
+
+/// Segment container
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DashSegmentType {
+    /// Chosen from the codecs (WebM for VP8/VP9/Vorbis/Opus, MP4 otherwise)
+    #[default]
+    Auto,
+    /// ISO BMFF segments
+    Mp4,
+    /// WebM segments
+    Webm,
+}
+
+impl DashSegmentType {
+    /// Value of `-dash_segment_type`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Auto => "auto",
+            Self::Mp4 => "mp4",
+            Self::Webm => "webm",
+        }
+    }
+}
+
+/// How segments are split into fragments
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FragmentType {
+    /// One fragment per segment
+    None,
+    /// One fragment per frame
+    EveryFrame,
+    /// Fragments of the fragment duration
+    Duration,
+    /// Fragments at P-frames (video only)
+    PFrames,
+}
+
+impl FragmentType {
+    /// Value of `frag_type`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::None => "none",
+            Self::EveryFrame => "every_frame",
+            Self::Duration => "duration",
+            Self::PFrames => "pframes",
+        }
+    }
+}
+
+/// Representation mapped into the manifest
+#[derive(Debug, Clone)]
+pub struct DashStream {
+    kind: StreamType,
+    source: StreamMap,
+    codec: Option<CodecOptions>,
+    language: Option<String>,
+}
+
+impl DashStream {
+    fn new(kind: StreamType, source: StreamMap) -> Self {
+        Self {
+            kind,
+            source,
+            codec: None,
+            language: None,
+        }
+    }
+
+    /// Video representation from a single video stream
+    pub fn video(source: StreamMap) -> Self {
+        Self::new(StreamType::Video, source)
+    }
+
+    /// Audio representation from a single audio stream
+    pub fn audio(source: StreamMap) -> Self {
+        Self::new(StreamType::Audio, source)
+    }
+
+    /// Subtitle representation from a single subtitle stream
+    pub fn subtitle(source: StreamMap) -> Self {
+        Self::new(StreamType::Subtitle, source)
+    }
+
+    /// Encoder settings
+    pub fn codec(mut self, codec: CodecOptions) -> Self {
+        self.codec = Some(codec);
+        self
+    }
+
+    /// Language written to the adaptation set (`lang` attribute)
+    pub fn language(mut self, language: impl Into<String>) -> Self {
+        self.language = Some(language.into());
+        self
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum SetStreams {
+    Type(StreamType),
+    Indices(Vec<usize>),
+}
+
+/// Adaptation set of the manifest
+#[derive(Debug, Clone)]
+pub struct AdaptationSet {
+    id: u32,
+    streams: SetStreams,
+    segment_duration: Option<f64>,
+    fragment_duration: Option<f64>,
+    fragment_type: Option<FragmentType>,
+}
+
+impl AdaptationSet {
+    fn new(id: u32, streams: SetStreams) -> Self {
+        Self {
+            id,
+            streams,
+            segment_duration: None,
+            fragment_duration: None,
+            fragment_type: None,
+        }
+    }
+
+    /// Set holding every video representation
+    pub fn video(id: u32) -> Self {
+        Self::new(id, SetStreams::Type(StreamType::Video))
+    }
+
+    /// Set holding every audio representation
+    pub fn audio(id: u32) -> Self {
+        Self::new(id, SetStreams::Type(StreamType::Audio))
+    }
+
+    /// Set holding the representations at the given indices (in the order
+    /// they were added to the package)
+    pub fn streams(id: u32, indices: impl IntoIterator<Item = usize>) -> Self {
+        Self::new(id, SetStreams::Indices(indices.into_iter().collect()))
+    }
+
+    /// Segment duration for this set, in seconds
+    pub fn segment_duration(mut self, seconds: f64) -> Self {
+        self.segment_duration = Some(seconds);
+        self
+    }
+
+    /// Fragment duration for this set, in seconds
+    pub fn fragment_duration(mut self, seconds: f64) -> Self {
+        self.fragment_duration = Some(seconds);
+        self
+    }
+
+    /// Fragmentation mode for this set
+    pub fn fragment_type(mut self, fragment_type: FragmentType) -> Self {
+        self.fragment_type = Some(fragment_type);
+        self
+    }
+
+    /// Entry of the `adaptation_sets` option; `streams` must come last
+    fn to_option(&self) -> String {
+        let mut entry = format!("id={}", self.id);
+        if let Some(seconds) = self.segment_duration {
+            let _ = write!(entry, ",seg_duration={seconds}");
+        }
+        if let Some(seconds) = self.fragment_duration {
+            let _ = write!(entry, ",frag_duration={seconds}");
+        }
+        if let Some(fragment_type) = self.fragment_type {
+            let _ = write!(entry, ",frag_type={}", fragment_type.as_str());
+        }
+        match &self.streams {
+            SetStreams::Type(kind) => {
+                let _ = write!(entry, ",streams={}", kind.as_str());
+            }
+            SetStreams::Indices(indices) => {
+                let indices: Vec<_> = indices.iter().map(ToString::to_string).collect();
+                let _ = write!(entry, ",streams={}", indices.join(","));
+            }
+        }
+        entry
+    }
+}
+
+/// Low-latency (CMAF chunked) settings
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LowLatency {
+    /// Target latency advertised to players, in seconds
+    pub target_latency: f64,
+    /// Slowest playback rate players may use to catch up
+    pub min_playback_rate: f64,
+    /// Fastest playback rate players may use to catch up
+    pub max_playback_rate: f64,
+}
+
+impl Default for LowLatency {
+    fn default() -> Self {
+        Self {
+            target_latency: 3.0,
+            min_playback_rate: 0.96,
+            max_playback_rate: 1.04,
+        }
+    }
+}
+
+/// DASH package
+#[derive(Debug, Clone)]
+pub struct DashPackage {
+    manifest: PathBuf,
+    streams: Vec<DashStream>,
+    adaptation_sets: Vec<AdaptationSet>,
+    segment_type: DashSegmentType,
+    segment_duration: Option<f64>,
+    fragment_duration: Option<f64>,
+    fragment_type: Option<FragmentType>,
+    init_template: Option<String>,
+    media_template: Option<String>,
+    single_file: bool,
+    single_file_name: Option<String>,
+    use_template: Option<bool>,
+    use_timeline: Option<bool>,
+    window_size: Option<u32>,
+    extra_window_size: Option<u32>,
+    remove_at_exit: bool,
+    utc_timing_url: Option<String>,
+    low_latency: Option<LowLatency>,
+}
+
+impl DashPackage {
+    /// Create a package whose manifest is written to `manifest`; segments
+    /// are written next to it
+    pub fn new(manifest: impl Into<PathBuf>) -> Self {
+        Self {
+            manifest: manifest.into(),
+            streams: Vec::new(),
+            adaptation_sets: Vec::new(),
+            segment_type: DashSegmentType::default(),
+            segment_duration: None,
+            fragment_duration: None,
+            fragment_type: None,
+            init_template: None,
+            media_template: None,
+            single_file: false,
+            single_file_name: None,
+            use_template: None,
+            use_timeline: None,
+            window_size: None,
+            extra_window_size: None,
+            remove_at_exit: false,
+            utc_timing_url: None,
+            low_latency: None,
+        }
+    }
+
+    /// Add a representation
+    pub fn stream(mut self, stream: DashStream) -> Self {
+        self.streams.push(stream);
+        self
+    }
+
+    /// Add an adaptation set
+    pub fn adaptation_set(mut self, set: AdaptationSet) -> Self {
+        self.adaptation_sets.push(set);
+        self
+    }
+
+    /// Segment container
+    pub fn segment_type(mut self, segment_type: DashSegmentType) -> Self {
+        self.segment_type = segment_type;
+        self
+    }
+
+    /// Default segment duration, in seconds
+    pub fn segment_duration(mut self, seconds: f64) -> Self {
+        self.segment_duration = Some(seconds);
+        self
+    }
+
+    /// Default fragment duration, in seconds
+    pub fn fragment_duration(mut self, seconds: f64) -> Self {
+        self.fragment_duration = Some(seconds);
+        self
+    }
+
+    /// Default fragmentation mode
+    pub fn fragment_type(mut self, fragment_type: FragmentType) -> Self {
+        self.fragment_type = Some(fragment_type);
+        self
+    }
+
+    /// Init segment template (`init-stream$RepresentationID$.$ext$` by default)
+    pub fn init_segment_template(mut self, template: impl Into<String>) -> Self {
+        self.init_template = Some(template.into());
+        self
+    }
+
+    /// Media segment template
+    /// (`chunk-stream$RepresentationID$-$Number%05d$.$ext$` by default)
+    pub fn media_segment_template(mut self, template: impl Into<String>) -> Self {
+        self.media_template = Some(template.into());
+        self
+    }
+
+    /// Write each representation to one file addressed by byte ranges
+    pub fn single_file(mut self) -> Self {
+        self.single_file = true;
+        self
+    }
+
+    /// Single-file mode with a file name template
+    /// (e.g. `$RepresentationID$.$ext$`)
+    pub fn single_file_name(mut self, template: impl Into<String>) -> Self {
+        self.single_file = true;
+        self.single_file_name = Some(template.into());
+        self
+    }
+
+    /// Use `SegmentTemplate` instead of `SegmentList`
+    pub fn use_template(mut self, enable: bool) -> Self {
+        self.use_template = Some(enable);
+        self
+    }
+
+    /// Use `SegmentTimeline` in the segment template
+    pub fn use_timeline(mut self, enable: bool) -> Self {
+        self.use_timeline = Some(enable);
+        self
+    }
+
+    /// Number of segments kept in a live manifest
+    pub fn window_size(mut self, segments: u32) -> Self {
+        self.window_size = Some(segments);
+        self
+    }
+
+    /// Number of segments kept on disk after leaving the live manifest
+    pub fn extra_window_size(mut self, segments: u32) -> Self {
+        self.extra_window_size = Some(segments);
+        self
+    }
+
+    /// Remove all segments and the manifest when FFmpeg exits
+    pub fn remove_at_exit(mut self, enable: bool) -> Self {
+        self.remove_at_exit = enable;
+        self
+    }
+
+    /// Time source players synchronize live streams against
+    /// (`UTCTiming` with the `http-xsdate` scheme)
+    pub fn utc_timing_url(mut self, url: impl Into<String>) -> Self {
+        self.utc_timing_url = Some(url.into());
+        self
+    }
+
+    /// Enable low-latency DASH with chunked CMAF segments
+    pub fn low_latency(mut self, settings: LowLatency) -> Self {
+        self.low_latency = Some(settings);
+        self
+    }
+
+    /// The `adaptation_sets` value
+    pub fn adaptation_sets_option(&self) -> String {
+        self.adaptation_sets
+            .iter()
+            .map(AdaptationSet::to_option)
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    fn validate(&self) -> Result<()> {
+        if self.streams.is_empty() {
+            return Err(Error::InvalidArgument(
+                "DASH package needs at least one stream".to_string(),
+            ));
+        }
+
+        let durations = [self.segment_duration, self.fragment_duration]
+            .into_iter()
+            .chain(
+                self.adaptation_sets
+                    .iter()
+                    .flat_map(|set| [set.segment_duration, set.fragment_duration]),
+            )
+            .flatten();
+        for seconds in durations {
+            if !seconds.is_finite() || seconds <= 0.0 {
+                return Err(Error::InvalidArgument(format!(
+                    "DASH durations must be positive, got {seconds}"
+                )));
+            }
+        }
+
+        if !self.adaptation_sets.is_empty() {
+            self.validate_adaptation_sets()?;
+        }
+
+        let single_file = self.single_file;
+        if let Some(template) = self.media_template.as_deref()
+            && !single_file
+            && !template.contains("$Number")
+            && !template.contains("$Time$")
+        {
+            return Err(Error::InvalidArgument(format!(
+                "DASH media segment template '{template}' needs $Number$ or $Time$"
+            )));
+        }
+        let templates = [
+            self.init_template.as_deref(),
+            self.media_template.as_deref(),
+            self.single_file_name.as_deref(),
+        ];
+        for template in templates.into_iter().flatten() {
+            if self.streams.len() > 1 && !template.contains("$RepresentationID$") {
+                return Err(Error::InvalidArgument(format!(
+                    "DASH template '{template}' must contain $RepresentationID$ for multiple streams"
+                )));
+            }
+        }
+
+        if self.low_latency.is_some() {
+            if single_file {
+                return Err(Error::InvalidArgument(
+                    "low-latency DASH cannot use single-file mode".to_string(),
+                ));
+            }
+            if self.segment_type == DashSegmentType::Webm {
+                return Err(Error::InvalidArgument(
+                    "low-latency DASH requires MP4 (CMAF) segments".to_string(),
+                ));
+            }
+            if self.utc_timing_url.is_none() {
+                return Err(Error::InvalidArgument(
+                    "low-latency DASH requires a UTC timing URL".to_string(),
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Check that every representation belongs to exactly one set of a
+    /// single media type
+    fn validate_adaptation_sets(&self) -> Result<()> {
+        let mut ids = HashSet::new();
+        let mut owner: Vec<Option<u32>> = vec![None; self.streams.len()];
+
+        for set in &self.adaptation_sets {
+            if !ids.insert(set.id) {
+                return Err(Error::InvalidArgument(format!(
+                    "duplicate adaptation set id {}",
+                    set.id
+                )));
+            }
+
+            let members: Vec<usize> = match &set.streams {
+                SetStreams::Type(kind) => (0..self.streams.len())
+                    .filter(|&i| self.streams[i].kind == *kind)
+                    .collect(),
+                SetStreams::Indices(indices) => indices.clone(),
+            };
+            if members.is_empty() {
+                return Err(Error::InvalidArgument(format!(
+                    "adaptation set {} has no streams",
+                    set.id
+                )));
+            }
+
+            let mut kind = None;
+            for index in members {
+                let stream = self.streams.get(index).ok_or_else(|| {
+                    Error::InvalidArgument(format!(
+                        "adaptation set {} references stream {index}, but only {} are mapped",
+                        set.id,
+                        self.streams.len()
+                    ))
+                })?;
+                if let Some(other) = owner[index].replace(set.id) {
+                    return Err(Error::InvalidArgument(format!(
+                        "stream {index} is in adaptation sets {other} and {}",
+                        set.id
+                    )));
+                }
+                if kind.replace(stream.kind).is_some_and(|k| k != stream.kind) {
+                    return Err(Error::InvalidArgument(format!(
+                        "adaptation set {} mixes media types",
+                        set.id
+                    )));
+                }
+            }
+        }
+
+        if let Some(index) = owner.iter().position(Option::is_none) {
+            return Err(Error::InvalidArgument(format!(
+                "stream {index} is not in any adaptation set"
+            )));
+        }
+        Ok(())
+    }
+
+    /// Build the `dash` output
+    pub fn into_output(self) -> Result<Output> {
+        self.validate()?;
+
+        let mut output = Output::new(MediaPath::from_path(self.manifest.clone()))
+            .format("dash")
+            .option("dash_segment_type", self.segment_type.as_str());
+
+        if !self.adaptation_sets.is_empty() {
+            output = output.option("adaptation_sets", self.adaptation_sets_option());
+        }
+        if let Some(seconds) = self.segment_duration {
+            output = output.option("seg_duration", seconds.to_string());
+        }
+        if let Some(seconds) = self.fragment_duration {
+            output = output.option("frag_duration", seconds.to_string());
+        }
+        if let Some(template) = self.init_template {
+            output = output.option("init_seg_name", template);
+        }
+        if let Some(template) = self.media_template {
+            output = output.option("media_seg_name", template);
+        }
+        if self.single_file {
+            output = output.option("single_file", "1");
+        }
+        if let Some(name) = self.single_file_name {
+            output = output.option("single_file_name", name);
+        }
+        if let Some(enable) = self.use_template {
+            output = output.option("use_template", u8::from(enable).to_string());
+        }
+        if let Some(size) = self.window_size {
+            output = output.option("window_size", size.to_string());
+        }
+        if let Some(size) = self.extra_window_size {
+            output = output.option("extra_window_size", size.to_string());
+        }
+        if self.remove_at_exit {
+            output = output.option("remove_at_exit", "1");
+        }
+        if let Some(url) = self.utc_timing_url {
+            output = output.option("utc_timing_url", url);
+        }
+
+        let mut use_timeline = self.use_timeline;
+        let mut fragment_type = self.fragment_type;
+        if let Some(settings) = self.low_latency {
+            // Chunked CMAF: one fragment per frame, addressed by $Number$
+            use_timeline = use_timeline.or(Some(false));
+            fragment_type = fragment_type.or(Some(FragmentType::EveryFrame));
+            output = output
+                .option("ldash", "1")
+                .option("streaming", "1")
+                .option("write_prft", "1")
+                .option("target_latency", settings.target_latency.to_string())
+                .option("min_playback_rate", settings.min_playback_rate.to_string())
+                .option("max_playback_rate", settings.max_playback_rate.to_string());
+        }
+        if let Some(enable) = use_timeline {
+            output = output.option("use_timeline", u8::from(enable).to_string());
+        }
+        if let Some(fragment_type) = fragment_type {
+            output = output.option("frag_type", fragment_type.as_str());
+        }
+
+        let mut counts = [0usize; 3];
+        for stream in self.streams {
+            let slot = match stream.kind {
+                StreamType::Audio => 1,
+                StreamType::Subtitle => 2,
+                _ => 0,
+            };
+            let spec = StreamSpecifier::TypeIndex(stream.kind, counts[slot]);
+            counts[slot] += 1;
+
+            output = output.map(stream.source);
+            if let Some(language) = stream.language {
+                output = output.stream_metadata(spec.to_string(), "language", language);
+            }
+            if let Some(codec) = stream.codec {
+                output = output.stream_codec_opts(spec, codec);
+            }
+        }
+
+        Ok(output)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ffmpeg_common::Codec;
+
+    fn value<'a>(args: &'a [String], key: &str) -> Option<&'a str> {
+        args.iter()
+            .position(|a| a == key)
+            .map(|i| args[i + 1].as_str())
+    }
+
+    fn package() -> DashPackage {
+        DashPackage::new("out/manifest.mpd")
+            .stream(
+                DashStream::video(StreamMap::video_from(0))
+                    .codec(CodecOptions::new(Codec::h264()).bitrate("5000k")),
+            )
+            .stream(
+                DashStream::video(StreamMap::video_from(0))
+                    .codec(CodecOptions::new(Codec::h264()).bitrate("2800k")),
+            )
+            .stream(DashStream::audio(StreamMap::audio_from(0)).language("en"))
+    }
+
+    #[test]
+    fn test_adaptation_sets_option() {
+        let package = package()
+            .adaptation_set(AdaptationSet::video(0).segment_duration(4.0))
+            .adaptation_set(
+                AdaptationSet::streams(1, [2])
+                    .fragment_type(FragmentType::Duration)
+                    .fragment_duration(0.5),
+            );
+        assert_eq!(
+            package.adaptation_sets_option(),
+            "id=0,seg_duration=4,streams=v id=1,frag_duration=0.5,frag_type=duration,streams=2"
+        );
+    }
+
+    #[test]
+    fn test_into_output() {
+        let args = package()
+            .adaptation_set(AdaptationSet::video(0))
+            .adaptation_set(AdaptationSet::audio(1))
+            .segment_duration(4.0)
+            .use_timeline(true)
+            .into_output()
+            .unwrap()
+            .build_args();
+
+        assert_eq!(value(&args, "-f"), Some("dash"));
+        assert_eq!(
+            value(&args, "-adaptation_sets"),
+            Some("id=0,streams=v id=1,streams=a")
+        );
+        assert_eq!(value(&args, "-seg_duration"), Some("4"));
+        assert_eq!(value(&args, "-use_timeline"), Some("1"));
+        assert_eq!(value(&args, "-b:v:1"), Some("2800k"));
+        assert_eq!(value(&args, "-metadata:s:a:0"), Some("language=en"));
+        assert_eq!(args.iter().filter(|a| *a == "-map").count(), 3);
+        assert_eq!(args.last().map(String::as_str), Some("out/manifest.mpd"));
+    }
+
+    #[test]
+    fn test_adaptation_set_coverage() {
+        // Audio stream left out
+        assert!(
+            package()
+                .adaptation_set(AdaptationSet::video(0))
+                .into_output()
+                .is_err()
+        );
+        // Stream in two sets
+        assert!(
+            package()
+                .adaptation_set(AdaptationSet::video(0))
+                .adaptation_set(AdaptationSet::streams(1, [1, 2]))
+                .into_output()
+                .is_err()
+        );
+        // Mixed media types
+        assert!(
+            package()
+                .adaptation_set(AdaptationSet::streams(0, [0, 1, 2]))
+                .into_output()
+                .is_err()
+        );
+        // Out of range and duplicate ids
+        assert!(
+            package()
+                .adaptation_set(AdaptationSet::video(0))
+                .adaptation_set(AdaptationSet::streams(1, [2, 3]))
+                .into_output()
+                .is_err()
+        );
+        assert!(
+            package()
+                .adaptation_set(AdaptationSet::video(0))
+                .adaptation_set(AdaptationSet::audio(0))
+                .into_output()
+                .is_err()
+        );
+        // Sets are optional: FFmpeg then groups by media type itself
+        assert!(package().into_output().is_ok());
+    }
+
+    #[test]
+    fn test_templates_and_single_file() {
+        assert!(
+            package()
+                .media_segment_template("seg-$Number$.m4s")
+                .into_output()
+                .is_err()
+        );
+        assert!(
+            package()
+                .media_segment_template("$RepresentationID$.m4s")
+                .into_output()
+                .is_err()
+        );
+
+        let args = package()
+            .single_file_name("$RepresentationID$.$ext$")
+            .into_output()
+            .unwrap()
+            .build_args();
+        assert_eq!(value(&args, "-single_file"), Some("1"));
+        assert_eq!(
+            value(&args, "-single_file_name"),
+            Some("$RepresentationID$.$ext$")
+        );
+    }
+
+    #[test]
+    fn test_low_latency() {
+        assert!(
+            package()
+                .low_latency(LowLatency::default())
+                .into_output()
+                .is_err()
+        );
+
+        let args = package()
+            .low_latency(LowLatency::default())
+            .utc_timing_url("https://time.akamai.com/?iso")
+            .window_size(5)
+            .into_output()
+            .unwrap()
+            .build_args();
+        assert_eq!(value(&args, "-ldash"), Some("1"));
+        assert_eq!(value(&args, "-streaming"), Some("1"));
+        assert_eq!(value(&args, "-use_timeline"), Some("0"));
+        assert_eq!(value(&args, "-frag_type"), Some("every_frame"));
+        assert_eq!(value(&args, "-target_latency"), Some("3"));
+        assert_eq!(value(&args, "-window_size"), Some("5"));
+    }
+}
@@ -0,0 +1,732 @@
+//! Scene, black, silence, freeze and crop detection
+//!
+//! Each detection decodes the input into the null muxer with an analysis
+//! filter and parses the filter's log output into typed results.
+//!
+//! ```no_run
+//! use rust_ffmpeg::detect::Detector;
+//! use rust_ffmpeg::{Duration, Input};
+//!
+//! # async fn example() -> ffmpeg_common::Result<()> {
+//! let detector = Detector::new(Input::new("episode.mp4"))
+//!     .on_progress(|p| println!("{:?}", p.time));
+//!
+//! for cut in detector.scenes(0.4).await? {
+//!     println!("cut at {:?} ({:.2})", cut.time, cut.score);
+//! }
+//! let dead_air = detector.silence(Duration::from_secs(2), -50.0).await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use ffmpeg_common::{Duration, Error, LogLevel, Progress, Result};
+use std::fmt::{self, Debug};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration as StdDuration;
+use tokio::io::AsyncReadExt;
+
+use crate::builder::FFmpegBuilder;
+use crate::filter::{AudioFilter, Expr, FilterGraph, InputStream, Var, VideoFilter};
+use crate::input::Input;
+use crate::output::Output;
+
+/// Scene change found by [`Detector::scenes`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SceneCut {
+    /// Time of the first frame of the new scene
+    pub time: Duration,
+    /// Scene change score, from 0 to 1
+    pub score: f64,
+}
+
+impl SceneCut {
+    /// Parse `select`+`metadata=mode=print` output
+    pub fn parse(stderr: &str) -> Vec<Self> {
+        let mut cuts = Vec::new();
+        let mut time = None;
+        for line in log_lines(stderr) {
+            if let Some(value) = field(line, "pts_time:") {
+                time = seconds(value);
+            } else if let Some(score) = field(line, "lavfi.scene_score=")
+                && let (Some(time), Ok(score)) = (time.take(), score.parse())
+            {
+                cuts.push(Self { time, score });
+            }
+        }
+        cuts
+    }
+}
+
+/// Time span reported by black, silence or freeze detection
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Interval {
+    /// Start of the span
+    pub start: Duration,
+    /// End of the span
+    pub end: Duration,
+    /// Length of the span
+    pub duration: Duration,
+}
+
+impl Interval {
+    fn new(start: Duration, end: Duration) -> Self {
+        let duration = StdDuration::from(end).saturating_sub(StdDuration::from(start));
+        Self {
+            start,
+            end,
+            duration: duration.into(),
+        }
+    }
+
+    /// Parse `blackdetect` output
+    pub fn parse_black(stderr: &str) -> Vec<Self> {
+        log_lines(stderr)
+            .filter_map(|line| {
+                let start = seconds(field(line, "black_start:")?)?;
+                let end = seconds(field(line, "black_end:")?)?;
+                Some(Self::new(start, end))
+            })
+            .collect()
+    }
+
+    /// Parse `silencedetect` output
+    pub fn parse_silence(stderr: &str) -> Vec<Self> {
+        parse_start_end(stderr, "silence_start:", "silence_end:")
+    }
+
+    /// Parse `freezedetect` output
+    pub fn parse_freeze(stderr: &str) -> Vec<Self> {
+        parse_start_end(
+            stderr,
+            "lavfi.freezedetect.freeze_start:",
+            "lavfi.freezedetect.freeze_end:",
+        )
+    }
+}
+
+/// Log lines, split on the carriage returns of progress output too
+fn log_lines(stderr: &str) -> impl Iterator<Item = &str> {
+    stderr
+        .split(['\r', '\n'])
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+}
+
+/// Value following `key` on a log line, up to the next whitespace
+fn field<'a>(line: &'a str, key: &str) -> Option<&'a str> {
+    let start = line.find(key)? + key.len();
+    line[start..].split_whitespace().next()
+}
+
+/// Timestamp in seconds; filters report slightly negative times at the start
+fn seconds(value: &str) -> Option<Duration> {
+    let seconds: f64 = value.parse().ok()?;
+    StdDuration::try_from_secs_f64(seconds.max(0.0))
+        .ok()
+        .map(Duration::from)
+}
+
+/// Pair start and end lines; a span still open at the end of the log is
+/// closed at the last progress time
+fn parse_start_end(stderr: &str, start_key: &str, end_key: &str) -> Vec<Interval> {
+    let mut intervals = Vec::new();
+    let mut open = None;
+    let mut last_time = None;
+    for line in log_lines(stderr) {
+        if let Some(start) = field(line, start_key).and_then(seconds) {
+            open = Some(start);
+        } else if let Some(end) = field(line, end_key).and_then(seconds) {
+            if let Some(start) = open.take() {
+                intervals.push(Interval::new(start, end));
+            }
+        } else if let Some(time) = Progress::parse_line(line).and_then(|p| p.time) {
+            last_time = Some(Duration::from(time));
+        }
+    }
+    if let (Some(start), Some(end)) = (open, last_time) {
+        intervals.push(Interval::new(start, end));
+    }
+    intervals
+}
+
+fn secs_f64(duration: impl Into<Duration>) -> f64 {
+    StdDuration::from(duration.into()).as_secs_f64()
+}
+
+/// Runs detection passes over an input
+#[derive(Clone)]
+pub struct Detector {
+    input: Input,
+    executable: Option<PathBuf>,
+    progress_callback: Option<Arc<dyn Fn(Progress) + Send + Sync>>,
+}
+
+impl Debug for Detector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Detector")
+            .field("input", &self.input)
+            .field("executable", &self.executable)
+            .field("progress_callback", &self.progress_callback.is_some())
+            .finish()
+    }
+}
+
+impl Detector {
+    /// Analyze `input`, e.g. with a seek or duration set to limit the range
+    pub fn new(input: Input) -> Self {
+        Self {
+            input,
+            executable: None,
+            progress_callback: None,
+        }
+    }
+
+    /// Use a specific ffmpeg executable instead of the one on `PATH`
+    pub fn executable(mut self, path: impl Into<PathBuf>) -> Self {
+        self.executable = Some(path.into());
+        self
+    }
+
+    /// Report progress while a pass runs
+    pub fn on_progress<F>(mut self, callback: F) -> Self
+    where
+        F: Fn(Progress) + Send + Sync + 'static,
+    {
+        self.progress_callback = Some(Arc::new(callback));
+        self
+    }
+
+    /// Find scene changes scoring above `threshold` (0 to 1, typically 0.3
+    /// to 0.5)
+    pub async fn scenes(&self, threshold: f64) -> Result<Vec<SceneCut>> {
+        let stderr = self.run(self.scene_pass(threshold)?).await?;
+        Ok(SceneCut::parse(&stderr))
+    }
+
+    /// Find black segments of at least `min_duration`
+    ///
+    /// `pixel_threshold` is the luminance (0 to 1) below which a pixel
+    /// counts as black; FFmpeg's default is 0.1.
+    pub async fn black(
+        &self,
+        min_duration: impl Into<Duration>,
+        pixel_threshold: f64,
+    ) -> Result<Vec<Interval>> {
+        let stderr = self
+            .run(self.black_pass(min_duration.into(), pixel_threshold)?)
+            .await?;
+        Ok(Interval::parse_black(&stderr))
+    }
+
+    /// Find silences of at least `min_duration` below `noise_db` (e.g. -50)
+    pub async fn silence(
+        &self,
+        min_duration: impl Into<Duration>,
+        noise_db: f64,
+    ) -> Result<Vec<Interval>> {
+        let stderr = self
+            .run(self.silence_pass(min_duration.into(), noise_db)?)
+            .await?;
+        Ok(Interval::parse_silence(&stderr))
+    }
+
+    /// Find frozen video of at least `min_duration`, with frame differences
+    /// below `noise_db` (e.g. -60)
+    pub async fn freezes(
+        &self,
+        min_duration: impl Into<Duration>,
+        noise_db: f64,
+    ) -> Result<Vec<Interval>> {
+        let stderr = self
+            .run(self.freeze_pass(min_duration.into(), noise_db)?)
+            .await?;
+        Ok(Interval::parse_freeze(&stderr))
+    }
+
+    /// ffmpeg logging at the level the analysis filters print at
+    fn command(&self) -> Result<FFmpegBuilder> {
+        let builder = match &self.executable {
+            Some(path) => FFmpegBuilder::with_executable(path),
+            None => FFmpegBuilder::new()?,
+        };
+        Ok(builder.log_level(LogLevel::Info))
+    }
+
+    fn builder(&self) -> Result<FFmpegBuilder> {
+        Ok(self.command()?.input(self.input.clone()))
+    }
+
+    fn scene_pass(&self, threshold: f64) -> Result<FFmpegBuilder> {
+        let output = Output::new("-")
+            .format("null")
+            .no_audio()
+            .video_filter(VideoFilter::select(Expr::from(Var::Scene).gt(threshold)))
+            .video_filter(VideoFilter::new("metadata").param("mode", "print"));
+        Ok(self.builder()?.output(output))
+    }
+
+    fn black_pass(&self, min_duration: Duration, pixel_threshold: f64) -> Result<FFmpegBuilder> {
+        let blackdetect = VideoFilter::new("blackdetect")
+            .param("d", secs_f64(min_duration))
+            .param("pix_th", pixel_threshold);
+        let output = Output::new("-")
+            .format("null")
+            .no_audio()
+            .video_filter(blackdetect);
+        Ok(self.builder()?.output(output))
+    }
+
+    fn silence_pass(&self, min_duration: Duration, noise_db: f64) -> Result<FFmpegBuilder> {
+        let silencedetect = AudioFilter::new("silencedetect")
+            .param("n", format!("{noise_db}dB"))
+            .param("d", secs_f64(min_duration));
+        let output = Output::new("-")
+            .format("null")
+            .no_video()
+            .audio_filter(silencedetect);
+        Ok(self.builder()?.output(output))
+    }
+
+    fn freeze_pass(&self, min_duration: Duration, noise_db: f64) -> Result<FFmpegBuilder> {
+        let freezedetect = VideoFilter::new("freezedetect")
+            .param("n", format!("{noise_db}dB"))
+            .param("d", secs_f64(min_duration));
+        let output = Output::new("-")
+            .format("null")
+            .no_audio()
+            .video_filter(freezedetect);
+        Ok(self.builder()?.output(output))
+    }
+
+    /// Run a pass and return its log, reporting progress as it goes
+    async fn run(&self, builder: FFmpegBuilder) -> Result<String> {
+        run_analysis(builder, self.progress_callback.as_deref()).await
+    }
+}
+
+/// Crop rectangle suggested by `cropdetect`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Crop {
+    /// Width of the picture area
+    pub width: u32,
+    /// Height of the picture area
+    pub height: u32,
+    /// Left edge
+    pub x: u32,
+    /// Top edge
+    pub y: u32,
+}
+
+impl Crop {
+    /// Parse a `crop=w:h:x:y` suggestion; black frames yield negative sizes,
+    /// which are rejected
+    fn parse(value: &str) -> Option<Self> {
+        let mut parts = value.split(':').map(str::parse::<u32>);
+        let crop = Self {
+            width: parts.next()?.ok()?,
+            height: parts.next()?.ok()?,
+            x: parts.next()?.ok()?,
+            y: parts.next()?.ok()?,
+        };
+        (crop.width > 0 && crop.height > 0).then_some(crop)
+    }
+
+    /// Smallest rectangle containing both crops
+    fn union(self, other: Self) -> Self {
+        let x = self.x.min(other.x);
+        let y = self.y.min(other.y);
+        let right = (self.x + self.width).max(other.x + other.width);
+        let bottom = (self.y + self.height).max(other.y + other.height);
+        Self {
+            width: right - x,
+            height: bottom - y,
+            x,
+            y,
+        }
+    }
+
+    /// The `crop` filter for this rectangle
+    pub fn filter(&self) -> VideoFilter {
+        VideoFilter::crop(self.width, self.height, self.x, self.y)
+    }
+}
+
+/// Options for [`Detector::crop`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct CropDetect {
+    samples: usize,
+    sample_length: Duration,
+    limit: u32,
+    min_confidence: f64,
+}
+
+impl Default for CropDetect {
+    fn default() -> Self {
+        Self {
+            samples: 10,
+            sample_length: Duration::from_secs(2),
+            limit: 24,
+            min_confidence: 0.6,
+        }
+    }
+}
+
+impl CropDetect {
+    /// Ten 2-second samples, black level 24, 60% majority
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of evenly spaced points to sample
+    pub fn samples(mut self, samples: usize) -> Self {
+        self.samples = samples;
+        self
+    }
+
+    /// How much video to analyze at each point
+    pub fn sample_length(mut self, length: impl Into<Duration>) -> Self {
+        self.sample_length = length.into();
+        self
+    }
+
+    /// Luma level (0 to 255) up to which borders count as black
+    pub fn limit(mut self, limit: u32) -> Self {
+        self.limit = limit;
+        self
+    }
+
+    /// Share of samples that must agree on a crop before it is used; below
+    /// it the content is treated as mixed-aspect
+    pub fn min_confidence(mut self, confidence: f64) -> Self {
+        self.min_confidence = confidence;
+        self
+    }
+
+    /// Start of each sample for an input of `duration`, centered in equal
+    /// slices so intros and credits are not over-represented
+    fn positions(&self, duration: Duration) -> Vec<Duration> {
+        let total = StdDuration::from(duration);
+        let count = u32::try_from(self.samples).unwrap_or(u32::MAX);
+        (0..count)
+            .map(|i| (total * (2 * i + 1) / (2 * count)).into())
+            .collect()
+    }
+}
+
+/// Result of [`Detector::crop`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct CropDetection {
+    /// Crop to apply
+    pub crop: Crop,
+    /// Share of usable samples that suggested exactly this crop
+    pub confidence: f64,
+    /// Whether samples disagreed, e.g. letterboxed and full-frame scenes;
+    /// the crop is then the union of all suggestions so nothing is cut off
+    pub mixed: bool,
+    /// Suggestion of each sample; `None` for samples that were black or
+    /// could not be read
+    pub samples: Vec<Option<Crop>>,
+}
+
+impl CropDetection {
+    /// Parse the output of a crop detection pass with `samples` chains, one
+    /// `cropdetect` instance per sample
+    pub fn parse(stderr: &str, samples: usize, min_confidence: f64) -> Result<Self> {
+        const MARKER: &str = "[Parsed_cropdetect_";
+
+        let mut suggestions = vec![None; samples];
+        for line in log_lines(stderr) {
+            let Some(rest) = line.strip_prefix(MARKER) else {
+                continue;
+            };
+            let instance = rest
+                .split(|c: char| !c.is_ascii_digit())
+                .next()
+                .and_then(|digits| digits.parse::<usize>().ok());
+            // cropdetect keeps widening its estimate, so the last line wins
+            if let (Some(slot), Some(crop)) = (
+                instance.and_then(|i| suggestions.get_mut(i)),
+                field(line, "crop=").and_then(Crop::parse),
+            ) {
+                *slot = Some(crop);
+            }
+        }
+        Self::aggregate(suggestions, min_confidence)
+    }
+
+    /// Pick the crop suggested by a stable majority of samples, falling
+    /// back to the union of all suggestions for mixed-aspect content
+    pub fn aggregate(samples: Vec<Option<Crop>>, min_confidence: f64) -> Result<Self> {
+        let valid: Vec<Crop> = samples.iter().flatten().copied().collect();
+        let Some(&first) = valid.first() else {
+            return Err(Error::InvalidOutput(
+                "cropdetect gave no usable suggestion; is the video black?".to_string(),
+            ));
+        };
+
+        let mut counts: Vec<(Crop, usize)> = Vec::new();
+        for crop in &valid {
+            match counts.iter_mut().find(|(c, _)| c == crop) {
+                Some((_, count)) => *count += 1,
+                None => counts.push((*crop, 1)),
+            }
+        }
+        // Ties go to the larger crop, which cuts off less
+        let (majority, votes) = counts
+            .into_iter()
+            .max_by_key(|(crop, count)| (*count, u64::from(crop.width) * u64::from(crop.height)))
+            .unwrap_or((first, 1));
+
+        #[allow(clippy::cast_precision_loss)]
+        let confidence = votes as f64 / valid.len() as f64;
+        let mixed = confidence < min_confidence;
+        let crop = if mixed {
+            valid.iter().copied().fold(first, Crop::union)
+        } else {
+            majority
+        };
+
+        Ok(Self {
+            crop,
+            confidence,
+            mixed,
+            samples,
+        })
+    }
+
+    /// The `crop` filter to apply
+    pub fn filter(&self) -> VideoFilter {
+        self.crop.filter()
+    }
+}
+
+impl Detector {
+    /// Detect black borders by sampling an input of `duration` at evenly
+    /// spaced points
+    pub async fn crop(
+        &self,
+        duration: impl Into<Duration>,
+        options: &CropDetect,
+    ) -> Result<CropDetection> {
+        let stderr = self.run(self.crop_pass(duration.into(), options)?).await?;
+        CropDetection::parse(&stderr, options.samples, options.min_confidence)
+    }
+
+    /// One seeked input and one `cropdetect` chain per sample, all decoded
+    /// by a single process
+    fn crop_pass(&self, duration: Duration, options: &CropDetect) -> Result<FFmpegBuilder> {
+        if options.samples == 0 {
+            return Err(Error::InvalidArgument(
+                "Crop detection needs at least one sample".to_string(),
+            ));
+        }
+
+        let mut builder = self.command()?;
+        let mut graph = FilterGraph::new();
+        let mut output = Output::new("-").format("null");
+        for (index, position) in options.positions(duration).into_iter().enumerate() {
+            let input = self
+                .input
+                .clone()
+                .seek(position)
+                .duration(options.sample_length);
+            let cropdetect = VideoFilter::new("cropdetect")
+                .param("limit", options.limit)
+                .param("round", 2)
+                .param("reset", 0);
+            let node = graph.chain(InputStream::video(index), cropdetect);
+            let label = graph.output(node.output(0), format!("crop{index}"));
+            builder = builder.input(input);
+            output = output.map(label.into());
+        }
+
+        Ok(builder.filter_graph(graph).output(output))
+    }
+}
+
+/// Run an analysis command and collect its stderr
+///
+/// Progress lines are passed to `progress` while the command runs. A
+/// failure carries the collected log.
+pub(crate) async fn run_analysis(
+    builder: FFmpegBuilder,
+    progress: Option<&(dyn Fn(Progress) + Send + Sync)>,
+) -> Result<String> {
+    let mut process = builder.spawn().await?;
+    let mut stderr = process
+        .stderr()
+        .ok_or_else(|| Error::InvalidOutput("FFmpeg stderr is not captured".to_string()))?;
+
+    let read_log = async {
+        let mut log = Vec::new();
+        let mut line_start = 0;
+        let mut chunk = [0u8; 8192];
+        loop {
+            let read = stderr.read(&mut chunk).await?;
+            if read == 0 {
+                break;
+            }
+            log.extend_from_slice(&chunk[..read]);
+            while let Some(end) = log[line_start..]
+                .iter()
+                .position(|&b| b == b'\r' || b == b'\n')
+            {
+                let line = String::from_utf8_lossy(&log[line_start..line_start + end]);
+                if let Some(callback) = progress
+                    && let Some(update) = Progress::parse_line(&line)
+                {
+                    callback(update);
+                }
+                line_start += end + 1;
+            }
+        }
+        Ok::<_, Error>(String::from_utf8_lossy(&log).into_owned())
+    };
+
+    let (log, status) = tokio::join!(read_log, process.wait());
+    let log = log?;
+    match status {
+        Ok(_) => Ok(log),
+        Err(Error::ProcessFailed {
+            message,
+            exit_status,
+            ..
+        }) => Err(Error::process_failed(message, exit_status, Some(log))),
+        Err(e) => Err(e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn secs(value: f64) -> Duration {
+        StdDuration::from_secs_f64(value).into()
+    }
+
+    #[test]
+    fn test_parse_scene_cuts() {
+        let stderr = "\
+[Parsed_metadata_1 @ 0x55d0c8] frame:0    pts:375     pts_time:15.015
+[Parsed_metadata_1 @ 0x55d0c8] lavfi.scene_score=0.612345
+frame=  412 fps=210 q=-0.0 size=N/A time=00:00:16.48 bitrate=N/A speed=8.4x\r\
+[Parsed_metadata_1 @ 0x55d0c8] frame:1    pts:1201    pts_time:48.048
+[Parsed_metadata_1 @ 0x55d0c8] lavfi.scene_score=0.445000
+";
+        let cuts = SceneCut::parse(stderr);
+        assert_eq!(cuts.len(), 2);
+        assert_eq!(cuts[0].time, secs(15.015));
+        assert!((cuts[1].score - 0.445).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_parse_intervals() {
+        let black = Interval::parse_black(
+            "[blackdetect @ 0x5600] black_start:0 black_end:2.04 black_duration:2.04\n\
+             [blackdetect @ 0x5600] black_start:1290.2 black_end:1292.5 black_duration:2.3\n",
+        );
+        assert_eq!(black.len(), 2);
+        assert_eq!(black[0].start, secs(0.0));
+        assert_eq!(black[1].end, secs(1292.5));
+
+        // Trailing silence without an end is closed at the last progress time
+        let silence = Interval::parse_silence(
+            "[silencedetect @ 0x7f] silence_start: -0.00068\n\
+             [silencedetect @ 0x7f] silence_end: 3.5 | silence_duration: 3.50068\n\
+             [silencedetect @ 0x7f] silence_start: 58\n\
+             size=N/A time=00:01:00.00 bitrate=N/A speed= 900x\r",
+        );
+        assert_eq!(silence.len(), 2);
+        assert_eq!(silence[0], Interval::new(secs(0.0), secs(3.5)));
+        assert_eq!(silence[1].duration, secs(2.0));
+
+        let freezes = Interval::parse_freeze(
+            "[freezedetect @ 0x1] lavfi.freezedetect.freeze_start: 5.005\n\
+             [freezedetect @ 0x1] lavfi.freezedetect.freeze_duration: 3.003\n\
+             [freezedetect @ 0x1] lavfi.freezedetect.freeze_end: 8.008\n",
+        );
+        assert_eq!(freezes, vec![Interval::new(secs(5.005), secs(8.008))]);
+    }
+
+    #[test]
+    fn test_crop_detection() {
+        let stderr = "\
+[Parsed_cropdetect_0 @ 0x1] x1:0 x2:1919 y1:140 y2:939 w:1920 h:800 x:0 y:140 pts:1 t:0.04 crop=1920:800:0:140
+[Parsed_cropdetect_0 @ 0x1] x1:0 x2:1919 y1:138 y2:941 w:1920 h:804 x:0 y:138 pts:2 t:0.08 crop=1920:804:0:138
+[Parsed_cropdetect_1 @ 0x2] x1:1919 x2:0 y1:1079 y2:0 w:-1904 h:-1072 x:1912 y:1076 pts:1 t:0.04 crop=-1904:-1072:1912:1076
+[Parsed_cropdetect_2 @ 0x3] x1:0 x2:1919 y1:138 y2:941 w:1920 h:804 x:0 y:138 pts:1 t:0.04 crop=1920:804:0:138
+";
+        let detection = CropDetection::parse(stderr, 3, 0.6).unwrap();
+        assert_eq!(detection.samples[1], None);
+        assert!(!detection.mixed);
+        assert!((detection.confidence - 1.0).abs() < f64::EPSILON);
+        assert_eq!(
+            detection.filter().to_string(),
+            "crop=w=1920:h=804:x=0:y=138"
+        );
+
+        // Letterboxed and full-frame scenes: keep everything
+        let letterbox = Crop {
+            width: 1920,
+            height: 800,
+            x: 0,
+            y: 140,
+        };
+        let full = Crop {
+            width: 1920,
+            height: 1080,
+            x: 0,
+            y: 0,
+        };
+        let pillarbox = Crop {
+            width: 1440,
+            height: 1080,
+            x: 240,
+            y: 0,
+        };
+        let detection =
+            CropDetection::aggregate(vec![Some(letterbox), Some(full), Some(pillarbox)], 0.6)
+                .unwrap();
+        assert!(detection.mixed);
+        assert_eq!(detection.crop, full);
+
+        assert!(CropDetection::aggregate(vec![None, None], 0.6).is_err());
+    }
+
+    #[test]
+    fn test_detection_passes() {
+        let detector = Detector::new(Input::new("in.mp4")).executable("ffmpeg");
+
+        let args = detector.scene_pass(0.4).unwrap().build_args().unwrap();
+        assert!(args.contains(&r"select=expr=gt(scene\,0.4),metadata=mode=print".to_string()));
+        assert!(args.contains(&"-an".to_string()));
+
+        let args = detector
+            .silence_pass(Duration::from_secs(2), -50.0)
+            .unwrap()
+            .build_args()
+            .unwrap();
+        assert!(args.contains(&"silencedetect=n=-50dB:d=2".to_string()));
+        assert!(args.contains(&"-vn".to_string()));
+        assert_eq!(args.last().map(String::as_str), Some("-"));
+
+        let options = CropDetect::new().samples(4);
+        let args = detector
+            .crop_pass(Duration::from_secs(400), &options)
+            .unwrap()
+            .build_args()
+            .unwrap();
+        let seeks: Vec<&String> = args
+            .iter()
+            .zip(args.iter().skip(1))
+            .filter(|(flag, _)| *flag == "-ss")
+            .map(|(_, position)| position)
+            .collect();
+        assert_eq!(seeks, ["00:00:50", "00:02:30", "00:04:10", "00:05:50"]);
+        assert!(
+            args.iter()
+                .any(|a| a.contains("[3:v]cropdetect=limit=24:round=2:reset=0[crop3]"))
+        );
+    }
+}
@@ -0,0 +1,415 @@
+use std::path::Path;
+
+use super::{Expr, VideoFilter, escape};
+
+/// Escape literal text for drawtext's own expansion level, where `\` and `%`
+/// are special
+fn escape_text(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.replace("\r\n", "\n").chars() {
+        if matches!(c, '\\' | '%') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Escape an argument of a `%{...}` expansion
+fn escape_expansion_arg(arg: &str) -> String {
+    let mut escaped = String::with_capacity(arg.len());
+    for c in arg.chars() {
+        if matches!(c, '\\' | '\'' | ':' | '}') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Format of a `%{pts}` expansion
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PtsFormat {
+    /// Seconds with microsecond precision
+    Flt,
+    /// `HH:MM:SS.mmm`
+    Hms,
+}
+
+/// Placement of the text on the frame
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextPosition {
+    /// Top left corner
+    TopLeft,
+    /// Top edge, centered horizontally
+    TopCenter,
+    /// Top right corner
+    TopRight,
+    /// Centered on the frame
+    Center,
+    /// Bottom left corner
+    BottomLeft,
+    /// Bottom edge, centered horizontally
+    BottomCenter,
+    /// Bottom right corner
+    BottomRight,
+}
+
+/// Distance kept from the frame edges by a [`TextPosition`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Margin {
+    Pixels(u32),
+    /// Fraction of the frame size, e.g. 0.05 for the title-safe area
+    Fraction(f64),
+}
+
+/// Builder for the `drawtext` filter
+///
+/// Text is escaped for drawtext expansion, the option list and the filter
+/// graph, so it can contain `:`, `%`, `'`, `,` and newlines.
+///
+/// ```
+/// use rust_ffmpeg::filter::{DrawText, PtsFormat, TextPosition, VideoFilter};
+///
+/// let filter: VideoFilter = DrawText::new("ACME: 100% ")
+///     .pts(PtsFormat::Hms)
+///     .fontsize(32)
+///     .fontcolor_alpha("white", 0.8)
+///     .position(TextPosition::BottomRight)
+///     .safe_area(0.05)
+///     .into();
+///
+/// assert_eq!(filter.option("text").as_deref(), Some(r"ACME: 100\% %{pts:hms}"));
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct DrawText {
+    text: Option<String>,
+    params: Vec<(String, String)>,
+    position: Option<TextPosition>,
+    margin: Margin,
+}
+
+impl DrawText {
+    /// Draw literal text
+    pub fn new(text: impl AsRef<str>) -> Self {
+        Self::empty().append(text)
+    }
+
+    fn empty() -> Self {
+        Self {
+            text: Some(String::new()),
+            params: Vec::new(),
+            position: None,
+            margin: Margin::Pixels(0),
+        }
+    }
+
+    /// Draw the contents of a text file
+    ///
+    /// The file contents are subject to `%{...}` expansion as written.
+    pub fn from_file(path: impl AsRef<Path>) -> Self {
+        let mut draw = Self::empty();
+        draw.text = None;
+        draw.set("textfile", path.as_ref().to_string_lossy());
+        draw
+    }
+
+    /// Draw a running SMPTE timecode starting at `timecode` (`HH:MM:SS:FF`)
+    /// at frame rate `rate`, e.g. `25` or `30000/1001`
+    pub fn timecode(timecode: &str, rate: &str) -> Self {
+        let mut draw = Self::empty();
+        draw.text = None;
+        draw.set("timecode", timecode);
+        draw.set("rate", rate);
+        draw
+    }
+
+    /// Replace an option, escaping the literal value
+    fn set(&mut self, key: &str, value: impl AsRef<str>) {
+        self.params.retain(|(k, _)| k != key);
+        self.params
+            .push((key.to_string(), escape::escape_value(value.as_ref())));
+    }
+
+    fn push_text(mut self, text: &str) -> Self {
+        self.text.get_or_insert_with(String::new).push_str(text);
+        self
+    }
+
+    /// Append literal text
+    pub fn append(self, text: impl AsRef<str>) -> Self {
+        let text = escape_text(text.as_ref());
+        self.push_text(&text)
+    }
+
+    /// Append the frame timestamp (`%{pts}`)
+    pub fn pts(self, format: PtsFormat) -> Self {
+        match format {
+            PtsFormat::Flt => self.push_text("%{pts:flt}"),
+            PtsFormat::Hms => self.push_text("%{pts:hms}"),
+        }
+    }
+
+    /// Append the local time, formatted with `strftime` (`%{localtime}`)
+    pub fn localtime(self, format: &str) -> Self {
+        let text = format!("%{{localtime:{}}}", escape_expansion_arg(format));
+        self.push_text(&text)
+    }
+
+    /// Append the frame number (`%{frame_num}`)
+    pub fn frame_number(self) -> Self {
+        self.push_text("%{frame_num}")
+    }
+
+    /// Append a frame metadata value (`%{metadata}`)
+    pub fn metadata(self, key: &str) -> Self {
+        let text = format!("%{{metadata:{}}}", escape_expansion_arg(key));
+        self.push_text(&text)
+    }
+
+    /// Append the value of an expression (`%{expr}`)
+    pub fn eval(self, expr: impl Into<Expr>) -> Self {
+        let text = format!(
+            "%{{expr:{}}}",
+            escape_expansion_arg(&expr.into().to_string())
+        );
+        self.push_text(&text)
+    }
+
+    /// Reload the text file before each frame
+    pub fn reload(mut self, reload: bool) -> Self {
+        self.set("reload", if reload { "1" } else { "0" });
+        self
+    }
+
+    /// Font family, resolved with fontconfig
+    pub fn font(mut self, font: &str) -> Self {
+        self.set("font", font);
+        self
+    }
+
+    /// Font file path
+    pub fn fontfile(mut self, path: impl AsRef<Path>) -> Self {
+        self.set("fontfile", path.as_ref().to_string_lossy());
+        self
+    }
+
+    /// Font size in pixels
+    pub fn fontsize(mut self, size: u32) -> Self {
+        self.set("fontsize", size.to_string());
+        self
+    }
+
+    /// Text color, e.g. `white` or `0xFFCC00`
+    pub fn fontcolor(mut self, color: &str) -> Self {
+        self.set("fontcolor", color);
+        self
+    }
+
+    /// Text color with opacity between 0 and 1
+    pub fn fontcolor_alpha(mut self, color: &str, alpha: f64) -> Self {
+        self.set("fontcolor", format!("{color}@{alpha}"));
+        self
+    }
+
+    /// Draw a box behind the text
+    pub fn background(mut self, color: &str, alpha: f64, border_width: u32) -> Self {
+        self.set("box", "1");
+        self.set("boxcolor", format!("{color}@{alpha}"));
+        self.set("boxborderw", border_width.to_string());
+        self
+    }
+
+    /// Draw a shadow offset by `x`/`y` pixels
+    pub fn shadow(mut self, color: &str, x: i32, y: i32) -> Self {
+        self.set("shadowcolor", color);
+        self.set("shadowx", x.to_string());
+        self.set("shadowy", y.to_string());
+        self
+    }
+
+    /// Draw an outline around the glyphs
+    pub fn border(mut self, color: &str, width: u32) -> Self {
+        self.set("bordercolor", color);
+        self.set("borderw", width.to_string());
+        self
+    }
+
+    /// Place the text with a preset
+    pub fn position(mut self, position: TextPosition) -> Self {
+        self.position = Some(position);
+        self
+    }
+
+    /// Distance in pixels from the edges for [`DrawText::position`]
+    pub fn margin(mut self, pixels: u32) -> Self {
+        self.margin = Margin::Pixels(pixels);
+        self
+    }
+
+    /// Keep the text inside a safe area, as a fraction of the frame size
+    /// on each side (0.05 for title-safe, 0.035 for action-safe)
+    pub fn safe_area(mut self, fraction: f64) -> Self {
+        self.margin = Margin::Fraction(fraction);
+        self
+    }
+
+    /// Horizontal position expression, overriding the preset
+    pub fn x(mut self, x: impl Into<Expr>) -> Self {
+        self.set("x", x.into().to_string());
+        self
+    }
+
+    /// Vertical position expression, overriding the preset
+    pub fn y(mut self, y: impl Into<Expr>) -> Self {
+        self.set("y", y.into().to_string());
+        self
+    }
+
+    /// `x` and `y` for the position preset
+    fn placement(&self, position: TextPosition) -> (Expr, Expr) {
+        let (margin_x, margin_y) = match self.margin {
+            Margin::Pixels(pixels) => (Expr::from(pixels), Expr::from(pixels)),
+            Margin::Fraction(fraction) => (Expr::var("w") * fraction, Expr::var("h") * fraction),
+        };
+        let left = margin_x.clone();
+        let center_x = (Expr::var("w") - Expr::var("text_w")) / 2;
+        let right = Expr::var("w") - Expr::var("text_w") - margin_x;
+        let top = margin_y.clone();
+        let center_y = (Expr::var("h") - Expr::var("text_h")) / 2;
+        let bottom = Expr::var("h") - Expr::var("text_h") - margin_y;
+
+        match position {
+            TextPosition::TopLeft => (left, top),
+            TextPosition::TopCenter => (center_x, top),
+            TextPosition::TopRight => (right, top),
+            TextPosition::Center => (center_x, center_y),
+            TextPosition::BottomLeft => (left, bottom),
+            TextPosition::BottomCenter => (center_x, bottom),
+            TextPosition::BottomRight => (right, bottom),
+        }
+    }
+
+    /// Build the filter
+    pub fn build(self) -> VideoFilter {
+        let mut filter = VideoFilter::new("drawtext");
+        if let Some(text) = &self.text {
+            filter = filter.param("text", escape::escape_value(text));
+        }
+
+        let explicit = |key: &str| self.params.iter().any(|(k, _)| k == key);
+        if let Some(position) = self.position {
+            let (x, y) = self.placement(position);
+            if !explicit("x") {
+                filter = filter.param_expr("x", x);
+            }
+            if !explicit("y") {
+                filter = filter.param_expr("y", y);
+            }
+        }
+
+        for (key, value) in self.params {
+            filter = filter.param(key, value);
+        }
+        filter
+    }
+}
+
+impl From<DrawText> for VideoFilter {
+    fn from(draw: DrawText) -> Self {
+        draw.build()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_drawtext_escaping() {
+        let text = "this is a 'string': may contain one, or more, special characters";
+        let filter = DrawText::new(text).build();
+        assert_eq!(
+            filter.to_string(),
+            r"drawtext=text=this is a \\\'string\\\'\\: may contain one\, or more\, special characters"
+        );
+
+        let filter = DrawText::new("ACME: 100%\r\nline two\\").build();
+        assert_eq!(
+            filter.option("text").as_deref(),
+            Some("ACME: 100\\%\nline two\\\\")
+        );
+        assert_eq!(
+            VideoFilter::drawtext("50%").option("text").as_deref(),
+            Some(r"50\%")
+        );
+
+        // Parses back to the same options
+        let parsed = VideoFilter::parse(&filter.to_string()).unwrap();
+        assert_eq!(parsed, filter);
+    }
+
+    #[test]
+    fn test_drawtext_expansion() {
+        let filter = DrawText::new("Recorded ")
+            .localtime("%Y-%m-%d %H:%M")
+            .append(" frame ")
+            .frame_number()
+            .build();
+        assert_eq!(
+            filter.option("text").as_deref(),
+            Some(r"Recorded %{localtime:%Y-%m-%d %H\:%M} frame %{frame_num}")
+        );
+    }
+
+    #[test]
+    fn test_drawtext_styling_and_position() {
+        let filter = DrawText::new("Preview")
+            .fontfile("C:/Fonts/Arial Bold.ttf")
+            .fontsize(48)
+            .background("black", 0.5, 10)
+            .border("white", 2)
+            .position(TextPosition::TopRight)
+            .margin(20)
+            .build();
+        assert_eq!(filter.option("x").as_deref(), Some("w-text_w-20"));
+        assert_eq!(filter.option("y").as_deref(), Some("20"));
+        assert_eq!(
+            filter.option("fontfile").as_deref(),
+            Some("C:/Fonts/Arial Bold.ttf")
+        );
+        assert!(
+            filter
+                .to_string()
+                .contains(r"fontfile=C\\:/Fonts/Arial Bold.ttf")
+        );
+        assert_eq!(filter.option("boxcolor").as_deref(), Some("black@0.5"));
+
+        let filter = DrawText::new("Safe")
+            .position(TextPosition::BottomCenter)
+            .safe_area(0.05)
+            .x(10)
+            .build();
+        assert_eq!(filter.option("x").as_deref(), Some("10"));
+        assert_eq!(filter.option("y").as_deref(), Some("h-text_h-h*0.05"));
+    }
+
+    #[test]
+    fn test_drawtext_sources() {
+        let filter = DrawText::from_file("/tmp/now playing.txt")
+            .reload(true)
+            .build();
+        assert_eq!(filter.option("text"), None);
+        assert_eq!(
+            filter.option("textfile").as_deref(),
+            Some("/tmp/now playing.txt")
+        );
+        assert_eq!(filter.option("reload").as_deref(), Some("1"));
+
+        let filter = DrawText::timecode("01:00:00:00", "25").build();
+        assert_eq!(
+            filter.to_string(),
+            r"drawtext=timecode=01\\:00\\:00\\:00:rate=25"
+        );
+    }
+}
@@ -0,0 +1,100 @@
+//! Escaping for the levels of FFmpeg's filtergraph syntax
+//!
+//! A value written inside a filter graph is unescaped twice: first when the
+//! graph description is split into filters, where `[`, `]`, `,` and `;` are
+//! special, then when the filter arguments are split into options, where `:`
+//! is special. Both levels accept `\` escapes and `'` quoting.
+
+const WHITESPACE: [char; 4] = [' ', '\n', '\t', '\r'];
+
+fn escape_chars(s: &str, special: &[char]) -> String {
+    let last = s.chars().count().saturating_sub(1);
+    let mut escaped = String::with_capacity(s.len());
+    for (i, c) in s.chars().enumerate() {
+        // Leading and trailing whitespace is trimmed unless escaped
+        let edge_space = (i == 0 || i == last) && WHITESPACE.contains(&c);
+        if c == '\\' || c == '\'' || special.contains(&c) || edge_space {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Escape a value for the option level (`key=value:key=value`)
+pub fn escape_option(value: &str) -> String {
+    escape_chars(value, &[':'])
+}
+
+/// Escape filter arguments for the graph level (`filter,filter;filter`)
+pub fn escape_graph(args: &str) -> String {
+    escape_chars(args, &['[', ']', ',', ';'])
+}
+
+/// Escape a literal value so it can be used as a filter parameter in a graph
+///
+/// ```
+/// use rust_ffmpeg::filter::escape;
+///
+/// assert_eq!(escape::escape_value("a:b,c"), r"a\\:b\,c");
+/// ```
+pub fn escape_value(value: &str) -> String {
+    escape_graph(&escape_option(value))
+}
+
+/// Remove one level of escaping and quoting
+///
+/// Follows FFmpeg's tokenizer: `\x` yields `x`, text between `'` is taken
+/// literally, and unescaped whitespace at both ends is dropped.
+pub fn unescape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    // Length of the output that must survive trailing whitespace trimming
+    let mut keep = 0;
+    let mut chars = s.trim_start_matches(WHITESPACE).chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                if let Some(next) = chars.next() {
+                    out.push(next);
+                    keep = out.len();
+                }
+            }
+            '\'' => {
+                out.extend(chars.by_ref().take_while(|&c| c != '\''));
+                keep = out.len();
+            }
+            c => out.push(c),
+        }
+    }
+
+    let trimmed = out[keep..].trim_end_matches(WHITESPACE).len();
+    out.truncate(keep + trimmed);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_escape_levels() {
+        assert_eq!(escape_option("10:20"), r"10\:20");
+        assert_eq!(escape_graph("a=1,b=[x]"), r"a=1\,b=\[x\]");
+        assert_eq!(escape_value("it's"), r"it\\\'s");
+        assert_eq!(escape_option(" pad "), r"\ pad\ ");
+
+        for value in ["a:b", "it's [here], ok; \\ done", " x ", "100%"] {
+            assert_eq!(unescape(&unescape(&escape_value(value))), value);
+        }
+    }
+
+    #[test]
+    fn test_unescape() {
+        assert_eq!(unescape(r"eq(n\,0)"), "eq(n,0)");
+        assert_eq!(unescape("'a:b' c"), "a:b c");
+        assert_eq!(unescape("  text  "), "text");
+        assert_eq!(unescape(r"text\ "), "text ");
+        assert_eq!(unescape("' x '"), " x ");
+    }
+}
@@ -0,0 +1,488 @@
+use std::fmt;
+use std::ops::{Add, Div, Mul, Neg, Rem, Sub};
+
+use super::escape;
+
+/// Operator precedence, loosest first
+const SEQUENCE: u8 = 0;
+const ADDITIVE: u8 = 1;
+const MULTIPLICATIVE: u8 = 2;
+const POWER: u8 = 3;
+const ATOM: u8 = 4;
+
+/// FFmpeg expression, as used by `select`, `setpts`, `overlay` and friends
+///
+/// Expressions are built from variables, numbers, functions and arithmetic
+/// and are kept unescaped; escaping is applied for the place the expression
+/// ends up in, see [`Expr::to_option_value`] and [`Expr::to_graph_value`].
+///
+/// ```
+/// use rust_ffmpeg::filter::{Expr, Var};
+///
+/// let every_10th = (Expr::from(Var::N) % 10).equals(0);
+/// let window = Expr::from(Var::T).between(5, 10);
+/// let expr = every_10th * window;
+///
+/// assert_eq!(expr.to_string(), "eq(mod(n,10),0)*between(t,5,10)");
+/// assert_eq!(expr.to_graph_value(), r"eq(mod(n\,10)\,0)*between(t\,5\,10)");
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Expr {
+    text: String,
+    precedence: u8,
+}
+
+/// Variables available to filter expressions
+///
+/// Not every filter defines every variable; see the filter's documentation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Var {
+    /// Timestamp in seconds (`t`)
+    T,
+    /// Frame number, starting at 0 (`n`)
+    N,
+    /// Presentation timestamp in time base units (`PTS`)
+    Pts,
+    /// PTS of the first frame (`STARTPTS`)
+    StartPts,
+    /// Time base (`TB`)
+    Tb,
+    /// Frame rate (`FRAME_RATE`)
+    FrameRate,
+    /// Main video width (`W`, also `main_w`)
+    MainW,
+    /// Main video height (`H`, also `main_h`)
+    MainH,
+    /// Overlay or input width (`w`, also `overlay_w`)
+    W,
+    /// Overlay or input height (`h`, also `overlay_h`)
+    H,
+    /// Input width (`iw`)
+    InW,
+    /// Input height (`ih`)
+    InH,
+    /// Output width (`ow`)
+    OutW,
+    /// Output height (`oh`)
+    OutH,
+    /// Horizontal position (`x`)
+    X,
+    /// Vertical position (`y`)
+    Y,
+    /// Sample aspect ratio (`sar`)
+    Sar,
+    /// Display aspect ratio (`dar`)
+    Dar,
+    /// Picture type of the frame (`pict_type`), compare with [`PictType`]
+    PictType,
+    /// Whether the frame is a key frame (`key`)
+    Key,
+    /// Scene change score between 0 and 1 (`scene`)
+    Scene,
+    /// Timestamp of the previously selected frame (`prev_selected_t`)
+    PrevSelectedT,
+}
+
+impl Var {
+    /// Name of the variable in FFmpeg expressions
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::T => "t",
+            Self::N => "n",
+            Self::Pts => "PTS",
+            Self::StartPts => "STARTPTS",
+            Self::Tb => "TB",
+            Self::FrameRate => "FRAME_RATE",
+            Self::MainW => "W",
+            Self::MainH => "H",
+            Self::W => "w",
+            Self::H => "h",
+            Self::InW => "iw",
+            Self::InH => "ih",
+            Self::OutW => "ow",
+            Self::OutH => "oh",
+            Self::X => "x",
+            Self::Y => "y",
+            Self::Sar => "sar",
+            Self::Dar => "dar",
+            Self::PictType => "pict_type",
+            Self::Key => "key",
+            Self::Scene => "scene",
+            Self::PrevSelectedT => "prev_selected_t",
+        }
+    }
+}
+
+/// Picture type constants for comparisons with [`Var::PictType`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PictType {
+    /// Intra frame
+    I,
+    /// Predicted frame
+    P,
+    /// Bi-directionally predicted frame
+    B,
+    /// Switching intra frame
+    Si,
+    /// Switching predicted frame
+    Sp,
+    /// Bi-directional intra frame
+    Bi,
+}
+
+impl PictType {
+    /// Name of the constant in FFmpeg expressions
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::I => "I",
+            Self::P => "P",
+            Self::B => "B",
+            Self::Si => "SI",
+            Self::Sp => "SP",
+            Self::Bi => "BI",
+        }
+    }
+}
+
+impl Expr {
+    fn new(text: String, precedence: u8) -> Self {
+        Self { text, precedence }
+    }
+
+    /// Variable or constant by name, e.g. `main_w` or `PI`
+    pub fn var(name: impl Into<String>) -> Self {
+        Self::new(name.into(), ATOM)
+    }
+
+    /// Plain expression text, unescaped
+    ///
+    /// The text is wrapped in parentheses when combined with other
+    /// expressions.
+    pub fn raw(text: impl Into<String>) -> Self {
+        Self::new(text.into(), SEQUENCE)
+    }
+
+    /// Call an expression function
+    pub fn call<I>(name: &str, args: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: Into<Expr>,
+    {
+        let args: Vec<String> = args
+            .into_iter()
+            .map(|arg| {
+                // Arguments are separated by `,`, only a sequence needs grouping
+                let arg = arg.into();
+                if arg.precedence == SEQUENCE {
+                    format!("({})", arg.text)
+                } else {
+                    arg.text
+                }
+            })
+            .collect();
+        Self::new(format!("{name}({})", args.join(",")), ATOM)
+    }
+
+    fn binary(self, op: &str, precedence: u8, rhs: Expr) -> Self {
+        let lhs = self.grouped(precedence);
+        // Right operands of the same precedence are grouped so that `-`, `/`
+        // and `^` keep their meaning and signs never follow an operator
+        let rhs = rhs.grouped(precedence + 1);
+        Self::new(format!("{lhs}{op}{rhs}"), precedence)
+    }
+
+    fn grouped(self, precedence: u8) -> String {
+        if self.precedence < precedence {
+            format!("({})", self.text)
+        } else {
+            self.text
+        }
+    }
+
+    /// `if(self, then)`: `then` when non-zero, else 0
+    pub fn if_then(self, then: impl Into<Expr>) -> Self {
+        Self::call("if", [self, then.into()])
+    }
+
+    /// `if(self, then, otherwise)`
+    pub fn if_else(self, then: impl Into<Expr>, otherwise: impl Into<Expr>) -> Self {
+        Self::call("if", [self, then.into(), otherwise.into()])
+    }
+
+    /// `between(self, min, max)`: 1 when `min <= self <= max`
+    pub fn between(self, min: impl Into<Expr>, max: impl Into<Expr>) -> Self {
+        Self::call("between", [self, min.into(), max.into()])
+    }
+
+    /// `eq(self, other)`
+    pub fn equals(self, other: impl Into<Expr>) -> Self {
+        Self::call("eq", [self, other.into()])
+    }
+
+    /// `gt(self, other)`
+    pub fn gt(self, other: impl Into<Expr>) -> Self {
+        Self::call("gt", [self, other.into()])
+    }
+
+    /// `gte(self, other)`
+    pub fn gte(self, other: impl Into<Expr>) -> Self {
+        Self::call("gte", [self, other.into()])
+    }
+
+    /// `lt(self, other)`
+    pub fn lt(self, other: impl Into<Expr>) -> Self {
+        Self::call("lt", [self, other.into()])
+    }
+
+    /// `lte(self, other)`
+    pub fn lte(self, other: impl Into<Expr>) -> Self {
+        Self::call("lte", [self, other.into()])
+    }
+
+    /// `not(self)`
+    #[allow(clippy::should_implement_trait)]
+    pub fn not(self) -> Self {
+        Self::call("not", [self])
+    }
+
+    /// Logical and of two conditions
+    pub fn and(self, other: impl Into<Expr>) -> Self {
+        self * other.into()
+    }
+
+    /// Logical or of two conditions
+    pub fn or(self, other: impl Into<Expr>) -> Self {
+        Self::call("gt", [self + other.into(), Expr::from(0)])
+    }
+
+    /// `mod(self, divisor)`
+    pub fn modulo(self, divisor: impl Into<Expr>) -> Self {
+        Self::call("mod", [self, divisor.into()])
+    }
+
+    /// `min(self, other)`
+    pub fn min(self, other: impl Into<Expr>) -> Self {
+        Self::call("min", [self, other.into()])
+    }
+
+    /// `max(self, other)`
+    pub fn max(self, other: impl Into<Expr>) -> Self {
+        Self::call("max", [self, other.into()])
+    }
+
+    /// `clip(self, min, max)`
+    pub fn clip(self, min: impl Into<Expr>, max: impl Into<Expr>) -> Self {
+        Self::call("clip", [self, min.into(), max.into()])
+    }
+
+    /// `abs(self)`
+    pub fn abs(self) -> Self {
+        Self::call("abs", [self])
+    }
+
+    /// `floor(self)`
+    pub fn floor(self) -> Self {
+        Self::call("floor", [self])
+    }
+
+    /// `ceil(self)`
+    pub fn ceil(self) -> Self {
+        Self::call("ceil", [self])
+    }
+
+    /// `round(self)`
+    pub fn round(self) -> Self {
+        Self::call("round", [self])
+    }
+
+    /// `trunc(self)`
+    pub fn trunc(self) -> Self {
+        Self::call("trunc", [self])
+    }
+
+    /// `sqrt(self)`
+    pub fn sqrt(self) -> Self {
+        Self::call("sqrt", [self])
+    }
+
+    /// `self^exponent`
+    pub fn pow(self, exponent: impl Into<Expr>) -> Self {
+        self.binary("^", POWER, exponent.into())
+    }
+
+    /// `st(slot, self)`: store the value in variable slot 0-9 and return it
+    pub fn store(self, slot: u8) -> Self {
+        Self::call("st", [Expr::from(slot), self])
+    }
+
+    /// `ld(slot)`: load the value stored in variable slot 0-9
+    pub fn load(slot: u8) -> Self {
+        Self::call("ld", [slot])
+    }
+
+    /// `self;next`: evaluate both, yielding the value of `next`
+    pub fn then(self, next: impl Into<Expr>) -> Self {
+        let next = next.into();
+        Self::new(format!("{};{}", self.text, next.text), SEQUENCE)
+    }
+
+    /// Expression for use as an option value (`key=value`), with `\`, `'`
+    /// and `:` escaped
+    pub fn to_option_value(&self) -> String {
+        escape::escape_option(&self.text)
+    }
+
+    /// Expression for use inside a filter graph or `-vf`/`-af`, escaped for
+    /// both the option and the graph level
+    pub fn to_graph_value(&self) -> String {
+        escape::escape_value(&self.text)
+    }
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.text)
+    }
+}
+
+impl From<Var> for Expr {
+    fn from(var: Var) -> Self {
+        Self::var(var.as_str())
+    }
+}
+
+impl From<PictType> for Expr {
+    fn from(pict_type: PictType) -> Self {
+        Self::var(pict_type.as_str())
+    }
+}
+
+impl From<&str> for Expr {
+    fn from(text: &str) -> Self {
+        Self::raw(text)
+    }
+}
+
+impl From<String> for Expr {
+    fn from(text: String) -> Self {
+        Self::raw(text)
+    }
+}
+
+impl From<f64> for Expr {
+    fn from(value: f64) -> Self {
+        let precedence = if value.is_sign_negative() {
+            ADDITIVE
+        } else {
+            ATOM
+        };
+        Self::new(value.to_string(), precedence)
+    }
+}
+
+macro_rules! expr_from_int {
+    (signed: $($s:ty),*; unsigned: $($u:ty),*) => {
+        $(
+            impl From<$s> for Expr {
+                fn from(value: $s) -> Self {
+                    let precedence = if value < 0 { ADDITIVE } else { ATOM };
+                    Self::new(value.to_string(), precedence)
+                }
+            }
+        )*
+        $(
+            impl From<$u> for Expr {
+                fn from(value: $u) -> Self {
+                    Self::new(value.to_string(), ATOM)
+                }
+            }
+        )*
+    };
+}
+
+expr_from_int!(signed: i32, i64; unsigned: u8, u32, u64, usize);
+
+macro_rules! expr_op {
+    ($trait:ident, $method:ident, $op:literal, $precedence:expr) => {
+        impl<T: Into<Expr>> $trait<T> for Expr {
+            type Output = Expr;
+
+            fn $method(self, rhs: T) -> Expr {
+                self.binary($op, $precedence, rhs.into())
+            }
+        }
+    };
+}
+
+expr_op!(Add, add, "+", ADDITIVE);
+expr_op!(Sub, sub, "-", ADDITIVE);
+expr_op!(Mul, mul, "*", MULTIPLICATIVE);
+expr_op!(Div, div, "/", MULTIPLICATIVE);
+
+impl<T: Into<Expr>> Rem<T> for Expr {
+    type Output = Expr;
+
+    fn rem(self, rhs: T) -> Expr {
+        self.modulo(rhs)
+    }
+}
+
+impl Neg for Expr {
+    type Output = Expr;
+
+    fn neg(self) -> Expr {
+        let operand = self.grouped(POWER);
+        Expr::new(format!("-{operand}"), ADDITIVE)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_arithmetic_grouping() {
+        let w = Expr::from(Var::MainW);
+        let ow = Expr::from(Var::W);
+        assert_eq!(((w.clone() - ow.clone()) / 2).to_string(), "(W-w)/2");
+        assert_eq!((w.clone() - (ow.clone() - 10)).to_string(), "W-(w-10)");
+        assert_eq!((w.clone() - ow.clone() - 10).to_string(), "W-w-10");
+        assert_eq!((w.clone() * 2 + 1).to_string(), "W*2+1");
+        assert_eq!((w.clone() + -ow.clone()).to_string(), "W+(-w)");
+        assert_eq!((w.clone() * -1).to_string(), "W*(-1)");
+        assert_eq!((-(w + ow)).to_string(), "-(W+w)");
+        assert_eq!(Expr::from(Var::T).pow(2).to_string(), "t^2");
+        assert_eq!((Expr::raw("a+b") * 2).to_string(), "(a+b)*2");
+    }
+
+    #[test]
+    fn test_functions() {
+        let keyframes = Expr::from(Var::PictType).equals(PictType::I);
+        assert_eq!(keyframes.to_string(), "eq(pict_type,I)");
+        assert_eq!(keyframes.to_graph_value(), r"eq(pict_type\,I)");
+
+        let fade = Expr::from(Var::T).lt(2).if_else(Expr::from(Var::T) / 2, 1);
+        assert_eq!(fade.to_string(), "if(lt(t,2),t/2,1)");
+
+        let scene = Expr::from(Var::Scene)
+            .gt(0.4)
+            .or(Expr::from(Var::N).equals(0));
+        assert_eq!(scene.to_string(), "gt(gt(scene,0.4)+eq(n,0),0)");
+
+        let counter = (Expr::load(0) + 1).store(0).then(Expr::load(0).modulo(3));
+        assert_eq!(counter.to_string(), "st(0,ld(0)+1);mod(ld(0),3)");
+        assert_eq!(
+            Expr::call("if", [counter.clone(), Expr::from(1)]).to_string(),
+            "if((st(0,ld(0)+1);mod(ld(0),3)),1)"
+        );
+        assert_eq!(counter.to_graph_value(), r"st(0\,ld(0)+1)\;mod(ld(0)\,3)");
+    }
+
+    #[test]
+    fn test_escaping_contexts() {
+        let clock = Expr::raw("'12:00'");
+        assert_eq!(clock.to_string(), "'12:00'");
+        assert_eq!(clock.to_option_value(), r"\'12\:00\'");
+        assert_eq!(clock.to_graph_value(), r"\\\'12\\:00\\\'");
+    }
+}
@@ -0,0 +1,945 @@
+use ffmpeg_common::{Result, error::ErrorBuilder};
+use std::collections::HashSet;
+use std::fmt::{self, Write as _};
+
+use super::{AudioFilter, VideoFilter};
+use crate::stream::StreamMap;
+
+/// Media type carried by a filter pad
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PadType {
+    /// Video frames
+    Video,
+    /// Audio samples
+    Audio,
+}
+
+impl PadType {
+    /// Stream type letter used in stream specifiers and labels
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Video => "v",
+            Self::Audio => "a",
+        }
+    }
+}
+
+impl fmt::Display for PadType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Video => write!(f, "video"),
+            Self::Audio => write!(f, "audio"),
+        }
+    }
+}
+
+/// Handle to a filter added to a [`FilterGraph`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct NodeId(usize);
+
+impl NodeId {
+    /// Input pad of this filter
+    pub fn input(self, index: usize) -> InputPad {
+        InputPad {
+            node: self.0,
+            index,
+        }
+    }
+
+    /// Output pad of this filter
+    pub fn output(self, index: usize) -> OutputPad {
+        OutputPad {
+            node: self.0,
+            index,
+        }
+    }
+}
+
+/// Input pad of a filter in a [`FilterGraph`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct InputPad {
+    node: usize,
+    index: usize,
+}
+
+/// Output pad of a filter in a [`FilterGraph`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct OutputPad {
+    node: usize,
+    index: usize,
+}
+
+/// Stream of an input file fed into a [`FilterGraph`] (`[0:v]`, `[1:a:0]`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct InputStream {
+    input_index: usize,
+    pad_type: PadType,
+    stream_index: Option<usize>,
+}
+
+impl InputStream {
+    /// Video of an input file
+    pub fn video(input_index: usize) -> Self {
+        Self {
+            input_index,
+            pad_type: PadType::Video,
+            stream_index: None,
+        }
+    }
+
+    /// Audio of an input file
+    pub fn audio(input_index: usize) -> Self {
+        Self {
+            input_index,
+            pad_type: PadType::Audio,
+            stream_index: None,
+        }
+    }
+
+    /// Select the n-th stream of this type (`[0:a:1]`)
+    pub fn index(mut self, stream_index: usize) -> Self {
+        self.stream_index = Some(stream_index);
+        self
+    }
+
+    /// Input file index
+    pub fn input_index(&self) -> usize {
+        self.input_index
+    }
+
+    /// Media type of the stream
+    pub fn pad_type(&self) -> PadType {
+        self.pad_type
+    }
+
+    /// Label referencing this stream inside a filter graph, without brackets
+    pub fn label(&self) -> String {
+        match self.stream_index {
+            Some(index) => format!("{}:{}:{}", self.input_index, self.pad_type.as_str(), index),
+            None => format!("{}:{}", self.input_index, self.pad_type.as_str()),
+        }
+    }
+}
+
+/// Anything that can feed an input pad: an input file stream or another
+/// filter's output pad
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PadSource {
+    /// Stream of an input file
+    Stream(InputStream),
+    /// Output pad of another filter
+    Pad(OutputPad),
+}
+
+impl From<InputStream> for PadSource {
+    fn from(stream: InputStream) -> Self {
+        Self::Stream(stream)
+    }
+}
+
+impl From<OutputPad> for PadSource {
+    fn from(pad: OutputPad) -> Self {
+        Self::Pad(pad)
+    }
+}
+
+/// Named output of a [`FilterGraph`], ready to be mapped to an output
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GraphOutput {
+    label: String,
+    pad_type: PadType,
+}
+
+impl GraphOutput {
+    /// Label of the output, without brackets
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+
+    /// Media type of the output
+    pub fn pad_type(&self) -> PadType {
+        self.pad_type
+    }
+}
+
+impl From<&GraphOutput> for StreamMap {
+    fn from(output: &GraphOutput) -> Self {
+        StreamMap::label(output.label.clone())
+    }
+}
+
+impl From<GraphOutput> for StreamMap {
+    fn from(output: GraphOutput) -> Self {
+        StreamMap::label(output.label)
+    }
+}
+
+/// Complex filter graph builder
+///
+/// Filters are added as typed nodes and wired together through pad handles.
+/// Link labels are generated automatically; only the outputs exposed with
+/// [`FilterGraph::output`] get user-visible names.
+///
+/// ```
+/// use rust_ffmpeg::filter::{FilterGraph, InputStream, VideoFilter};
+///
+/// let mut graph = FilterGraph::new();
+/// let scale = graph.add(VideoFilter::scale(320, -1));
+/// let overlay = graph.add(VideoFilter::overlay("10", "10"));
+/// graph.connect(InputStream::video(1), scale.input(0));
+/// graph.connect(InputStream::video(0), overlay.input(0));
+/// graph.connect(scale.output(0), overlay.input(1));
+/// let vout = graph.output(overlay.output(0), "vout");
+///
+/// assert!(graph.validate().is_ok());
+/// assert_eq!(vout.label(), "vout");
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct FilterGraph {
+    nodes: Vec<FilterNode>,
+    edges: Vec<FilterEdge>,
+    outputs: Vec<(OutputPad, String)>,
+}
+
+#[derive(Debug, Clone)]
+enum FilterNode {
+    /// Filter with known pads, linked through the graph's edges
+    Typed {
+        filter: String,
+        name: String,
+        inputs: Vec<PadType>,
+        outputs: Vec<PadType>,
+    },
+    /// Raw filter string with hand-written labels
+    Raw {
+        filter: String,
+        inputs: Vec<String>,
+        outputs: Vec<String>,
+    },
+}
+
+#[derive(Debug, Clone)]
+struct FilterEdge {
+    from: PadSource,
+    to: InputPad,
+}
+
+impl FilterGraph {
+    /// Create a new filter graph
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a raw filter node with hand-written labels
+    ///
+    /// Raw nodes are emitted as-is and are not checked by
+    /// [`FilterGraph::validate`].
+    pub fn add_filter(
+        mut self,
+        filter: impl Into<String>,
+        inputs: Vec<String>,
+        outputs: Vec<String>,
+    ) -> Self {
+        self.nodes.push(FilterNode::Raw {
+            filter: filter.into(),
+            inputs,
+            outputs,
+        });
+        self
+    }
+
+    /// Add a video or audio filter, inferring its pads from the filter name
+    /// and options (e.g. `overlay` has two video inputs, `split=3` three outputs)
+    pub fn add(&mut self, filter: impl Into<GraphFilter>) -> NodeId {
+        let filter = filter.into();
+        let (inputs, outputs) = filter.default_pads();
+        self.push_typed(&filter, inputs, outputs)
+    }
+
+    /// Add a filter with explicitly declared pads
+    pub fn add_with_pads(
+        &mut self,
+        filter: impl Into<GraphFilter>,
+        inputs: &[PadType],
+        outputs: &[PadType],
+    ) -> NodeId {
+        self.push_typed(&filter.into(), inputs.to_vec(), outputs.to_vec())
+    }
+
+    fn push_typed(
+        &mut self,
+        filter: &GraphFilter,
+        inputs: Vec<PadType>,
+        outputs: Vec<PadType>,
+    ) -> NodeId {
+        let id = NodeId(self.nodes.len());
+        self.nodes.push(FilterNode::Typed {
+            name: filter.name().to_string(),
+            filter: filter.to_string(),
+            inputs,
+            outputs,
+        });
+        id
+    }
+
+    /// Connect an input stream or a filter output to a filter input
+    pub fn connect(&mut self, from: impl Into<PadSource>, to: InputPad) -> &mut Self {
+        self.edges.push(FilterEdge {
+            from: from.into(),
+            to,
+        });
+        self
+    }
+
+    /// Add a filter fed by a single source and return it, for building chains
+    pub fn chain(&mut self, from: impl Into<PadSource>, filter: impl Into<GraphFilter>) -> NodeId {
+        let node = self.add(filter);
+        self.connect(from, node.input(0));
+        node
+    }
+
+    /// Expose a filter output under a name so it can be mapped to an output
+    pub fn output(&mut self, pad: OutputPad, name: impl Into<String>) -> GraphOutput {
+        let label = name.into();
+        let pad_type = self.output_type(pad).unwrap_or(PadType::Video);
+        self.outputs.push((pad, label.clone()));
+        GraphOutput { label, pad_type }
+    }
+
+    /// Replace the named output with `count` copies made by `split` or
+    /// `asplit`, named `{label}_0`, `{label}_1` and so on
+    pub(crate) fn split_output(&mut self, label: &str, count: usize) -> Option<Vec<GraphOutput>> {
+        let position = self.outputs.iter().position(|(_, l)| l == label)?;
+        let (pad, _) = self.outputs.remove(position);
+        let pad_type = self.output_type(pad).unwrap_or(PadType::Video);
+        let split: GraphFilter = match pad_type {
+            PadType::Video => VideoFilter::new("split").param("outputs", count).into(),
+            PadType::Audio => AudioFilter::new("asplit").param("outputs", count).into(),
+        };
+        let node = self.add_with_pads(split, &[pad_type], &vec![pad_type; count]);
+        self.connect(pad, node.input(0));
+        Some(
+            (0..count)
+                .map(|i| self.output(node.output(i), format!("{label}_{i}")))
+                .collect(),
+        )
+    }
+
+    /// Named outputs exposed by this graph
+    pub fn outputs(&self) -> Vec<GraphOutput> {
+        self.outputs
+            .iter()
+            .map(|(pad, label)| GraphOutput {
+                label: label.clone(),
+                pad_type: self.output_type(*pad).unwrap_or(PadType::Video),
+            })
+            .collect()
+    }
+
+    /// Labels that can be mapped to an output, including raw node labels
+    pub fn output_labels(&self) -> Vec<String> {
+        graph_output_labels(&self.build())
+    }
+
+    /// Input file indices referenced by typed nodes
+    pub fn input_indices(&self) -> Vec<usize> {
+        let mut indices: Vec<usize> = self
+            .edges
+            .iter()
+            .filter_map(|edge| match edge.from {
+                PadSource::Stream(stream) => Some(stream.input_index),
+                PadSource::Pad(_) => None,
+            })
+            .collect();
+        indices.sort_unstable();
+        indices.dedup();
+        indices
+    }
+
+    /// Whether the graph has no filters
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    fn typed_pads(&self, node: usize) -> Option<(&[PadType], &[PadType])> {
+        match self.nodes.get(node)? {
+            FilterNode::Typed {
+                inputs, outputs, ..
+            } => Some((inputs, outputs)),
+            FilterNode::Raw { .. } => None,
+        }
+    }
+
+    fn node_name(&self, node: usize) -> String {
+        match self.nodes.get(node) {
+            Some(FilterNode::Typed { name, .. }) => format!("#{node} ({name})"),
+            _ => format!("#{node}"),
+        }
+    }
+
+    fn output_type(&self, pad: OutputPad) -> Option<PadType> {
+        self.typed_pads(pad.node)
+            .and_then(|(_, outputs)| outputs.get(pad.index).copied())
+    }
+
+    fn input_type(&self, pad: InputPad) -> Option<PadType> {
+        self.typed_pads(pad.node)
+            .and_then(|(inputs, _)| inputs.get(pad.index).copied())
+    }
+
+    /// Check pad types and counts, unconnected pads, cycles and output names
+    pub fn validate(&self) -> Result<()> {
+        let mut problems = Vec::new();
+        let mut input_uses = vec![Vec::new(); self.nodes.len()];
+        let mut output_uses = vec![Vec::new(); self.nodes.len()];
+
+        for (node, (inputs, outputs)) in self
+            .nodes
+            .iter()
+            .enumerate()
+            .filter_map(|(i, _)| self.typed_pads(i).map(|pads| (i, pads)))
+        {
+            input_uses[node] = vec![0usize; inputs.len()];
+            output_uses[node] = vec![0usize; outputs.len()];
+        }
+
+        for edge in &self.edges {
+            let Some(to_type) = self.input_type(edge.to) else {
+                problems.push(format!(
+                    "filter {} has no input pad {}",
+                    self.node_name(edge.to.node),
+                    edge.to.index
+                ));
+                continue;
+            };
+            input_uses[edge.to.node][edge.to.index] += 1;
+
+            let from_type = match edge.from {
+                PadSource::Stream(stream) => stream.pad_type,
+                PadSource::Pad(pad) => {
+                    let Some(from_type) = self.output_type(pad) else {
+                        problems.push(format!(
+                            "filter {} has no output pad {}",
+                            self.node_name(pad.node),
+                            pad.index
+                        ));
+                        continue;
+                    };
+                    output_uses[pad.node][pad.index] += 1;
+                    from_type
+                }
+            };
+
+            if from_type != to_type {
+                problems.push(format!(
+                    "{} output connected to {} input {} of filter {}",
+                    from_type,
+                    to_type,
+                    edge.to.index,
+                    self.node_name(edge.to.node)
+                ));
+            }
+        }
+
+        let mut names = HashSet::new();
+        for (pad, label) in &self.outputs {
+            if self.output_type(*pad).is_none() {
+                problems.push(format!(
+                    "output '{label}' refers to missing output pad {} of filter {}",
+                    pad.index,
+                    self.node_name(pad.node)
+                ));
+                continue;
+            }
+            output_uses[pad.node][pad.index] += 1;
+
+            if label.is_empty()
+                || !label
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+            {
+                problems.push(format!("output name '{label}' is not a valid link label"));
+            }
+            if !names.insert(label.as_str()) {
+                problems.push(format!("output name '{label}' is used more than once"));
+            }
+        }
+
+        problems.extend(self.pad_use_problems(&input_uses, &output_uses));
+
+        let (_, cyclic) = self.topological_order();
+        if !cyclic.is_empty() {
+            let nodes: Vec<String> = cyclic.iter().map(|&n| self.node_name(n)).collect();
+            problems.push(format!("cycle between filters {}", nodes.join(", ")));
+        }
+
+        if problems.is_empty() {
+            return Ok(());
+        }
+
+        let mut error = ErrorBuilder::new("Invalid filter graph");
+        for problem in problems {
+            error = error.detail(problem);
+        }
+        Err(error.build())
+    }
+
+    /// Report pads that are not connected exactly once
+    fn pad_use_problems(
+        &self,
+        input_uses: &[Vec<usize>],
+        output_uses: &[Vec<usize>],
+    ) -> Vec<String> {
+        let mut problems = Vec::new();
+
+        for (node, uses) in input_uses.iter().enumerate() {
+            for (index, count) in uses.iter().enumerate() {
+                match count {
+                    0 => problems.push(format!(
+                        "input pad {index} of filter {} is not connected",
+                        self.node_name(node)
+                    )),
+                    1 => {}
+                    _ => problems.push(format!(
+                        "input pad {index} of filter {} is connected {count} times",
+                        self.node_name(node)
+                    )),
+                }
+            }
+        }
+
+        for (node, uses) in output_uses.iter().enumerate() {
+            for (index, count) in uses.iter().enumerate() {
+                match count {
+                    0 => problems.push(format!(
+                        "output pad {index} of filter {} is not connected",
+                        self.node_name(node)
+                    )),
+                    1 => {}
+                    _ => problems.push(format!(
+                        "output pad {index} of filter {} is used {count} times (use split/asplit)",
+                        self.node_name(node)
+                    )),
+                }
+            }
+        }
+
+        problems
+    }
+
+    /// Order nodes so every filter comes after the filters feeding it,
+    /// keeping insertion order where possible. Nodes on a cycle are returned
+    /// separately.
+    fn topological_order(&self) -> (Vec<usize>, Vec<usize>) {
+        let mut pending = vec![0usize; self.nodes.len()];
+        for edge in &self.edges {
+            if let PadSource::Pad(pad) = edge.from
+                && pad.node < self.nodes.len()
+                && edge.to.node < self.nodes.len()
+            {
+                pending[edge.to.node] += 1;
+            }
+        }
+
+        let mut order = Vec::with_capacity(self.nodes.len());
+        let mut done = vec![false; self.nodes.len()];
+        while let Some(node) = (0..self.nodes.len()).find(|&n| !done[n] && pending[n] == 0) {
+            done[node] = true;
+            order.push(node);
+            for edge in &self.edges {
+                if let PadSource::Pad(pad) = edge.from
+                    && pad.node == node
+                    && edge.to.node < self.nodes.len()
+                {
+                    pending[edge.to.node] -= 1;
+                }
+            }
+        }
+
+        let cyclic = (0..self.nodes.len()).filter(|&n| !done[n]).collect();
+        (order, cyclic)
+    }
+
+    /// Build the filter graph string
+    pub fn build(&self) -> String {
+        let (mut order, cyclic) = self.topological_order();
+        order.extend(cyclic);
+
+        // Pick a label for every output pad: exposed name or generated one
+        let taken: HashSet<&str> = self.outputs.iter().map(|(_, l)| l.as_str()).collect();
+        let mut counter = 0;
+        let mut labels: Vec<(OutputPad, String)> = self
+            .outputs
+            .iter()
+            .map(|(pad, label)| (*pad, label.clone()))
+            .collect();
+        for edge in &self.edges {
+            if let PadSource::Pad(pad) = edge.from {
+                if labels.iter().any(|(p, _)| *p == pad) {
+                    continue;
+                }
+                let prefix = self.output_type(pad).unwrap_or(PadType::Video).as_str();
+                let label = loop {
+                    let candidate = format!("{prefix}{counter}");
+                    counter += 1;
+                    if !taken.contains(candidate.as_str()) {
+                        break candidate;
+                    }
+                };
+                labels.push((pad, label));
+            }
+        }
+        let label_of = |pad: OutputPad| {
+            labels
+                .iter()
+                .find(|(p, _)| *p == pad)
+                .map(|(_, l)| l.as_str())
+        };
+
+        let mut parts = Vec::new();
+
+        for node in order {
+            let mut part = String::new();
+
+            match &self.nodes[node] {
+                FilterNode::Typed {
+                    filter,
+                    inputs,
+                    outputs,
+                    ..
+                } => {
+                    for index in 0..inputs.len() {
+                        let pad = InputPad { node, index };
+                        let source = self.edges.iter().find(|e| e.to == pad).map(|e| e.from);
+                        match source {
+                            Some(PadSource::Stream(stream)) => {
+                                let _ = write!(part, "[{}]", stream.label());
+                            }
+                            Some(PadSource::Pad(from)) => {
+                                if let Some(label) = label_of(from) {
+                                    let _ = write!(part, "[{label}]");
+                                }
+                            }
+                            None => {}
+                        }
+                    }
+
+                    part.push_str(filter);
+
+                    for index in 0..outputs.len() {
+                        if let Some(label) = label_of(OutputPad { node, index }) {
+                            let _ = write!(part, "[{label}]");
+                        }
+                    }
+                }
+                FilterNode::Raw {
+                    filter,
+                    inputs,
+                    outputs,
+                } => {
+                    // Inputs
+                    if !inputs.is_empty() {
+                        part.push_str(&inputs.join(""));
+                    }
+
+                    // Filter
+                    part.push_str(filter);
+
+                    // Outputs
+                    if !outputs.is_empty() {
+                        part.push_str(&outputs.join(""));
+                    }
+                }
+            }
+
+            parts.push(part);
+        }
+
+        parts.join(";")
+    }
+}
+
+impl fmt::Display for FilterGraph {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.build())
+    }
+}
+
+/// Video or audio filter placed in a [`FilterGraph`]
+#[derive(Debug, Clone, PartialEq)]
+pub enum GraphFilter {
+    /// Video filter
+    Video(VideoFilter),
+    /// Audio filter
+    Audio(AudioFilter),
+}
+
+impl GraphFilter {
+    /// Filter name, including an `@instance` suffix if any
+    pub fn name(&self) -> &str {
+        match self {
+            Self::Video(filter) => &filter.name,
+            Self::Audio(filter) => &filter.name,
+        }
+    }
+
+    /// Parameters as written; positional options have an empty key
+    pub fn params(&self) -> &[(String, String)] {
+        match self {
+            Self::Video(filter) => filter.params(),
+            Self::Audio(filter) => filter.params(),
+        }
+    }
+
+    /// Unescaped value of a parameter (use `""` for the first positional one)
+    pub fn option(&self, key: &str) -> Option<String> {
+        match self {
+            Self::Video(filter) => filter.option(key),
+            Self::Audio(filter) => filter.option(key),
+        }
+    }
+
+    /// Media type of the frames the filter reads
+    pub fn media(&self) -> PadType {
+        match self {
+            Self::Video(_) => PadType::Video,
+            Self::Audio(_) => PadType::Audio,
+        }
+    }
+
+    /// The same filter as a video filter
+    pub fn into_video(self) -> VideoFilter {
+        match self {
+            Self::Video(filter) => filter,
+            Self::Audio(AudioFilter { name, params }) => VideoFilter { name, params },
+        }
+    }
+
+    /// The same filter as an audio filter
+    pub fn into_audio(self) -> AudioFilter {
+        match self {
+            Self::Video(VideoFilter { name, params }) => AudioFilter { name, params },
+            Self::Audio(filter) => filter,
+        }
+    }
+
+    /// The same filter reading frames of type `media`
+    pub(crate) fn with_media(self, media: PadType) -> Self {
+        match media {
+            PadType::Video => Self::Video(self.into_video()),
+            PadType::Audio => Self::Audio(self.into_audio()),
+        }
+    }
+
+    fn param(&self, key: &str) -> Option<usize> {
+        let params = match self {
+            Self::Video(filter) => &filter.params,
+            Self::Audio(filter) => &filter.params,
+        };
+        params
+            .iter()
+            .find(|(k, _)| k == key)
+            .and_then(|(_, v)| v.parse().ok())
+    }
+
+    /// Pads of well-known filters; anything else is a single-input,
+    /// single-output filter of the node's own media type
+    pub(crate) fn default_pads(&self) -> (Vec<PadType>, Vec<PadType>) {
+        use PadType::{Audio, Video};
+
+        let media = match self {
+            Self::Video(_) => Video,
+            Self::Audio(_) => Audio,
+        };
+        let count = |key: &str, default: usize| self.param(key).unwrap_or(default);
+
+        match self.name() {
+            "overlay" | "blend" | "xfade" | "alphamerge" | "paletteuse" | "psnr" | "ssim"
+            | "libvmaf" => (vec![Video; 2], vec![Video]),
+            "hstack" | "vstack" | "xstack" => (vec![Video; count("inputs", 2)], vec![Video]),
+            "split" => (vec![Video], vec![Video; count("outputs", 2)]),
+            "asplit" => (vec![Audio], vec![Audio; count("outputs", 2)]),
+            "amix" | "amerge" | "join" => (vec![Audio; count("inputs", 2)], vec![Audio]),
+            "sidechaincompress" | "sidechaingate" | "acrossfade" => (vec![Audio; 2], vec![Audio]),
+            "concat" => {
+                let segments = count("n", 2);
+                let video = count("v", 1);
+                let audio = count("a", 0);
+                let mut outputs = vec![Video; video];
+                outputs.extend(vec![Audio; audio]);
+                (outputs.repeat(segments), outputs)
+            }
+            "showwaves" | "showspectrum" | "showvolume" | "showcqt" | "avectorscope"
+            | "ahistogram" => (vec![Audio], vec![Video]),
+            "color" | "testsrc" | "testsrc2" | "nullsrc" | "smptebars" => (vec![], vec![Video]),
+            "anullsrc" | "sine" | "aevalsrc" => (vec![], vec![Audio]),
+            _ => (vec![media], vec![media]),
+        }
+    }
+}
+
+impl fmt::Display for GraphFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Video(filter) => write!(f, "{filter}"),
+            Self::Audio(filter) => write!(f, "{filter}"),
+        }
+    }
+}
+
+impl From<VideoFilter> for GraphFilter {
+    fn from(filter: VideoFilter) -> Self {
+        Self::Video(filter)
+    }
+}
+
+impl From<AudioFilter> for GraphFilter {
+    fn from(filter: AudioFilter) -> Self {
+        Self::Audio(filter)
+    }
+}
+
+/// Collect the labels of filter graph outputs that are not consumed inside the
+/// graph itself, i.e. the labels that can be mapped to an output with `-map`.
+pub(crate) fn graph_output_labels(graph: &str) -> Vec<String> {
+    let mut inputs = Vec::new();
+    let mut outputs = Vec::new();
+    // Whether the scanner has seen a filter name since the last separator
+    let mut after_filter = false;
+    let mut quoted = false;
+    let mut chars = graph.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                chars.next();
+            }
+            '\'' => quoted = !quoted,
+            _ if quoted => {}
+            '[' => {
+                let label: String = chars.by_ref().take_while(|&c| c != ']').collect();
+                if after_filter {
+                    outputs.push(label);
+                } else {
+                    inputs.push(label);
+                }
+            }
+            ',' | ';' => after_filter = false,
+            c if c.is_whitespace() => {}
+            _ => after_filter = true,
+        }
+    }
+
+    outputs.retain(|label| !inputs.contains(label));
+    outputs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_filter_graph() {
+        let graph = FilterGraph::new()
+            .add_filter(
+                "scale=640:480",
+                vec!["[0:v]".to_string()],
+                vec!["[scaled]".to_string()],
+            )
+            .add_filter(
+                "overlay",
+                vec!["[scaled]".to_string(), "[1:v]".to_string()],
+                vec!["[out]".to_string()],
+            );
+
+        let result = graph.build();
+        assert!(result.contains("[0:v]scale=640:480[scaled]"));
+        assert!(result.contains("[scaled][1:v]overlay[out]"));
+        assert_eq!(graph_output_labels(&result), vec!["out"]);
+    }
+
+    #[test]
+    fn test_graph_output_labels() {
+        let graph = "[0:v]split=2[a][b];[a]scale=640:-2[small];\
+                     [b]drawtext=text='x[y]z'[big];[1:a]anull[aout]";
+        assert_eq!(graph_output_labels(graph), vec!["small", "big", "aout"]);
+    }
+
+    #[test]
+    fn test_typed_graph() {
+        let mut graph = FilterGraph::new();
+        let overlay = graph.add(VideoFilter::overlay("10", "10"));
+        let split = graph.chain(InputStream::video(0), VideoFilter::new("split"));
+        let logo = graph.chain(InputStream::video(1), VideoFilter::scale(120, -1));
+        graph.connect(split.output(0), overlay.input(0));
+        graph.connect(logo.output(0), overlay.input(1));
+        let proxy = graph.chain(split.output(1), VideoFilter::scale(640, -2));
+        let vout = graph.output(overlay.output(0), "vout");
+        let small = graph.output(proxy.output(0), "proxy");
+
+        graph.validate().unwrap();
+        assert_eq!(vout.pad_type(), PadType::Video);
+        assert_eq!(StreamMap::from(&small).to_string(), "[proxy]");
+        assert_eq!(
+            graph.build(),
+            "[0:v]split[v0][v2];[1:v]scale=w=120:h=-1[v1];\
+             [v0][v1]overlay=x=10:y=10[vout];[v2]scale=w=640:h=-2[proxy]"
+        );
+        assert_eq!(graph.output_labels(), vec!["vout", "proxy"]);
+        assert_eq!(graph.input_indices(), vec![0, 1]);
+    }
+
+    #[test]
+    fn test_graph_validation() {
+        // Media type mismatch and an unconnected overlay input
+        let mut graph = FilterGraph::new();
+        let overlay = graph.add(VideoFilter::overlay("0", "0"));
+        graph.connect(InputStream::audio(0), overlay.input(0));
+        graph.output(overlay.output(0), "out");
+        let err = graph.validate().unwrap_err().to_string();
+        assert!(err.contains("audio output connected to video input 0"));
+        assert!(err.contains("input pad 1 of filter #0 (overlay) is not connected"));
+
+        // Output used twice without split
+        let mut graph = FilterGraph::new();
+        let scale = graph.chain(InputStream::video(0), VideoFilter::scale(640, 360));
+        graph.output(scale.output(0), "a");
+        graph.output(scale.output(0), "b");
+        let err = graph.validate().unwrap_err().to_string();
+        assert!(err.contains("used 2 times"));
+
+        // Cycle
+        let mut graph = FilterGraph::new();
+        let a = graph.add(VideoFilter::hflip());
+        let b = graph.add(VideoFilter::vflip());
+        graph.connect(a.output(0), b.input(0));
+        graph.connect(b.output(0), a.input(0));
+        let err = graph.validate().unwrap_err().to_string();
+        assert!(err.contains("cycle between filters #0 (hflip), #1 (vflip)"));
+
+        // Bad and duplicate output names
+        let mut graph = FilterGraph::new();
+        let split = graph.chain(InputStream::audio(0), AudioFilter::new("asplit"));
+        graph.output(split.output(0), "out");
+        graph.output(split.output(1), "out");
+        let err = graph.validate().unwrap_err().to_string();
+        assert!(err.contains("used more than once"));
+    }
+
+    #[test]
+    fn test_pad_inference() {
+        let concat = GraphFilter::from(
+            VideoFilter::new("concat")
+                .param("n", 3)
+                .param("v", 1)
+                .param("a", 1),
+        );
+        let (inputs, outputs) = concat.default_pads();
+        assert_eq!(inputs.len(), 6);
+        assert_eq!(inputs[1], PadType::Audio);
+        assert_eq!(outputs, vec![PadType::Video, PadType::Audio]);
+
+        let amix = GraphFilter::from(AudioFilter::new("amix").param("inputs", 4));
+        assert_eq!(amix.default_pads().0.len(), 4);
+    }
+}
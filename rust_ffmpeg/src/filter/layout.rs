@@ -0,0 +1,550 @@
+use ffmpeg_common::{Error, Result};
+
+use super::{DrawText, Expr, FilterGraph, GraphOutput, PadSource, TextPosition, Var, VideoFilter};
+
+/// Border drawn around each tile
+#[derive(Debug, Clone, PartialEq)]
+struct Border {
+    width: u32,
+    color: String,
+}
+
+/// Per-input decoration shared by the layouts
+#[derive(Debug, Clone, PartialEq)]
+struct Tile {
+    source: PadSource,
+    label: Option<String>,
+}
+
+/// Inputs of a tiled layout and the decoration drawn on each of them
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Tiles {
+    list: Vec<Tile>,
+    border: Option<Border>,
+}
+
+impl Tiles {
+    /// Border width on both sides of a tile
+    fn inset(&self) -> u32 {
+        self.border.as_ref().map_or(0, |border| 2 * border.width)
+    }
+}
+
+/// Builder methods shared by [`Grid`] and [`Stack`]
+pub trait TileLayout: Sized {
+    /// The layout's tiles
+    #[doc(hidden)]
+    fn tiles_mut(&mut self) -> &mut Tiles;
+
+    /// Add the next tile
+    fn input(mut self, source: impl Into<PadSource>) -> Self {
+        self.tiles_mut().list.push(Tile {
+            source: source.into(),
+            label: None,
+        });
+        self
+    }
+
+    /// Add several tiles
+    fn inputs<S: Into<PadSource>>(self, sources: impl IntoIterator<Item = S>) -> Self {
+        sources.into_iter().fold(self, Self::input)
+    }
+
+    /// Add the next tile with a caption, e.g. the camera name
+    fn labeled_input(mut self, source: impl Into<PadSource>, label: impl Into<String>) -> Self {
+        self.tiles_mut().list.push(Tile {
+            source: source.into(),
+            label: Some(label.into()),
+        });
+        self
+    }
+
+    /// Draw a border around every tile
+    fn border(mut self, width: u32, color: impl Into<String>) -> Self {
+        self.tiles_mut().border = Some(Border {
+            width,
+            color: color.into(),
+        });
+        self
+    }
+}
+
+/// Font size of tile labels
+const LABEL_SIZE: u32 = 24;
+
+/// Draw the label, then the border, at the end of a tile's chain
+fn decorate(
+    graph: &mut FilterGraph,
+    mut source: PadSource,
+    label: Option<&str>,
+    border: Option<&Border>,
+) -> PadSource {
+    if let Some(label) = label {
+        let text = DrawText::new(label)
+            .fontsize(LABEL_SIZE)
+            .fontcolor("white")
+            .background("black", 0.6, 6)
+            .position(TextPosition::BottomLeft)
+            .margin(10);
+        source = graph.chain(source, text.build()).output(0).into();
+    }
+    if let Some(border) = border {
+        let width = 2 * border.width;
+        let pad = VideoFilter::new("pad")
+            .param_expr("w", Expr::from(Var::InW) + width)
+            .param_expr("h", Expr::from(Var::InH) + width)
+            .param("x", border.width)
+            .param("y", border.width)
+            .param("color", &border.color);
+        source = graph.chain(source, pad).output(0).into();
+    }
+    source
+}
+
+/// Fit a source into `width`x`height`, letterboxed and centered
+fn fit(graph: &mut FilterGraph, source: PadSource, width: u32, height: u32) -> PadSource {
+    let filters = [
+        VideoFilter::new("scale")
+            .param("w", width)
+            .param("h", height)
+            .param("force_original_aspect_ratio", "decrease"),
+        VideoFilter::pad(width, height),
+        VideoFilter::new("setsar").param("r", 1),
+    ];
+    filters.into_iter().fold(source, |source, filter| {
+        graph.chain(source, filter).output(0).into()
+    })
+}
+
+/// Mosaic of inputs in a grid, built with `xstack`
+///
+/// Every input is scaled to fit its cell; empty cells are filled with black.
+///
+/// ```
+/// use rust_ffmpeg::filter::{Grid, InputStream, TileLayout};
+///
+/// let (graph, mosaic) = Grid::new(2, 640, 360)
+///     .inputs((0..4).map(InputStream::video))
+///     .build("mosaic")
+///     .unwrap();
+/// assert!(graph.build().contains("xstack=inputs=4:layout=0_0|640_0|0_360|640_360[mosaic]"));
+/// assert_eq!(mosaic.label(), "mosaic");
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Grid {
+    columns: usize,
+    cell_width: u32,
+    cell_height: u32,
+    tiles: Tiles,
+}
+
+impl Grid {
+    /// Create a grid with `columns` columns of `cell_width`x`cell_height`
+    /// cells, borders included
+    pub fn new(columns: usize, cell_width: u32, cell_height: u32) -> Self {
+        Self {
+            columns,
+            cell_width,
+            cell_height,
+            tiles: Tiles::default(),
+        }
+    }
+
+    /// Add the grid to an existing graph and expose it as `name`
+    pub fn add_to(&self, graph: &mut FilterGraph, name: &str) -> Result<GraphOutput> {
+        if self.tiles.list.is_empty() || self.columns == 0 {
+            return Err(Error::InvalidArgument(
+                "A grid needs at least one column and one input".to_string(),
+            ));
+        }
+        let inset = self.tiles.inset();
+        if inset >= self.cell_width || inset >= self.cell_height {
+            return Err(Error::InvalidArgument(format!(
+                "Border is wider than the {}x{} cells",
+                self.cell_width, self.cell_height
+            )));
+        }
+
+        let cells: Vec<PadSource> = self
+            .tiles
+            .list
+            .iter()
+            .map(|tile| {
+                let fitted = fit(
+                    graph,
+                    tile.source,
+                    self.cell_width - inset,
+                    self.cell_height - inset,
+                );
+                decorate(
+                    graph,
+                    fitted,
+                    tile.label.as_deref(),
+                    self.tiles.border.as_ref(),
+                )
+            })
+            .collect();
+
+        let [single] = cells.as_slice() else {
+            let layout: Vec<String> = (0..cells.len())
+                .map(|i| {
+                    let x = (i % self.columns) as u64 * u64::from(self.cell_width);
+                    let y = (i / self.columns) as u64 * u64::from(self.cell_height);
+                    format!("{x}_{y}")
+                })
+                .collect();
+            let mut xstack = VideoFilter::new("xstack")
+                .param("inputs", cells.len())
+                .param("layout", layout.join("|"));
+            if !cells.len().is_multiple_of(self.columns) && cells.len() > self.columns {
+                xstack = xstack.param("fill", "black");
+            }
+
+            let node = graph.add(xstack);
+            for (index, cell) in cells.iter().enumerate() {
+                graph.connect(*cell, node.input(index));
+            }
+            return Ok(graph.output(node.output(0), name));
+        };
+
+        // A single cell needs no stacking; pass it through under the name
+        let node = graph.chain(*single, VideoFilter::new("null"));
+        Ok(graph.output(node.output(0), name))
+    }
+
+    /// Build a graph holding only the grid
+    pub fn build(&self, name: &str) -> Result<(FilterGraph, GraphOutput)> {
+        let mut graph = FilterGraph::new();
+        let output = self.add_to(&mut graph, name)?;
+        Ok((graph, output))
+    }
+}
+
+impl TileLayout for Grid {
+    fn tiles_mut(&mut self) -> &mut Tiles {
+        &mut self.tiles
+    }
+}
+
+/// Direction of a [`Stack`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StackDirection {
+    /// Side by side (`hstack`)
+    Horizontal,
+    /// On top of each other (`vstack`)
+    Vertical,
+}
+
+/// Inputs side by side or on top of each other
+///
+/// Inputs are scaled to a common height (`hstack`) or width (`vstack`),
+/// keeping their aspect ratio, as the stack filters require.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Stack {
+    direction: StackDirection,
+    size: u32,
+    tiles: Tiles,
+}
+
+impl Stack {
+    /// Inputs side by side, all scaled to `height`
+    pub fn horizontal(height: u32) -> Self {
+        Self::new(StackDirection::Horizontal, height)
+    }
+
+    /// Inputs on top of each other, all scaled to `width`
+    pub fn vertical(width: u32) -> Self {
+        Self::new(StackDirection::Vertical, width)
+    }
+
+    fn new(direction: StackDirection, size: u32) -> Self {
+        Self {
+            direction,
+            size,
+            tiles: Tiles::default(),
+        }
+    }
+
+    /// Add the stack to an existing graph and expose it as `name`
+    pub fn add_to(&self, graph: &mut FilterGraph, name: &str) -> Result<GraphOutput> {
+        if self.tiles.list.len() < 2 {
+            return Err(Error::InvalidArgument(
+                "A stack needs at least two inputs".to_string(),
+            ));
+        }
+        let inset = self.tiles.inset();
+        let size = self
+            .size
+            .checked_sub(inset)
+            .filter(|&size| size > 0)
+            .ok_or_else(|| {
+                Error::InvalidArgument(format!("Border is wider than the stack size {}", self.size))
+            })?;
+
+        let (filter, scale) = match self.direction {
+            StackDirection::Horizontal => (
+                "hstack",
+                VideoFilter::scale(-2, size.try_into().unwrap_or(i32::MAX)),
+            ),
+            StackDirection::Vertical => (
+                "vstack",
+                VideoFilter::scale(size.try_into().unwrap_or(i32::MAX), -2),
+            ),
+        };
+
+        let sources: Vec<PadSource> = self
+            .tiles
+            .list
+            .iter()
+            .map(|tile| {
+                let scaled = graph.chain(tile.source, scale.clone());
+                let square =
+                    graph.chain(scaled.output(0), VideoFilter::new("setsar").param("r", 1));
+                decorate(
+                    graph,
+                    square.output(0).into(),
+                    tile.label.as_deref(),
+                    self.tiles.border.as_ref(),
+                )
+            })
+            .collect();
+
+        let node = graph.add(VideoFilter::new(filter).param("inputs", sources.len()));
+        for (index, source) in sources.into_iter().enumerate() {
+            graph.connect(source, node.input(index));
+        }
+        Ok(graph.output(node.output(0), name))
+    }
+
+    /// Build a graph holding only the stack
+    pub fn build(&self, name: &str) -> Result<(FilterGraph, GraphOutput)> {
+        let mut graph = FilterGraph::new();
+        let output = self.add_to(&mut graph, name)?;
+        Ok((graph, output))
+    }
+}
+
+impl TileLayout for Stack {
+    fn tiles_mut(&mut self) -> &mut Tiles {
+        &mut self.tiles
+    }
+}
+
+/// Picture-in-picture: a small inset over a main video
+///
+/// ```
+/// use rust_ffmpeg::filter::{InputStream, PictureInPicture, TextPosition};
+///
+/// let (graph, _) = PictureInPicture::new(InputStream::video(0), InputStream::video(1))
+///     .width(480)
+///     .position(TextPosition::BottomRight)
+///     .margin(24)
+///     .build("pip")
+///     .unwrap();
+/// assert!(graph.build().contains("overlay=x=W-w-24:y=H-h-24[pip]"));
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct PictureInPicture {
+    main: PadSource,
+    inset: Tile,
+    width: u32,
+    position: TextPosition,
+    margin: u32,
+    border: Option<Border>,
+}
+
+impl PictureInPicture {
+    /// Inset `inset` over `main`, 320 pixels wide in the top right corner
+    pub fn new(main: impl Into<PadSource>, inset: impl Into<PadSource>) -> Self {
+        Self {
+            main: main.into(),
+            inset: Tile {
+                source: inset.into(),
+                label: None,
+            },
+            width: 320,
+            position: TextPosition::TopRight,
+            margin: 16,
+            border: None,
+        }
+    }
+
+    /// Width of the inset in pixels; the height keeps the aspect ratio
+    pub fn width(mut self, width: u32) -> Self {
+        self.width = width;
+        self
+    }
+
+    /// Corner or edge the inset is placed at
+    pub fn position(mut self, position: TextPosition) -> Self {
+        self.position = position;
+        self
+    }
+
+    /// Distance from the frame edges in pixels
+    pub fn margin(mut self, margin: u32) -> Self {
+        self.margin = margin;
+        self
+    }
+
+    /// Caption drawn on the inset
+    pub fn label(mut self, label: impl Into<String>) -> Self {
+        self.inset.label = Some(label.into());
+        self
+    }
+
+    /// Draw a border around the inset
+    pub fn border(mut self, width: u32, color: impl Into<String>) -> Self {
+        self.border = Some(Border {
+            width,
+            color: color.into(),
+        });
+        self
+    }
+
+    /// Overlay `x`/`y` for the position preset
+    fn placement(&self) -> (Expr, Expr) {
+        let margin = Expr::from(self.margin);
+        let left = margin.clone();
+        let center_x = (Expr::from(Var::MainW) - Expr::from(Var::W)) / 2;
+        let right = Expr::from(Var::MainW) - Expr::from(Var::W) - margin.clone();
+        let top = margin.clone();
+        let center_y = (Expr::from(Var::MainH) - Expr::from(Var::H)) / 2;
+        let bottom = Expr::from(Var::MainH) - Expr::from(Var::H) - margin;
+
+        match self.position {
+            TextPosition::TopLeft => (left, top),
+            TextPosition::TopCenter => (center_x, top),
+            TextPosition::TopRight => (right, top),
+            TextPosition::Center => (center_x, center_y),
+            TextPosition::BottomLeft => (left, bottom),
+            TextPosition::BottomCenter => (center_x, bottom),
+            TextPosition::BottomRight => (right, bottom),
+        }
+    }
+
+    /// Add the composite to an existing graph and expose it as `name`
+    pub fn add_to(&self, graph: &mut FilterGraph, name: &str) -> Result<GraphOutput> {
+        let inset = self.border.as_ref().map_or(0, |border| 2 * border.width);
+        let width = self
+            .width
+            .checked_sub(inset)
+            .filter(|&width| width > 0)
+            .ok_or_else(|| {
+                Error::InvalidArgument(format!(
+                    "Border is wider than the inset width {}",
+                    self.width
+                ))
+            })?;
+
+        let scale = VideoFilter::scale(width.try_into().unwrap_or(i32::MAX), -2);
+        let scaled = graph.chain(self.inset.source, scale).output(0).into();
+        let source = decorate(
+            graph,
+            scaled,
+            self.inset.label.as_deref(),
+            self.border.as_ref(),
+        );
+
+        let (x, y) = self.placement();
+        let overlay = graph.add(VideoFilter::overlay(x, y));
+        graph.connect(self.main, overlay.input(0));
+        graph.connect(source, overlay.input(1));
+        Ok(graph.output(overlay.output(0), name))
+    }
+
+    /// Build a graph holding only the composite
+    pub fn build(&self, name: &str) -> Result<(FilterGraph, GraphOutput)> {
+        let mut graph = FilterGraph::new();
+        let output = self.add_to(&mut graph, name)?;
+        Ok((graph, output))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filter::InputStream;
+
+    fn video(index: usize) -> InputStream {
+        InputStream::video(index)
+    }
+
+    #[test]
+    fn test_grid() {
+        let (graph, output) = Grid::new(2, 640, 360)
+            .labeled_input(video(0), "CAM A")
+            .labeled_input(video(1), "CAM B")
+            .input(video(2))
+            .border(2, "white")
+            .build("mosaic")
+            .unwrap();
+        graph.validate().unwrap();
+        assert_eq!(output.label(), "mosaic");
+
+        let built = graph.build();
+        assert!(built.contains("[0:v]scale=w=636:h=356:force_original_aspect_ratio=decrease[v0]"));
+        assert!(built.contains("drawtext=text=CAM A:"));
+        assert!(built.contains("pad=w=iw+4:h=ih+4:x=2:y=2:color=white"));
+        assert!(built.contains("xstack=inputs=3:layout=0_0|640_0|0_360:fill=black[mosaic]"));
+
+        let (graph, _) = Grid::new(3, 320, 180)
+            .input(video(0))
+            .build("single")
+            .unwrap();
+        graph.validate().unwrap();
+        assert!(graph.build().ends_with("null[single]"));
+
+        assert!(Grid::new(2, 320, 180).build("empty").is_err());
+        assert!(
+            Grid::new(2, 8, 8)
+                .input(video(0))
+                .border(4, "red")
+                .build("x")
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_stack() {
+        let (graph, _) = Stack::horizontal(720)
+            .inputs([video(0), video(1)])
+            .build("side")
+            .unwrap();
+        graph.validate().unwrap();
+        assert_eq!(
+            graph.build(),
+            "[0:v]scale=w=-2:h=720[v0];[v0]setsar=r=1[v2];[1:v]scale=w=-2:h=720[v1];\
+             [v1]setsar=r=1[v3];[v2][v3]hstack=inputs=2[side]"
+        );
+
+        let (graph, _) = Stack::vertical(1280)
+            .labeled_input(video(0), "before")
+            .labeled_input(video(1), "after")
+            .build("compare")
+            .unwrap();
+        graph.validate().unwrap();
+        assert!(graph.build().contains("scale=w=1280:h=-2"));
+        assert!(graph.build().contains("vstack=inputs=2[compare]"));
+
+        assert!(Stack::horizontal(720).input(video(0)).build("one").is_err());
+    }
+
+    #[test]
+    fn test_picture_in_picture() {
+        let mut graph = FilterGraph::new();
+        let main = graph.chain(InputStream::video(0), VideoFilter::scale(1920, 1080));
+        let pip = PictureInPicture::new(main.output(0), video(1))
+            .position(TextPosition::TopLeft)
+            .border(3, "yellow")
+            .label("Speaker")
+            .add_to(&mut graph, "out")
+            .unwrap();
+        graph.validate().unwrap();
+        assert_eq!(pip.label(), "out");
+
+        let built = graph.build();
+        assert!(built.contains("[1:v]scale=w=314:h=-2"));
+        assert!(built.contains("overlay=x=16:y=16[out]"));
+    }
+}
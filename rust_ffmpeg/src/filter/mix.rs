@@ -0,0 +1,476 @@
+use ffmpeg_common::{Duration, Error, Result};
+use std::fmt::{self, Write as _};
+use std::time::Duration as StdDuration;
+
+use super::{AudioFilter, FilterGraph, GraphOutput, PadSource};
+
+/// Which input decides the length of a mix
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MixDuration {
+    /// Until the longest input ends
+    #[default]
+    Longest,
+    /// Until the shortest input ends
+    Shortest,
+    /// Until the first input ends
+    First,
+}
+
+impl MixDuration {
+    /// Value of the `duration` option
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Longest => "longest",
+            Self::Shortest => "shortest",
+            Self::First => "first",
+        }
+    }
+}
+
+/// Whether a weight leaves the level unchanged
+fn is_unity(weight: f64) -> bool {
+    (weight - 1.0).abs() < f64::EPSILON
+}
+
+/// `adelay` shifting all channels by `offset`
+fn delay_filter(offset: Duration) -> AudioFilter {
+    let millis = StdDuration::from(offset).as_millis();
+    AudioFilter::new("adelay")
+        .param("delays", millis)
+        .param("all", 1)
+}
+
+/// One input of an [`AudioMix`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct MixInput {
+    source: PadSource,
+    weight: f64,
+    offset: Option<Duration>,
+}
+
+impl MixInput {
+    /// Input with weight 1 and no offset
+    pub fn new(source: impl Into<PadSource>) -> Self {
+        Self {
+            source: source.into(),
+            weight: 1.0,
+            offset: None,
+        }
+    }
+
+    /// Relative gain of this input in the mix
+    pub fn weight(mut self, weight: f64) -> Self {
+        self.weight = weight;
+        self
+    }
+
+    /// Start this input later in the mix, via `adelay`
+    pub fn offset(mut self, offset: impl Into<Duration>) -> Self {
+        self.offset = Some(offset.into());
+        self
+    }
+}
+
+impl<T: Into<PadSource>> From<T> for MixInput {
+    fn from(source: T) -> Self {
+        Self::new(source)
+    }
+}
+
+/// Mix several audio inputs with `amix`
+///
+/// ```
+/// use rust_ffmpeg::filter::{AudioMix, InputStream, MixInput};
+/// use rust_ffmpeg::Duration;
+///
+/// let (graph, mix) = AudioMix::new()
+///     .input(InputStream::audio(0))
+///     .input(MixInput::new(InputStream::audio(1)).weight(0.3).offset(Duration::from_secs(2)))
+///     .normalize(false)
+///     .build("mix")
+///     .unwrap();
+/// assert_eq!(
+///     graph.build(),
+///     "[1:a]adelay=delays=2000:all=1[a0];\
+///      [0:a][a0]amix=inputs=2:duration=longest:weights=1 0.3:normalize=0[mix]"
+/// );
+/// assert_eq!(mix.label(), "mix");
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AudioMix {
+    inputs: Vec<MixInput>,
+    duration: MixDuration,
+    normalize: Option<bool>,
+    dropout_transition: Option<f64>,
+}
+
+impl AudioMix {
+    /// Create an empty mix
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add an input, either a plain source or a [`MixInput`]
+    pub fn input(mut self, input: impl Into<MixInput>) -> Self {
+        self.inputs.push(input.into());
+        self
+    }
+
+    /// Add several inputs
+    pub fn inputs<I: Into<MixInput>>(self, inputs: impl IntoIterator<Item = I>) -> Self {
+        inputs.into_iter().fold(self, Self::input)
+    }
+
+    /// Which input decides the length of the mix
+    pub fn duration(mut self, duration: MixDuration) -> Self {
+        self.duration = duration;
+        self
+    }
+
+    /// Whether `amix` scales inputs down so the sum cannot clip
+    ///
+    /// On by default in FFmpeg; turn it off to keep the weights as absolute
+    /// gains.
+    pub fn normalize(mut self, normalize: bool) -> Self {
+        self.normalize = Some(normalize);
+        self
+    }
+
+    /// Seconds over which the volume is renormalized when an input ends
+    pub fn dropout_transition(mut self, seconds: f64) -> Self {
+        self.dropout_transition = Some(seconds);
+        self
+    }
+
+    /// The `amix` filter for the current inputs
+    fn amix(&self) -> AudioFilter {
+        let mut amix = AudioFilter::new("amix")
+            .param("inputs", self.inputs.len())
+            .param("duration", self.duration.as_str());
+        if let Some(seconds) = self.dropout_transition {
+            amix = amix.param("dropout_transition", seconds);
+        }
+        if self.inputs.iter().any(|input| !is_unity(input.weight)) {
+            let weights: Vec<String> = self.inputs.iter().map(|i| i.weight.to_string()).collect();
+            amix = amix.param("weights", weights.join(" "));
+        }
+        if let Some(normalize) = self.normalize {
+            amix = amix.param("normalize", u8::from(normalize));
+        }
+        amix
+    }
+
+    /// Add the mix to an existing graph and expose it as `name`
+    pub fn add_to(&self, graph: &mut FilterGraph, name: &str) -> Result<GraphOutput> {
+        if self.inputs.len() < 2 {
+            return Err(Error::InvalidArgument(
+                "A mix needs at least two inputs".to_string(),
+            ));
+        }
+
+        let sources: Vec<PadSource> = self
+            .inputs
+            .iter()
+            .map(|input| match input.offset {
+                Some(offset) => graph
+                    .chain(input.source, delay_filter(offset))
+                    .output(0)
+                    .into(),
+                None => input.source,
+            })
+            .collect();
+
+        let node = graph.add(self.amix());
+        for (index, source) in sources.into_iter().enumerate() {
+            graph.connect(source, node.input(index));
+        }
+        Ok(graph.output(node.output(0), name))
+    }
+
+    /// Build a graph holding only the mix
+    pub fn build(&self, name: &str) -> Result<(FilterGraph, GraphOutput)> {
+        let mut graph = FilterGraph::new();
+        let output = self.add_to(&mut graph, name)?;
+        Ok((graph, output))
+    }
+}
+
+/// Channel gain matrix for the `pan` filter
+///
+/// Each output channel is a weighted sum of input channels, given by index.
+///
+/// ```
+/// use rust_ffmpeg::filter::Pan;
+///
+/// // Swap left and right, with a little crosstalk
+/// let pan = Pan::new("stereo")
+///     .gain("FL", 1, 0.9)
+///     .gain("FL", 0, 0.1)
+///     .gain("FR", 0, 0.9)
+///     .gain("FR", 1, 0.1);
+/// assert_eq!(pan.build().to_string(), "pan=stereo|FL=0.9*c1+0.1*c0|FR=0.9*c0+0.1*c1");
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pan {
+    layout: String,
+    outputs: Vec<(String, Vec<(usize, f64)>)>,
+    renormalize: bool,
+}
+
+impl Pan {
+    /// Pan into an output channel layout, e.g. `mono`, `stereo` or `5.1`
+    pub fn new(layout: impl Into<String>) -> Self {
+        Self {
+            layout: layout.into(),
+            outputs: Vec::new(),
+            renormalize: false,
+        }
+    }
+
+    /// Average the two channels of a stereo input into mono
+    pub fn stereo_to_mono() -> Self {
+        Self::new("mono").gain("c0", 0, 0.5).gain("c0", 1, 0.5)
+    }
+
+    /// Route a mono input to both stereo channels
+    pub fn mono_to_stereo() -> Self {
+        Self::new("stereo").gain("FL", 0, 1.0).gain("FR", 0, 1.0)
+    }
+
+    /// Add `gain` times input channel `input` to output channel `output`
+    ///
+    /// Output channels are named (`FL`, `FR`, `LFE`, ...) or numbered (`c0`).
+    pub fn gain(mut self, output: impl Into<String>, input: usize, gain: f64) -> Self {
+        let output = output.into();
+        match self.outputs.iter_mut().find(|(name, _)| *name == output) {
+            Some((_, terms)) => terms.push((input, gain)),
+            None => self.outputs.push((output, vec![(input, gain)])),
+        }
+        self
+    }
+
+    /// Scale each output channel so its gains sum to 1, avoiding clipping
+    pub fn renormalize(mut self, renormalize: bool) -> Self {
+        self.renormalize = renormalize;
+        self
+    }
+
+    /// Build the filter
+    pub fn build(&self) -> AudioFilter {
+        AudioFilter::new("pan").param("", self)
+    }
+}
+
+impl fmt::Display for Pan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.layout)?;
+        let assign = if self.renormalize { '<' } else { '=' };
+        for (output, terms) in &self.outputs {
+            write!(f, "|{output}{assign}")?;
+            for (i, (input, gain)) in terms.iter().enumerate() {
+                let mut term = String::new();
+                if gain.is_sign_negative() {
+                    term.push('-');
+                } else if i > 0 {
+                    term.push('+');
+                }
+                write!(term, "{}*c{input}", gain.abs())?;
+                f.write_str(&term)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl From<Pan> for AudioFilter {
+    fn from(pan: Pan) -> Self {
+        pan.build()
+    }
+}
+
+/// Duck background music under a voice track with `sidechaincompress`
+///
+/// The voice drives the compressor on the music and is then mixed back on
+/// top, unchanged.
+///
+/// ```
+/// use rust_ffmpeg::filter::{Ducking, InputStream};
+///
+/// let (graph, _) = Ducking::new(InputStream::audio(1), InputStream::audio(0))
+///     .threshold(0.03)
+///     .ratio(10.0)
+///     .build("podcast")
+///     .unwrap();
+/// assert!(graph.build().contains("sidechaincompress=threshold=0.03:ratio=10:attack=20:release=400"));
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Ducking {
+    music: MixInput,
+    voice: MixInput,
+    threshold: f64,
+    ratio: f64,
+    attack_ms: f64,
+    release_ms: f64,
+    duration: MixDuration,
+}
+
+impl Ducking {
+    /// Duck `music` whenever `voice` is active
+    ///
+    /// Either side can be a [`MixInput`] to set its weight or offset.
+    pub fn new(music: impl Into<MixInput>, voice: impl Into<MixInput>) -> Self {
+        Self {
+            music: music.into(),
+            voice: voice.into(),
+            threshold: 0.05,
+            ratio: 8.0,
+            attack_ms: 20.0,
+            release_ms: 400.0,
+            duration: MixDuration::Longest,
+        }
+    }
+
+    /// Voice level (linear, 0 to 1) above which the music is ducked
+    pub fn threshold(mut self, threshold: f64) -> Self {
+        self.threshold = threshold;
+        self
+    }
+
+    /// Compression ratio applied to the music
+    pub fn ratio(mut self, ratio: f64) -> Self {
+        self.ratio = ratio;
+        self
+    }
+
+    /// Milliseconds for the music to duck once the voice starts
+    pub fn attack(mut self, milliseconds: f64) -> Self {
+        self.attack_ms = milliseconds;
+        self
+    }
+
+    /// Milliseconds for the music to come back once the voice stops
+    pub fn release(mut self, milliseconds: f64) -> Self {
+        self.release_ms = milliseconds;
+        self
+    }
+
+    /// Which input decides the length of the result; the music is first
+    pub fn duration(mut self, duration: MixDuration) -> Self {
+        self.duration = duration;
+        self
+    }
+
+    /// Add the ducked mix to an existing graph and expose it as `name`
+    pub fn add_to(&self, graph: &mut FilterGraph, name: &str) -> Result<GraphOutput> {
+        let prepare = |graph: &mut FilterGraph, input: &MixInput| -> PadSource {
+            let mut source = input.source;
+            if let Some(offset) = input.offset {
+                source = graph.chain(source, delay_filter(offset)).output(0).into();
+            }
+            if !is_unity(input.weight) {
+                source = graph
+                    .chain(source, AudioFilter::volume(input.weight))
+                    .output(0)
+                    .into();
+            }
+            source
+        };
+        let music = prepare(graph, &self.music);
+        let voice = prepare(graph, &self.voice);
+
+        // One copy of the voice keys the compressor, the other is mixed in
+        let split = graph.chain(voice, AudioFilter::new("asplit").param("outputs", 2));
+
+        let compressor = graph.add(
+            AudioFilter::new("sidechaincompress")
+                .param("threshold", self.threshold)
+                .param("ratio", self.ratio)
+                .param("attack", self.attack_ms)
+                .param("release", self.release_ms),
+        );
+        graph.connect(music, compressor.input(0));
+        graph.connect(split.output(0), compressor.input(1));
+
+        let mix = AudioMix::new()
+            .input(compressor.output(0))
+            .input(split.output(1))
+            .duration(self.duration)
+            .normalize(false);
+        mix.add_to(graph, name)
+    }
+
+    /// Build a graph holding only the ducked mix
+    pub fn build(&self, name: &str) -> Result<(FilterGraph, GraphOutput)> {
+        let mut graph = FilterGraph::new();
+        let output = self.add_to(&mut graph, name)?;
+        Ok((graph, output))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filter::InputStream;
+
+    #[test]
+    fn test_audio_mix() {
+        let (graph, output) = AudioMix::new()
+            .inputs([
+                InputStream::audio(0),
+                InputStream::audio(1),
+                InputStream::audio(2),
+            ])
+            .duration(MixDuration::First)
+            .dropout_transition(0.5)
+            .build("mix")
+            .unwrap();
+        graph.validate().unwrap();
+        assert_eq!(output.pad_type(), crate::filter::PadType::Audio);
+        assert_eq!(
+            graph.build(),
+            "[0:a][1:a][2:a]amix=inputs=3:duration=first:dropout_transition=0.5[mix]"
+        );
+
+        assert!(
+            AudioMix::new()
+                .input(InputStream::audio(0))
+                .build("solo")
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_pan() {
+        assert_eq!(
+            Pan::stereo_to_mono().build().to_string(),
+            "pan=mono|c0=0.5*c0+0.5*c1"
+        );
+
+        let pan = Pan::new("stereo")
+            .gain("FL", 0, 1.0)
+            .gain("FL", 2, -0.5)
+            .gain("FR", 1, 1.0)
+            .renormalize(true)
+            .build();
+        assert_eq!(pan.to_string(), "pan=stereo|FL<1*c0-0.5*c2|FR<1*c1");
+        assert_eq!(AudioFilter::parse(&pan.to_string()).unwrap(), pan);
+    }
+
+    #[test]
+    fn test_ducking() {
+        let (graph, _) = Ducking::new(
+            MixInput::new(InputStream::audio(1)).weight(0.5),
+            MixInput::new(InputStream::audio(0)).offset(Duration::from_millis(1500)),
+        )
+        .duration(MixDuration::First)
+        .build("out")
+        .unwrap();
+        graph.validate().unwrap();
+
+        let built = graph.build();
+        assert!(built.contains("[1:a]volume=volume=0.5"));
+        assert!(built.contains("[0:a]adelay=delays=1500:all=1"));
+        assert!(built.contains("asplit=outputs=2"));
+        assert!(built.contains("amix=inputs=2:duration=first:normalize=0[out]"));
+    }
+}
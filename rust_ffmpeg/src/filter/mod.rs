@@ -1,8 +1,29 @@
-use ffmpeg_common::{utils, Result};
+use ffmpeg_common::Result;
 use std::fmt;
 
+mod drawtext;
+pub mod escape;
+mod expr;
+mod graph;
+mod layout;
+mod mix;
+mod parser;
+mod timeline;
+
+pub use drawtext::{DrawText, PtsFormat, TextPosition};
+pub use expr::{Expr, PictType, Var};
+pub use graph::{
+    FilterGraph, GraphFilter, GraphOutput, InputPad, InputStream, NodeId, OutputPad, PadSource,
+    PadType,
+};
+pub(crate) use graph::graph_output_labels;
+pub use layout::{Grid, PictureInPicture, Stack, StackDirection, TileLayout, Tiles};
+pub use mix::{AudioMix, Ducking, MixDuration, MixInput, Pan};
+pub use parser::{ParsedChain, ParsedFilter, ParsedGraph};
+pub use timeline::Timeline;
+
 /// Video filter
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct VideoFilter {
     name: String,
     params: Vec<(String, String)>,
@@ -23,6 +44,32 @@ impl VideoFilter {
         self
     }
 
+    /// Add an expression parameter, escaped for use in a filter graph
+    ///
+    /// Plain strings are taken as unescaped expression text.
+    pub fn param_expr(mut self, key: impl Into<String>, expr: impl Into<Expr>) -> Self {
+        self.params.push((key.into(), expr.into().to_graph_value()));
+        self
+    }
+
+    /// Filter name, including an `@instance` suffix if any
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Parameters as written; positional options have an empty key
+    pub fn params(&self) -> &[(String, String)] {
+        &self.params
+    }
+
+    /// Unescaped value of a parameter (use `""` for the first positional one)
+    pub fn option(&self, key: &str) -> Option<String> {
+        self.params
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| escape::unescape(&escape::unescape(v)))
+    }
+
     /// Scale filter
     pub fn scale(width: i32, height: i32) -> Self {
         Self::new("scale")
@@ -101,16 +148,15 @@ impl VideoFilter {
     }
 
     /// Overlay filter
-    pub fn overlay(x: impl Into<String>, y: impl Into<String>) -> Self {
+    pub fn overlay(x: impl Into<Expr>, y: impl Into<Expr>) -> Self {
         Self::new("overlay")
-            .param("x", x.into())
-            .param("y", y.into())
+            .param_expr("x", x)
+            .param_expr("y", y)
     }
 
-    /// Draw text
+    /// Draw text, see [`DrawText`] for styling and positioning
     pub fn drawtext(text: impl Into<String>) -> Self {
-        Self::new("drawtext")
-            .param("text", utils::escape_filter_string(&text.into()))
+        DrawText::new(text.into()).build()
     }
 
     /// Fade in
@@ -129,13 +175,13 @@ impl VideoFilter {
     }
 
     /// Set PTS (presentation timestamp)
-    pub fn setpts(expr: impl Into<String>) -> Self {
-        Self::new("setpts").param("expr", expr.into())
+    pub fn setpts(expr: impl Into<Expr>) -> Self {
+        Self::new("setpts").param_expr("expr", expr)
     }
 
     /// Select frames
-    pub fn select(expr: impl Into<String>) -> Self {
-        Self::new("select").param("expr", expr.into())
+    pub fn select(expr: impl Into<Expr>) -> Self {
+        Self::new("select").param_expr("expr", expr)
     }
 
     /// EQ (brightness/contrast/saturation)
@@ -175,21 +221,26 @@ impl VideoFilter {
 
 impl fmt::Display for VideoFilter {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", self.name)?;
-        if !self.params.is_empty() {
-            write!(f, "=")?;
-            let params: Vec<String> = self.params
-                .iter()
-                .map(|(k, v)| format!("{}={}", k, v))
-                .collect();
-            write!(f, "{}", params.join(":"))?;
+        write_filter(f, &self.name, &self.params)
+    }
+}
+
+/// Write `name=key=value:...`, with positional options as bare values
+fn write_filter(f: &mut fmt::Formatter<'_>, name: &str, params: &[(String, String)]) -> fmt::Result {
+    write!(f, "{name}")?;
+    for (i, (key, value)) in params.iter().enumerate() {
+        let separator = if i == 0 { '=' } else { ':' };
+        if key.is_empty() {
+            write!(f, "{separator}{value}")?;
+        } else {
+            write!(f, "{separator}{key}={value}")?;
         }
-        Ok(())
     }
+    Ok(())
 }
 
 /// Audio filter
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct AudioFilter {
     name: String,
     params: Vec<(String, String)>,
@@ -210,6 +261,32 @@ impl AudioFilter {
         self
     }
 
+    /// Add an expression parameter, escaped for use in a filter graph
+    ///
+    /// Plain strings are taken as unescaped expression text.
+    pub fn param_expr(mut self, key: impl Into<String>, expr: impl Into<Expr>) -> Self {
+        self.params.push((key.into(), expr.into().to_graph_value()));
+        self
+    }
+
+    /// Filter name, including an `@instance` suffix if any
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Parameters as written; positional options have an empty key
+    pub fn params(&self) -> &[(String, String)] {
+        &self.params
+    }
+
+    /// Unescaped value of a parameter (use `""` for the first positional one)
+    pub fn option(&self, key: &str) -> Option<String> {
+        self.params
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| escape::unescape(&escape::unescape(v)))
+    }
+
     /// Volume adjustment
     pub fn volume(level: f64) -> Self {
         Self::new("volume").param("volume", level)
@@ -316,16 +393,7 @@ impl AudioFilter {
 
 impl fmt::Display for AudioFilter {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", self.name)?;
-        if !self.params.is_empty() {
-            write!(f, "=")?;
-            let params: Vec<String> = self.params
-                .iter()
-                .map(|(k, v)| format!("{}={}", k, v))
-                .collect();
-            write!(f, "{}", params.join(":"))?;
-        }
-        Ok(())
+        write_filter(f, &self.name, &self.params)
     }
 }
 
@@ -339,86 +407,6 @@ pub enum TransposeDirection {
     ClockwiseFlip = 3,
 }
 
-/// Complex filter graph builder
-#[derive(Debug, Clone, Default)]
-pub struct FilterGraph {
-    nodes: Vec<FilterNode>,
-    edges: Vec<FilterEdge>,
-}
-
-#[derive(Debug, Clone)]
-struct FilterNode {
-    id: String,
-    filter: String,
-    inputs: Vec<String>,
-    outputs: Vec<String>,
-}
-
-#[derive(Debug, Clone)]
-struct FilterEdge {
-    from: String,
-    to: String,
-}
-
-impl FilterGraph {
-    /// Create a new filter graph
-    pub fn new() -> Self {
-        Self::default()
-    }
-
-    /// Add a filter node
-    pub fn add_filter(
-        mut self,
-        filter: impl Into<String>,
-        inputs: Vec<String>,
-        outputs: Vec<String>,
-    ) -> Self {
-        let id = format!("f{}", self.nodes.len());
-        self.nodes.push(FilterNode {
-            id: id.clone(),
-            filter: filter.into(),
-            inputs,
-            outputs,
-        });
-        self
-    }
-
-    /// Connect two filter nodes
-    pub fn connect(mut self, from: impl Into<String>, to: impl Into<String>) -> Self {
-        self.edges.push(FilterEdge {
-            from: from.into(),
-            to: to.into(),
-        });
-        self
-    }
-
-    /// Build the filter graph string
-    pub fn build(&self) -> String {
-        let mut parts = Vec::new();
-
-        for node in &self.nodes {
-            let mut part = String::new();
-
-            // Inputs
-            if !node.inputs.is_empty() {
-                part.push_str(&node.inputs.join(""));
-            }
-
-            // Filter
-            part.push_str(&node.filter);
-
-            // Outputs
-            if !node.outputs.is_empty() {
-                part.push_str(&node.outputs.join(""));
-            }
-
-            parts.push(part);
-        }
-
-        parts.join(";")
-    }
-}
-
 /// Common filter chains
 pub mod chains {
     use super::*;
@@ -426,7 +414,7 @@ pub mod chains {
     /// Create a thumbnail extraction filter chain
     pub fn thumbnail() -> Vec<VideoFilter> {
         vec![
-            VideoFilter::select("eq(pict_type\\,I)"),
+            VideoFilter::select(Expr::from(Var::PictType).equals(PictType::I)),
             VideoFilter::scale(320, -1),
         ]
     }
@@ -530,15 +518,4 @@ mod tests {
         let tempo = AudioFilter::atempo(1.5);
         assert_eq!(tempo.to_string(), "atempo=tempo=1.5");
     }
-
-    #[test]
-    fn test_filter_graph() {
-        let graph = FilterGraph::new()
-            .add_filter("scale=640:480", vec!["[0:v]".to_string()], vec!["[scaled]".to_string()])
-            .add_filter("overlay", vec!["[scaled]".to_string(), "[1:v]".to_string()], vec!["[out]".to_string()]);
-
-        let result = graph.build();
-        assert!(result.contains("[0:v]scale=640:480[scaled]"));
-        assert!(result.contains("[scaled][1:v]overlay[out]"));
-    }
-}
\ No newline at end of file
+}
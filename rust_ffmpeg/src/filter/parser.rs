@@ -0,0 +1,695 @@
+use ffmpeg_common::{Error, Result};
+use std::collections::HashMap;
+use std::fmt;
+
+use super::graph::{GraphFilter, PadType, graph_output_labels};
+use super::{AudioFilter, FilterGraph, VideoFilter};
+
+const WHITESPACE: [char; 4] = [' ', '\n', '\t', '\r'];
+
+/// Common audio filters, for filters whose input type cannot be told from
+/// their links
+const AUDIO_FILTERS: &[&str] = &[
+    "acompressor",
+    "acopy",
+    "acrossfade",
+    "adelay",
+    "aecho",
+    "afade",
+    "afftdn",
+    "aformat",
+    "agate",
+    "alimiter",
+    "aloop",
+    "amerge",
+    "amix",
+    "anull",
+    "apad",
+    "aresample",
+    "areverse",
+    "asetpts",
+    "asetrate",
+    "asplit",
+    "astats",
+    "atempo",
+    "atrim",
+    "bandpass",
+    "bandreject",
+    "bass",
+    "channelmap",
+    "channelsplit",
+    "compand",
+    "dynaudnorm",
+    "ebur128",
+    "equalizer",
+    "highpass",
+    "join",
+    "loudnorm",
+    "lowpass",
+    "pan",
+    "silencedetect",
+    "silenceremove",
+    "treble",
+    "volume",
+    "volumedetect",
+];
+
+/// Filter graph parsed from FFmpeg's filtergraph syntax
+///
+/// Option values are kept exactly as written, escapes and quotes included,
+/// so printing a parsed graph gives back the same description.
+///
+/// ```
+/// use rust_ffmpeg::filter::ParsedGraph;
+///
+/// let graph = ParsedGraph::parse("[0:v]scale=640:-2,format=yuv420p[v];[1:a]anull[a]").unwrap();
+/// assert_eq!(graph.chains.len(), 2);
+/// assert_eq!(graph.output_labels(), vec!["v", "a"]);
+/// assert_eq!(graph.to_string(), "[0:v]scale=640:-2,format=yuv420p[v];[1:a]anull[a]");
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ParsedGraph {
+    /// Scaler flags from a leading `sws_flags=...;` section
+    pub sws_flags: Option<String>,
+    /// Filter chains, separated by `;`
+    pub chains: Vec<ParsedChain>,
+}
+
+/// Chain of filters separated by `,`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ParsedChain {
+    /// Filters in chain order
+    pub filters: Vec<ParsedFilter>,
+}
+
+/// Filter together with its link labels
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParsedFilter {
+    /// Input link labels, without brackets
+    pub inputs: Vec<String>,
+    /// Filter name and options, typed by the media it reads
+    pub filter: GraphFilter,
+    /// Output link labels, without brackets
+    pub outputs: Vec<String>,
+}
+
+impl ParsedGraph {
+    /// Parse a `-filter_complex`, `-vf` or `-af` description
+    pub fn parse(description: &str) -> Result<Self> {
+        let mut graph = Parser::new(description).graph()?;
+        graph.infer_media();
+        Ok(graph)
+    }
+
+    /// Decide whether each filter reads video or audio from the streams and
+    /// links feeding it, falling back to its name when nothing feeds it
+    fn infer_media(&mut self) {
+        let mut links: HashMap<String, PadType> = HashMap::new();
+        // A second pass picks up links that are used before they are defined
+        for _ in 0..2 {
+            for chain in &mut self.chains {
+                let mut previous = None;
+                for parsed in &mut chain.filters {
+                    let fed = parsed.inputs.iter().find_map(|label| {
+                        stream_media(label).or_else(|| links.get(label).copied())
+                    });
+                    let media = fed
+                        .or(previous)
+                        .unwrap_or_else(|| media_by_name(parsed.filter.name()));
+                    parsed.filter = parsed.filter.clone().with_media(media);
+
+                    let (_, outputs) = parsed.filter.default_pads();
+                    let first = outputs.first().copied().unwrap_or(media);
+                    for (index, label) in parsed.outputs.iter().enumerate() {
+                        links.insert(label.clone(), outputs.get(index).copied().unwrap_or(first));
+                    }
+                    previous = Some(first);
+                }
+            }
+        }
+    }
+
+    /// All filters of all chains, in order
+    pub fn filters(&self) -> impl Iterator<Item = &ParsedFilter> {
+        self.chains.iter().flat_map(|chain| chain.filters.iter())
+    }
+
+    /// All filters of all chains, in order, for in-place edits
+    pub fn filters_mut(&mut self) -> impl Iterator<Item = &mut ParsedFilter> {
+        self.chains
+            .iter_mut()
+            .flat_map(|chain| chain.filters.iter_mut())
+    }
+
+    /// Labels read by the graph but not produced by it, e.g. `0:v`
+    pub fn input_labels(&self) -> Vec<String> {
+        let produced: Vec<&String> = self.filters().flat_map(|f| &f.outputs).collect();
+        let mut labels: Vec<String> = Vec::new();
+        for label in self.filters().flat_map(|f| &f.inputs) {
+            if !produced.contains(&label) && !labels.contains(label) {
+                labels.push(label.clone());
+            }
+        }
+        labels
+    }
+
+    /// Labels produced by the graph and not consumed inside it
+    pub fn output_labels(&self) -> Vec<String> {
+        graph_output_labels(&self.to_string())
+    }
+
+    /// Print the graph with one chain per line
+    ///
+    /// The result parses back to the same graph.
+    pub fn pretty(&self) -> String {
+        self.join(";\n")
+    }
+
+    fn join(&self, separator: &str) -> String {
+        let mut parts: Vec<String> = self
+            .sws_flags
+            .iter()
+            .map(|flags| format!("sws_flags={flags}"))
+            .collect();
+        parts.extend(self.chains.iter().map(ToString::to_string));
+        parts.join(separator)
+    }
+}
+
+impl fmt::Display for ParsedGraph {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.join(";"))
+    }
+}
+
+impl fmt::Display for ParsedChain {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let filters: Vec<String> = self.filters.iter().map(ToString::to_string).collect();
+        write!(f, "{}", filters.join(","))
+    }
+}
+
+impl fmt::Display for ParsedFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for label in &self.inputs {
+            write!(f, "[{label}]")?;
+        }
+        write!(f, "{}", self.filter)?;
+        for label in &self.outputs {
+            write!(f, "[{label}]")?;
+        }
+        Ok(())
+    }
+}
+
+impl From<ParsedGraph> for FilterGraph {
+    /// Convert into a graph of raw nodes, one per chain, keeping all labels
+    fn from(parsed: ParsedGraph) -> Self {
+        let mut graph = FilterGraph::new();
+        if let Some(flags) = parsed.sws_flags {
+            // Emitted as its own `;`-separated section ahead of the chains
+            graph = graph.add_filter(format!("sws_flags={flags}"), Vec::new(), Vec::new());
+        }
+        let bracket = |labels: &mut Vec<String>| -> Vec<String> {
+            labels.drain(..).map(|label| format!("[{label}]")).collect()
+        };
+        for mut chain in parsed.chains {
+            let inputs = chain.filters.first_mut().map(|f| bracket(&mut f.inputs));
+            let outputs = chain.filters.last_mut().map(|f| bracket(&mut f.outputs));
+            graph = graph.add_filter(
+                chain.to_string(),
+                inputs.unwrap_or_default(),
+                outputs.unwrap_or_default(),
+            );
+        }
+        graph
+    }
+}
+
+impl FilterGraph {
+    /// Parse an existing filter graph description
+    ///
+    /// Each chain becomes a raw node that keeps its hand-written labels; use
+    /// [`ParsedGraph::parse`] to inspect or edit individual filters.
+    pub fn parse(description: &str) -> Result<Self> {
+        ParsedGraph::parse(description).map(Into::into)
+    }
+}
+
+impl VideoFilter {
+    /// Parse a single filter such as `scale=w=640:h=-2`
+    pub fn parse(description: &str) -> Result<Self> {
+        let mut filters = Self::parse_chain(description)?;
+        if filters.len() != 1 {
+            return Err(Error::ParseError(format!(
+                "expected a single filter, found {} in '{description}'",
+                filters.len()
+            )));
+        }
+        Ok(filters.remove(0))
+    }
+
+    /// Parse a simple filter chain as passed to `-vf`
+    pub fn parse_chain(description: &str) -> Result<Vec<Self>> {
+        let graph = ParsedGraph::parse(description)?;
+        if graph.chains.len() > 1 || graph.sws_flags.is_some() {
+            return Err(Error::ParseError(format!(
+                "'{description}' is a filter graph, not a single chain"
+            )));
+        }
+        graph
+            .chains
+            .into_iter()
+            .flat_map(|chain| chain.filters)
+            .map(|parsed| {
+                if parsed.inputs.is_empty() && parsed.outputs.is_empty() {
+                    Ok(parsed.filter.into_video())
+                } else {
+                    Err(Error::ParseError(format!(
+                        "link labels are not allowed in a simple filter chain: '{parsed}'"
+                    )))
+                }
+            })
+            .collect()
+    }
+}
+
+impl AudioFilter {
+    /// Parse a single filter such as `volume=0.5`
+    pub fn parse(description: &str) -> Result<Self> {
+        VideoFilter::parse(description).map(Into::into)
+    }
+
+    /// Parse a simple filter chain as passed to `-af`
+    pub fn parse_chain(description: &str) -> Result<Vec<Self>> {
+        VideoFilter::parse_chain(description)
+            .map(|filters| filters.into_iter().map(Into::into).collect())
+    }
+}
+
+impl From<VideoFilter> for AudioFilter {
+    fn from(filter: VideoFilter) -> Self {
+        Self {
+            name: filter.name,
+            params: filter.params,
+        }
+    }
+}
+
+/// Media type of an input stream label such as `0:a` or `1:v:0`
+fn stream_media(label: &str) -> Option<PadType> {
+    let mut parts = label.split(':');
+    let index = parts.next()?;
+    if index.is_empty() || !index.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    match parts.next()? {
+        "a" => Some(PadType::Audio),
+        "v" | "V" => Some(PadType::Video),
+        _ => None,
+    }
+}
+
+/// Media type a filter reads, guessed from its name
+fn media_by_name(name: &str) -> PadType {
+    let name = name.split('@').next().unwrap_or(name);
+    let (inputs, outputs) = GraphFilter::from(VideoFilter::new(name)).default_pads();
+    let pads = if inputs.is_empty() { outputs } else { inputs };
+    if AUDIO_FILTERS.contains(&name) || pads.first() == Some(&PadType::Audio) {
+        PadType::Audio
+    } else {
+        PadType::Video
+    }
+}
+
+/// Split filter arguments into options at the unescaped `:` separators
+///
+/// The arguments are written at the graph level, so a character is only a
+/// separator if it is still special after the graph level has been removed.
+fn split_options(args: &str) -> Vec<(String, String)> {
+    let mut chunks = Vec::new();
+    let mut start = 0;
+    let mut graph_quoted = false;
+    let mut option_escaped = false;
+    let mut option_quoted = false;
+    let mut chars = args.char_indices();
+
+    while let Some((pos, c)) = chars.next() {
+        // Character as seen by the option level, after graph-level unescaping
+        let unescaped = if graph_quoted {
+            if c == '\'' {
+                graph_quoted = false;
+                continue;
+            }
+            c
+        } else {
+            match c {
+                '\\' => match chars.next() {
+                    Some((_, next)) => next,
+                    None => continue,
+                },
+                '\'' => {
+                    graph_quoted = true;
+                    continue;
+                }
+                c => c,
+            }
+        };
+
+        if option_escaped {
+            option_escaped = false;
+        } else if unescaped == '\\' {
+            option_escaped = true;
+        } else if unescaped == '\'' {
+            option_quoted = !option_quoted;
+        } else if unescaped == ':' && !option_quoted {
+            chunks.push(&args[start..pos]);
+            start = pos + c.len_utf8();
+        }
+    }
+    chunks.push(&args[start..]);
+
+    chunks
+        .into_iter()
+        .map(|chunk| {
+            let key_len = chunk
+                .find(|c: char| !(c.is_ascii_alphanumeric() || "-_/.".contains(c)))
+                .unwrap_or(chunk.len());
+            if key_len > 0 && chunk[key_len..].starts_with('=') {
+                (
+                    chunk[..key_len].to_string(),
+                    chunk[key_len + 1..].to_string(),
+                )
+            } else {
+                (String::new(), chunk.to_string())
+            }
+        })
+        .collect()
+}
+
+struct Parser<'a> {
+    src: &'a str,
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn new(src: &'a str) -> Self {
+        Self { src, pos: 0 }
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.src[self.pos..]
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = self.rest();
+        self.pos += rest.len() - rest.trim_start_matches(WHITESPACE).len();
+    }
+
+    fn error(&self, message: impl fmt::Display) -> Error {
+        Error::ParseError(format!(
+            "{message} at position {} in filter graph '{}'",
+            self.pos, self.src
+        ))
+    }
+
+    fn graph(&mut self) -> Result<ParsedGraph> {
+        let mut graph = ParsedGraph::default();
+
+        self.skip_whitespace();
+        if let Some(flags) = self.rest().strip_prefix("sws_flags=") {
+            let Some(end) = flags.find(';') else {
+                return Err(self.error("sws_flags not terminated with ';'"));
+            };
+            graph.sws_flags = Some(flags[..end].trim().to_string());
+            self.pos += "sws_flags=".len() + end + 1;
+            self.skip_whitespace();
+        }
+
+        if self.peek().is_none() {
+            return Ok(graph);
+        }
+
+        loop {
+            graph.chains.push(self.chain()?);
+            self.skip_whitespace();
+            match self.peek() {
+                None => return Ok(graph),
+                Some(';') => self.pos += 1,
+                Some(c) => return Err(self.error(format!("unexpected '{c}'"))),
+            }
+        }
+    }
+
+    fn chain(&mut self) -> Result<ParsedChain> {
+        let mut chain = ParsedChain::default();
+        loop {
+            chain.filters.push(self.filter()?);
+            self.skip_whitespace();
+            if self.peek() == Some(',') {
+                self.pos += 1;
+            } else {
+                return Ok(chain);
+            }
+        }
+    }
+
+    fn filter(&mut self) -> Result<ParsedFilter> {
+        let inputs = self.labels()?;
+
+        let name = self.token(&['=', ',', ';', '[', ']'])?;
+        if name.is_empty() {
+            return Err(self.error("expected a filter name"));
+        }
+        let mut filter = VideoFilter::new(name);
+
+        if self.peek() == Some('=') {
+            self.pos += 1;
+            let args = self.token(&['[', ']', ',', ';'])?;
+            if !args.is_empty() {
+                filter.params = split_options(args);
+            }
+        }
+
+        let outputs = self.labels()?;
+        Ok(ParsedFilter {
+            inputs,
+            filter: filter.into(),
+            outputs,
+        })
+    }
+
+    fn labels(&mut self) -> Result<Vec<String>> {
+        let mut labels = Vec::new();
+        self.skip_whitespace();
+        while self.peek() == Some('[') {
+            let Some(end) = self.rest().find(']') else {
+                return Err(self.error("unterminated link label"));
+            };
+            let label = &self.rest()[1..end];
+            if label.is_empty() {
+                return Err(self.error("empty link label"));
+            }
+            labels.push(label.to_string());
+            self.pos += end + 1;
+            self.skip_whitespace();
+        }
+        Ok(labels)
+    }
+
+    /// Read raw text up to an unescaped, unquoted terminator, without the
+    /// surrounding whitespace
+    fn token(&mut self, terminators: &[char]) -> Result<&'a str> {
+        self.skip_whitespace();
+        let start = self.pos;
+        let mut end = start;
+        let mut chars = self.rest().char_indices();
+
+        while let Some((offset, c)) = chars.next() {
+            let at = start + offset;
+            match c {
+                '\\' => {
+                    end = chars
+                        .next()
+                        .map_or(at + 1, |(o, c)| start + o + c.len_utf8());
+                }
+                '\'' => {
+                    let Some((o, _)) = chars.by_ref().find(|&(_, c)| c == '\'') else {
+                        self.pos = at;
+                        return Err(self.error("unterminated quote"));
+                    };
+                    end = start + o + 1;
+                }
+                c if terminators.contains(&c) => {
+                    self.pos = at;
+                    return Ok(&self.src[start..end]);
+                }
+                c => {
+                    if !WHITESPACE.contains(&c) {
+                        end = at + c.len_utf8();
+                    }
+                }
+            }
+        }
+
+        self.pos = self.src.len();
+        Ok(&self.src[start..end])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filter::Timeline;
+    use crate::filter::escape::escape_value;
+    use ffmpeg_common::Duration;
+
+    #[test]
+    fn test_parse_graph() {
+        let text = "[0:v][1:v]overlay=x=W-w-10:y=10[bg];[bg]split=2[a][b];\
+                    [a]scale=640:-2,format=yuv420p[small];[b]null[big]";
+        let graph = ParsedGraph::parse(text).unwrap();
+
+        assert_eq!(graph.chains.len(), 4);
+        let overlay = &graph.chains[0].filters[0];
+        assert_eq!(overlay.inputs, vec!["0:v", "1:v"]);
+        assert_eq!(overlay.filter.name(), "overlay");
+        assert_eq!(overlay.filter.option("x").as_deref(), Some("W-w-10"));
+        assert_eq!(overlay.outputs, vec!["bg"]);
+
+        let scale = &graph.chains[2].filters[0];
+        assert_eq!(scale.filter.params()[1], (String::new(), "-2".to_string()));
+        assert!(
+            graph
+                .filters()
+                .all(|f| matches!(f.filter, GraphFilter::Video(_)))
+        );
+
+        assert_eq!(graph.to_string(), text);
+        assert_eq!(graph.input_labels(), vec!["0:v", "1:v"]);
+        assert_eq!(graph.output_labels(), vec!["small", "big"]);
+        assert_eq!(ParsedGraph::parse(&graph.pretty()).unwrap(), graph);
+    }
+
+    #[test]
+    fn test_parse_escaping() {
+        // Both escaping levels, quoting and separators inside values
+        let text = r"drawtext=text=it\\\'s 10\\\:00:fontcolor=white,select='eq(n,0)+gt(t\,1)':n=2";
+        let graph = ParsedGraph::parse(text).unwrap();
+        let filters: Vec<&ParsedFilter> = graph.filters().collect();
+
+        assert_eq!(filters.len(), 2);
+        assert_eq!(filters[0].filter.params().len(), 2);
+        assert_eq!(
+            filters[0].filter.option("text").as_deref(),
+            Some("it's 10:00")
+        );
+        assert_eq!(
+            filters[1].filter.option("").as_deref(),
+            Some("eq(n,0)+gt(t,1)")
+        );
+        assert_eq!(filters[1].filter.option("n").as_deref(), Some("2"));
+        assert_eq!(graph.to_string(), text);
+
+        // A graph-level escape alone leaves `:` special at the option level
+        let filter = VideoFilter::parse(r"drawtext=text=a\:b").unwrap();
+        assert_eq!(filter.params().len(), 2);
+        let filter = VideoFilter::parse(r"drawtext=text='a\:b'").unwrap();
+        assert_eq!(filter.option("text").as_deref(), Some("a:b"));
+
+        // Values built with escape_value survive a round trip
+        let value = "50% [done], ok; it's 12:00";
+        let filter = VideoFilter::new("drawtext").param("text", escape_value(value));
+        let parsed = VideoFilter::parse(&filter.to_string()).unwrap();
+        assert_eq!(parsed.option("text").as_deref(), Some(value));
+    }
+
+    #[test]
+    fn test_parse_sws_flags_and_whitespace() {
+        let graph = ParsedGraph::parse(
+            "sws_flags=lanczos;\n  [0:v] scale=1280:720 [v] ;\n  [0:a] volume=0.5 [a]\n",
+        )
+        .unwrap();
+        assert_eq!(graph.sws_flags.as_deref(), Some("lanczos"));
+        assert_eq!(
+            graph.to_string(),
+            "sws_flags=lanczos;[0:v]scale=1280:720[v];[0:a]volume=0.5[a]"
+        );
+
+        let filter_graph = FilterGraph::from(graph);
+        assert_eq!(
+            filter_graph.build(),
+            "sws_flags=lanczos;[0:v]scale=1280:720[v];[0:a]volume=0.5[a]"
+        );
+        assert_eq!(filter_graph.output_labels(), vec!["v", "a"]);
+    }
+
+    #[test]
+    fn test_parse_media_types() {
+        // From input streams, links defined later and the filter name
+        let graph = ParsedGraph::parse(
+            "[mix]loudnorm[out];[0:a][1:a]amix=inputs=2,volume=2[mix];\
+             [0:a]showwaves=s=640x120,format=yuv420p[waves];volume=0.5",
+        )
+        .unwrap();
+        let media: Vec<PadType> = graph.filters().map(|f| f.filter.media()).collect();
+        assert_eq!(
+            media,
+            [
+                PadType::Audio,
+                PadType::Audio,
+                PadType::Audio,
+                PadType::Audio,
+                PadType::Video,
+                PadType::Audio
+            ]
+        );
+
+        // Audio filters can take audio-only transforms such as timeline edits
+        let mut graph = ParsedGraph::parse("[0:a]volume=2[a]").unwrap();
+        let parsed = graph.filters_mut().next().unwrap();
+        let GraphFilter::Audio(volume) = parsed.filter.clone() else {
+            panic!("volume was not parsed as an audio filter");
+        };
+        let timeline = Timeline::between(Duration::from_secs(1), Duration::from_secs(2));
+        parsed.filter = volume.enable(timeline).into();
+        assert!(graph.to_string().starts_with("[0:a]volume=2:enable="));
+    }
+
+    #[test]
+    fn test_parse_chain_labels() {
+        // Labels in the middle of a chain are kept on their filter
+        let text = "[0:v]split[main],scale=320:-1,[main]overlay[out]";
+        let graph = ParsedGraph::parse(text).unwrap();
+        assert_eq!(graph.chains[0].filters[0].outputs, vec!["main"]);
+        assert_eq!(graph.chains[0].filters[2].inputs, vec!["main"]);
+        assert_eq!(FilterGraph::parse(text).unwrap().build(), text);
+
+        let chain = AudioFilter::parse_chain("highpass=f=80, volume=2").unwrap();
+        assert_eq!(chain.len(), 2);
+        assert_eq!(chain[1].to_string(), "volume=2");
+        assert!(VideoFilter::parse_chain("[0:v]scale=640:360").is_err());
+        assert!(VideoFilter::parse_chain("null;null").is_err());
+        assert!(VideoFilter::parse("null,null").is_err());
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(ParsedGraph::parse("").unwrap().chains.is_empty());
+        for text in [
+            "[0:v",
+            "[]null",
+            "scale=640:360;",
+            "drawtext=text='open",
+            "[a][b]",
+            "null]",
+        ] {
+            let err = ParsedGraph::parse(text).unwrap_err();
+            assert!(matches!(err, Error::ParseError(_)), "{text}: {err}");
+        }
+    }
+}
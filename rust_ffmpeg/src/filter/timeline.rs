@@ -0,0 +1,169 @@
+use ffmpeg_common::{Capabilities, Duration, Error, Result};
+use std::ops::Range;
+use std::time::Duration as StdDuration;
+
+use super::{AudioFilter, Expr, Var, VideoFilter};
+
+/// When a timeline-capable filter is active, rendered as its `enable` option
+///
+/// ```
+/// use rust_ffmpeg::filter::{Timeline, VideoFilter};
+/// use rust_ffmpeg::Duration;
+///
+/// let blur = VideoFilter::blur(10)
+///     .enable(Timeline::between(Duration::from_secs(12), Duration::from_secs(18)));
+/// assert_eq!(blur.to_string(), r"boxblur=lr=10:lp=1:enable=between(t\,12\,18)");
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Timeline(Expr);
+
+fn seconds(time: impl Into<Duration>) -> Expr {
+    Expr::from(StdDuration::from(time.into()).as_secs_f64())
+}
+
+impl Timeline {
+    /// Active from `start` to `end`, inclusive, in seconds of stream time
+    pub fn between(start: impl Into<Duration>, end: impl Into<Duration>) -> Self {
+        Self(Expr::from(Var::T).between(seconds(start), seconds(end)))
+    }
+
+    /// Active from `start` onwards
+    pub fn after(start: impl Into<Duration>) -> Self {
+        Self(Expr::from(Var::T).gte(seconds(start)))
+    }
+
+    /// Active until `end`
+    pub fn before(end: impl Into<Duration>) -> Self {
+        Self(Expr::from(Var::T).lt(seconds(end)))
+    }
+
+    /// Active for frame numbers `start` to `end`, inclusive
+    pub fn frames(start: u64, end: u64) -> Self {
+        Self(Expr::from(Var::N).between(start, end))
+    }
+
+    /// Active while an arbitrary expression is non-zero
+    pub fn expr(expr: impl Into<Expr>) -> Self {
+        Self(expr.into())
+    }
+
+    /// Active whenever either timeline is
+    pub fn or(self, other: impl Into<Timeline>) -> Self {
+        Self(self.0.or(other.into().0))
+    }
+
+    /// Underlying expression
+    pub fn as_expr(&self) -> &Expr {
+        &self.0
+    }
+}
+
+impl From<Expr> for Timeline {
+    fn from(expr: Expr) -> Self {
+        Self(expr)
+    }
+}
+
+impl<T: Into<Duration>> From<Range<T>> for Timeline {
+    fn from(range: Range<T>) -> Self {
+        Self::between(range.start, range.end)
+    }
+}
+
+/// Replace the `enable` option of a filter
+fn set_enable(params: &mut Vec<(String, String)>, timeline: &Timeline) {
+    params.retain(|(key, _)| key != "enable");
+    params.push(("enable".to_string(), timeline.as_expr().to_graph_value()));
+}
+
+/// Fail if the filter has an `enable` option but the capability data says
+/// it does not support timeline editing
+fn check_timeline(name: &str, params: &[(String, String)], caps: &Capabilities) -> Result<()> {
+    if !params.iter().any(|(key, _)| key == "enable") {
+        return Ok(());
+    }
+    // Instance names (`boxblur@face`) are not part of the filter name
+    let name = name.split('@').next().unwrap_or(name);
+    match caps.supports_timeline(name) {
+        Some(false) => Err(Error::Unsupported(format!(
+            "filter '{name}' does not support timeline editing (enable=)"
+        ))),
+        _ => Ok(()),
+    }
+}
+
+impl VideoFilter {
+    /// Only apply the filter during the given timeline
+    pub fn enable(mut self, timeline: impl Into<Timeline>) -> Self {
+        set_enable(&mut self.params, &timeline.into());
+        self
+    }
+
+    /// Check an `enable` option against the filter's timeline support
+    ///
+    /// Passes when the capabilities hold no filter data.
+    pub fn check_timeline(&self, caps: &Capabilities) -> Result<()> {
+        check_timeline(&self.name, &self.params, caps)
+    }
+}
+
+impl AudioFilter {
+    /// Only apply the filter during the given timeline
+    pub fn enable(mut self, timeline: impl Into<Timeline>) -> Self {
+        set_enable(&mut self.params, &timeline.into());
+        self
+    }
+
+    /// Check an `enable` option against the filter's timeline support
+    ///
+    /// Passes when the capabilities hold no filter data.
+    pub fn check_timeline(&self, caps: &Capabilities) -> Result<()> {
+        check_timeline(&self.name, &self.params, caps)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_timeline_enable() {
+        let mute =
+            AudioFilter::volume(0.0).enable(Duration::from_secs(185)..Duration::from_secs(187));
+        assert_eq!(
+            mute.to_string(),
+            r"volume=volume=0:enable=between(t\,185\,187)"
+        );
+        assert_eq!(mute.option("enable").as_deref(), Some("between(t,185,187)"));
+
+        // Setting it again replaces the previous range
+        let blur = VideoFilter::blur(5)
+            .enable(Timeline::before(Duration::from_millis(1500)))
+            .enable(Timeline::frames(10, 20).or(Timeline::after(Duration::from_secs(60))));
+        assert_eq!(
+            blur.option("enable").as_deref(),
+            Some("gt(between(n,10,20)+gte(t,60),0)")
+        );
+        assert_eq!(
+            blur.params().iter().filter(|(k, _)| k == "enable").count(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_timeline_capabilities() {
+        let mut caps = Capabilities::default();
+        let fps = VideoFilter::fps(25.0).enable(Timeline::after(Duration::from_secs(1)));
+        assert!(fps.check_timeline(&caps).is_ok());
+
+        caps.parse_filters(" TSC boxblur  V->V  Blur.\n ... fps  V->V  Framerate.");
+        assert!(matches!(
+            fps.check_timeline(&caps),
+            Err(Error::Unsupported(_))
+        ));
+        assert!(VideoFilter::fps(25.0).check_timeline(&caps).is_ok());
+
+        let blur = VideoFilter::new("boxblur@face").enable(Timeline::expr("gte(t,1)"));
+        assert!(blur.check_timeline(&caps).is_ok());
+    }
+}
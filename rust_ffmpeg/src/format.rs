@@ -24,6 +24,11 @@ impl FormatOptions {
         self
     }
 
+    /// Format name, if set
+    pub fn format_name(&self) -> Option<&str> {
+        self.format.as_deref()
+    }
+
     /// Add a format option
     pub fn option(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
         self.options.insert(key.into(), value.into());
@@ -36,6 +41,16 @@ impl FormatOptions {
         self
     }
 
+    /// Format-specific options
+    pub fn options(&self) -> &HashMap<String, String> {
+        &self.options
+    }
+
+    /// Muxer flags
+    pub fn flags(&self) -> &[String] {
+        &self.flags
+    }
+
     /// Build command line arguments
     pub fn build_args(&self) -> Vec<String> {
         let mut cmd = CommandBuilder::new();
@@ -187,6 +202,9 @@ pub mod formats {
     }
 
     /// DASH format options
+    ///
+    /// See [`DashPackage`](crate::dash::DashPackage) for adaptation sets and
+    /// low-latency settings.
     pub struct Dash;
 
     impl Dash {
@@ -0,0 +1,836 @@
+//! HLS packaging with variant streams and rendition groups
+//!
+//! An [`HlsPackage`] is written by a single `hls` muxer output: every video
+//! variant, audio rendition and subtitle rendition is mapped into it, and
+//! `var_stream_map` splits them into media playlists referenced from one
+//! master playlist.
+//!
+//! ```no_run
+//! use rust_ffmpeg::hls::{HlsEncryption, HlsFlag, HlsPackage, HlsRendition, HlsSegmentType, HlsVariant};
+//! use rust_ffmpeg::{CodecOptions, FFmpegBuilder, Input, StreamMap};
+//! use ffmpeg_common::Codec;
+//!
+//! # async fn example() -> ffmpeg_common::Result<()> {
+//! let output = HlsPackage::new("out")
+//!     .variant(
+//!         HlsVariant::new("1080p", StreamMap::video_from(0))
+//!             .codec(CodecOptions::new(Codec::h264()).bitrate("5000k").size(1920, 1080)),
+//!     )
+//!     .variant(
+//!         HlsVariant::new("720p", StreamMap::video_from(0))
+//!             .codec(CodecOptions::new(Codec::h264()).bitrate("2800k").size(1280, 720)),
+//!     )
+//!     .audio(
+//!         HlsRendition::new("en", StreamMap::audio_from(0))
+//!             .language("en")
+//!             .default(true)
+//!             .codec(CodecOptions::new(Codec::aac()).bitrate("128k")),
+//!     )
+//!     .segment_type(HlsSegmentType::Fmp4)
+//!     .flag(HlsFlag::IndependentSegments)
+//!     // The key is served separately, never uploaded next to the segments
+//!     .encryption(HlsEncryption::generate("https://keys.example.com/k1", "keys/k1.key")?)
+//!     .into_output()?;
+//!
+//! FFmpegBuilder::new()?
+//!     .input(Input::new("master.mov"))
+//!     .output(output)
+//!     .run()
+//!     .await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use ffmpeg_common::{Codec, Error, MediaPath, Result, StreamSpecifier, StreamType};
+use std::collections::HashSet;
+use std::fmt::Write as _;
+use std::io::{self, Write as _};
+use std::path::{Component, Path, PathBuf};
+
+use crate::codec::CodecOptions;
+use crate::output::Output;
+use crate::stream::StreamMap;
+
+/// Segment container
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum HlsSegmentType {
+    /// MPEG transport stream segments (`.ts`)
+    #[default]
+    MpegTs,
+    /// Fragmented MP4 segments (`.m4s`) with a per-variant init segment
+    Fmp4,
+}
+
+impl HlsSegmentType {
+    /// Value of `-hls_segment_type`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::MpegTs => "mpegts",
+            Self::Fmp4 => "fmp4",
+        }
+    }
+
+    /// Extension used by the default segment template
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::MpegTs => "ts",
+            Self::Fmp4 => "m4s",
+        }
+    }
+}
+
+/// Media playlist type
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum HlsPlaylistType {
+    /// Complete playlist with every segment and `#EXT-X-ENDLIST`
+    #[default]
+    Vod,
+    /// Growing playlist that never drops segments
+    Event,
+    /// Sliding-window live playlist (no `#EXT-X-PLAYLIST-TYPE` tag)
+    Live,
+}
+
+/// Value of `-hls_flags`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum HlsFlag {
+    /// Every segment starts with a keyframe (`#EXT-X-INDEPENDENT-SEGMENTS`)
+    IndependentSegments,
+    /// Remove segments that left the live window
+    DeleteSegments,
+    /// Emit `#EXT-X-PROGRAM-DATE-TIME` for every segment
+    ProgramDateTime,
+    /// Write segments to a temporary name and rename them when complete
+    TempFile,
+    /// Do not append `#EXT-X-ENDLIST`
+    OmitEndlist,
+    /// Cut segments at `hls_time` even without a keyframe
+    SplitByTime,
+    /// Continue an existing playlist instead of overwriting it
+    AppendList,
+}
+
+impl HlsFlag {
+    /// Flag name
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::IndependentSegments => "independent_segments",
+            Self::DeleteSegments => "delete_segments",
+            Self::ProgramDateTime => "program_date_time",
+            Self::TempFile => "temp_file",
+            Self::OmitEndlist => "omit_endlist",
+            Self::SplitByTime => "split_by_time",
+            Self::AppendList => "append_list",
+        }
+    }
+}
+
+/// Video variant of the master playlist
+#[derive(Debug, Clone)]
+pub struct HlsVariant {
+    name: String,
+    source: StreamMap,
+    codec: Option<CodecOptions>,
+}
+
+impl HlsVariant {
+    /// Create a variant named `name` (used for `%v`) from a video stream
+    pub fn new(name: impl Into<String>, source: StreamMap) -> Self {
+        Self {
+            name: name.into(),
+            source,
+            codec: None,
+        }
+    }
+
+    /// Encoder settings; the bitrate also becomes the advertised bandwidth
+    pub fn codec(mut self, codec: CodecOptions) -> Self {
+        self.codec = Some(codec);
+        self
+    }
+}
+
+/// Alternate audio or subtitle rendition
+#[derive(Debug, Clone)]
+pub struct HlsRendition {
+    name: String,
+    source: StreamMap,
+    language: Option<String>,
+    default: bool,
+    codec: Option<CodecOptions>,
+}
+
+impl HlsRendition {
+    /// Create a rendition named `name` (used for `%v`) from a stream
+    pub fn new(name: impl Into<String>, source: StreamMap) -> Self {
+        Self {
+            name: name.into(),
+            source,
+            language: None,
+            default: false,
+            codec: None,
+        }
+    }
+
+    /// Language tag (`en`, `pt-BR`, ...)
+    pub fn language(mut self, language: impl Into<String>) -> Self {
+        self.language = Some(language.into());
+        self
+    }
+
+    /// Make this the group's default rendition
+    pub fn default(mut self, default: bool) -> Self {
+        self.default = default;
+        self
+    }
+
+    /// Encoder settings
+    pub fn codec(mut self, codec: CodecOptions) -> Self {
+        self.codec = Some(codec);
+        self
+    }
+}
+
+/// AES-128 segment encryption
+#[derive(Clone, PartialEq, Eq)]
+pub struct HlsEncryption {
+    key: [u8; 16],
+    key_uri: String,
+    key_file: PathBuf,
+    iv: Option<[u8; 16]>,
+}
+
+impl std::fmt::Debug for HlsEncryption {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("HlsEncryption")
+            .field("key_uri", &self.key_uri)
+            .field("key_file", &self.key_file)
+            .field("iv", &self.iv.map(|iv| hex(&iv)))
+            .finish_non_exhaustive()
+    }
+}
+
+impl HlsEncryption {
+    /// Encrypt with a freshly generated random key, fetched by players from
+    /// `key_uri` (relative to the media playlists, or absolute)
+    ///
+    /// The key is written to `key_file` when the job starts. It must lie
+    /// outside the package directory, which is usually published as a whole.
+    pub fn generate(key_uri: impl Into<String>, key_file: impl Into<PathBuf>) -> Result<Self> {
+        let mut key = [0u8; 16];
+        getrandom::fill(&mut key).map_err(|e| Error::Io(io::Error::other(e.to_string())))?;
+        Ok(Self::with_key(key, key_uri, key_file))
+    }
+
+    /// Encrypt with an existing key, written to `key_file` when the job starts
+    pub fn with_key(
+        key: [u8; 16],
+        key_uri: impl Into<String>,
+        key_file: impl Into<PathBuf>,
+    ) -> Self {
+        Self {
+            key,
+            key_uri: key_uri.into(),
+            key_file: key_file.into(),
+            iv: None,
+        }
+    }
+
+    /// Fixed initialization vector; by default the segment sequence number is used
+    pub fn iv(mut self, iv: [u8; 16]) -> Self {
+        self.iv = Some(iv);
+        self
+    }
+
+    /// The content key
+    pub fn key(&self) -> &[u8; 16] {
+        &self.key
+    }
+
+    /// Contents of the `hls_key_info_file`
+    pub fn key_info(&self) -> String {
+        let mut info = format!("{}\n{}\n", self.key_uri, self.key_file.display());
+        if let Some(iv) = self.iv {
+            let _ = writeln!(info, "{}", hex(&iv));
+        }
+        info
+    }
+}
+
+/// Absolute form of `path` with `.` and `..` resolved without touching the
+/// filesystem
+fn lexical_absolute(path: &Path) -> Result<PathBuf> {
+    let mut resolved = PathBuf::new();
+    for component in std::path::absolute(path)?.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                resolved.pop();
+            }
+            other => resolved.push(other),
+        }
+    }
+    Ok(resolved)
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .fold(String::with_capacity(bytes.len() * 2), |mut s, b| {
+            let _ = write!(s, "{b:02x}");
+            s
+        })
+}
+
+/// Multi-variant HLS package
+#[derive(Debug, Clone)]
+pub struct HlsPackage {
+    directory: PathBuf,
+    variants: Vec<HlsVariant>,
+    audio: Vec<HlsRendition>,
+    subtitles: Vec<HlsRendition>,
+    audio_group: String,
+    subtitle_group: String,
+    master_playlist: String,
+    playlist_template: String,
+    segment_template: Option<String>,
+    init_filename: String,
+    segment_type: HlsSegmentType,
+    playlist_type: HlsPlaylistType,
+    segment_duration: f64,
+    list_size: Option<u32>,
+    flags: Vec<HlsFlag>,
+    encryption: Option<HlsEncryption>,
+}
+
+impl HlsPackage {
+    /// Create a package written below `directory`
+    pub fn new(directory: impl Into<PathBuf>) -> Self {
+        Self {
+            directory: directory.into(),
+            variants: Vec::new(),
+            audio: Vec::new(),
+            subtitles: Vec::new(),
+            audio_group: "audio".to_string(),
+            subtitle_group: "subs".to_string(),
+            master_playlist: "master.m3u8".to_string(),
+            playlist_template: "%v/index.m3u8".to_string(),
+            segment_template: None,
+            init_filename: "init.mp4".to_string(),
+            segment_type: HlsSegmentType::default(),
+            playlist_type: HlsPlaylistType::default(),
+            segment_duration: 6.0,
+            list_size: None,
+            flags: Vec::new(),
+            encryption: None,
+        }
+    }
+
+    /// Add a video variant
+    pub fn variant(mut self, variant: HlsVariant) -> Self {
+        self.variants.push(variant);
+        self
+    }
+
+    /// Add an audio rendition to the audio group
+    pub fn audio(mut self, rendition: HlsRendition) -> Self {
+        self.audio.push(rendition);
+        self
+    }
+
+    /// Add a subtitle rendition (WebVTT) to the subtitle group
+    pub fn subtitle(mut self, rendition: HlsRendition) -> Self {
+        self.subtitles.push(rendition);
+        self
+    }
+
+    /// Group id of the audio renditions (`audio` by default)
+    pub fn audio_group(mut self, id: impl Into<String>) -> Self {
+        self.audio_group = id.into();
+        self
+    }
+
+    /// Group id of the subtitle renditions (`subs` by default)
+    pub fn subtitle_group(mut self, id: impl Into<String>) -> Self {
+        self.subtitle_group = id.into();
+        self
+    }
+
+    /// Master playlist name (`master.m3u8` by default)
+    pub fn master_playlist(mut self, name: impl Into<String>) -> Self {
+        self.master_playlist = name.into();
+        self
+    }
+
+    /// Media playlist path relative to the directory (`%v/index.m3u8` by default)
+    pub fn playlist_template(mut self, template: impl Into<String>) -> Self {
+        self.playlist_template = template.into();
+        self
+    }
+
+    /// Segment path relative to the directory (`%v/segment_%05d.<ext>` by default)
+    pub fn segment_template(mut self, template: impl Into<String>) -> Self {
+        self.segment_template = Some(template.into());
+        self
+    }
+
+    /// fMP4 init segment name, written next to each media playlist
+    pub fn init_filename(mut self, name: impl Into<String>) -> Self {
+        self.init_filename = name.into();
+        self
+    }
+
+    /// Segment container
+    pub fn segment_type(mut self, segment_type: HlsSegmentType) -> Self {
+        self.segment_type = segment_type;
+        self
+    }
+
+    /// Playlist type
+    pub fn playlist_type(mut self, playlist_type: HlsPlaylistType) -> Self {
+        self.playlist_type = playlist_type;
+        self
+    }
+
+    /// Target segment duration in seconds
+    pub fn segment_duration(mut self, seconds: f64) -> Self {
+        self.segment_duration = seconds;
+        self
+    }
+
+    /// Number of segments kept in live playlists
+    pub fn list_size(mut self, size: u32) -> Self {
+        self.list_size = Some(size);
+        self
+    }
+
+    /// Add an `hls_flags` entry
+    pub fn flag(mut self, flag: HlsFlag) -> Self {
+        if !self.flags.contains(&flag) {
+            self.flags.push(flag);
+        }
+        self
+    }
+
+    /// Encrypt segments with AES-128
+    pub fn encryption(mut self, encryption: HlsEncryption) -> Self {
+        self.encryption = Some(encryption);
+        self
+    }
+
+    /// The `var_stream_map` value
+    ///
+    /// Output stream indices follow the mapping order used by
+    /// [`into_output`](Self::into_output): variants, then audio, then
+    /// subtitle renditions.
+    pub fn var_stream_map(&self) -> String {
+        let audio_group = !self.variants.is_empty() && !self.audio.is_empty();
+        let subtitle_group = !self.subtitles.is_empty();
+        let mut entries = Vec::new();
+
+        for (i, variant) in self.variants.iter().enumerate() {
+            let mut entry = format!("v:{i}");
+            if audio_group {
+                let _ = write!(entry, ",agroup:{}", self.audio_group);
+            }
+            if subtitle_group {
+                let _ = write!(entry, ",sgroup:{}", self.subtitle_group);
+            }
+            let _ = write!(entry, ",name:{}", variant.name);
+            entries.push(entry);
+        }
+
+        let renditions = [
+            ("a", audio_group.then_some(&self.audio_group), &self.audio),
+            ("s", Some(&self.subtitle_group), &self.subtitles),
+        ];
+        for (kind, group, list) in renditions {
+            for (i, rendition) in list.iter().enumerate() {
+                let mut entry = format!("{kind}:{i}");
+                if let Some(group) = group {
+                    let key = if kind == "a" { "agroup" } else { "sgroup" };
+                    let _ = write!(entry, ",{key}:{group}");
+                }
+                if let Some(ref language) = rendition.language {
+                    let _ = write!(entry, ",language:{language}");
+                }
+                let _ = write!(entry, ",name:{}", rendition.name);
+                if rendition.default {
+                    entry.push_str(",default:yes");
+                }
+                entries.push(entry);
+            }
+        }
+
+        entries.join(" ")
+    }
+
+    fn validate(&self) -> Result<()> {
+        if self.variants.is_empty() && self.audio.is_empty() {
+            return Err(Error::InvalidArgument(
+                "HLS package needs at least one variant or audio rendition".to_string(),
+            ));
+        }
+        if !self.subtitles.is_empty() && self.variants.is_empty() {
+            return Err(Error::InvalidArgument(
+                "HLS subtitle renditions need at least one video variant".to_string(),
+            ));
+        }
+        if !self.segment_duration.is_finite() || self.segment_duration <= 0.0 {
+            return Err(Error::InvalidArgument(format!(
+                "HLS segment duration must be positive, got {}",
+                self.segment_duration
+            )));
+        }
+
+        let valid_token = |s: &str| !s.is_empty() && !s.contains([' ', ',', ':']);
+        for group in [&self.audio_group, &self.subtitle_group] {
+            if !valid_token(group) {
+                return Err(Error::InvalidArgument(format!(
+                    "invalid HLS group id '{group}'"
+                )));
+            }
+        }
+
+        let mut names = HashSet::new();
+        let renditions = self.audio.iter().chain(&self.subtitles);
+        let all = self
+            .variants
+            .iter()
+            .map(|v| (&v.name, None))
+            .chain(renditions.map(|r| (&r.name, r.language.as_ref())));
+        for (name, language) in all {
+            if !valid_token(name) || language.is_some_and(|l| !valid_token(l)) {
+                return Err(Error::InvalidArgument(format!(
+                    "invalid HLS stream name or language for '{name}'"
+                )));
+            }
+            if !names.insert(name) {
+                return Err(Error::InvalidArgument(format!(
+                    "duplicate HLS stream name '{name}'"
+                )));
+            }
+        }
+
+        for (kind, list) in [("audio", &self.audio), ("subtitle", &self.subtitles)] {
+            if list.iter().filter(|r| r.default).count() > 1 {
+                return Err(Error::InvalidArgument(format!(
+                    "more than one default {kind} rendition"
+                )));
+            }
+        }
+
+        let streams = self.variants.len() + self.audio.len() + self.subtitles.len();
+        let templates = [
+            Some(&self.playlist_template),
+            self.segment_template.as_ref(),
+        ];
+        for template in templates.into_iter().flatten() {
+            if streams > 1 && !template.contains("%v") {
+                return Err(Error::InvalidArgument(format!(
+                    "HLS path template '{template}' must contain %v for multiple streams"
+                )));
+            }
+        }
+
+        if let Some(encryption) = &self.encryption
+            && lexical_absolute(&encryption.key_file)?
+                .starts_with(lexical_absolute(&self.directory)?)
+        {
+            return Err(Error::InvalidArgument(format!(
+                "HLS key file {} must be outside the package directory",
+                encryption.key_file.display()
+            )));
+        }
+
+        if self.flags.contains(&HlsFlag::DeleteSegments)
+            && self.playlist_type != HlsPlaylistType::Live
+        {
+            return Err(Error::InvalidArgument(
+                "delete_segments requires a live playlist".to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Build the `hls` output
+    ///
+    /// With encryption configured this writes a temporary key info file,
+    /// which stays alive as long as the output or the process running it.
+    /// The key itself is only written when the job starts.
+    pub fn into_output(self) -> Result<Output> {
+        self.validate()?;
+
+        let segment_template = self
+            .segment_template
+            .clone()
+            .unwrap_or_else(|| format!("%v/segment_%05d.{}", self.segment_type.extension()));
+
+        let mut output = Output::new(MediaPath::from_path(
+            self.directory.join(&self.playlist_template),
+        ))
+        .format("hls")
+        .option("hls_time", self.segment_duration.to_string())
+        .option("hls_segment_type", self.segment_type.as_str())
+        .option(
+            "hls_segment_filename",
+            self.directory.join(&segment_template).to_string_lossy(),
+        )
+        .option("master_pl_name", &self.master_playlist)
+        .option("var_stream_map", self.var_stream_map());
+
+        if self.segment_type == HlsSegmentType::Fmp4 {
+            output = output.option("hls_fmp4_init_filename", &self.init_filename);
+        }
+
+        output = match self.playlist_type {
+            HlsPlaylistType::Vod => output.option("hls_playlist_type", "vod"),
+            HlsPlaylistType::Event => output.option("hls_playlist_type", "event"),
+            HlsPlaylistType::Live => output,
+        };
+        let list_size = match self.playlist_type {
+            HlsPlaylistType::Live => self.list_size,
+            _ => Some(self.list_size.unwrap_or(0)),
+        };
+        if let Some(size) = list_size {
+            output = output.option("hls_list_size", size.to_string());
+        }
+
+        if !self.flags.is_empty() {
+            let flags: Vec<_> = self.flags.iter().map(HlsFlag::as_str).collect();
+            output = output.option("hls_flags", flags.join("+"));
+        }
+
+        for (i, variant) in self.variants.into_iter().enumerate() {
+            output = output.map(variant.source);
+            if let Some(codec) = variant.codec {
+                output = output
+                    .stream_codec_opts(StreamSpecifier::TypeIndex(StreamType::Video, i), codec);
+            }
+        }
+        for (i, rendition) in self.audio.into_iter().enumerate() {
+            output = output.map(rendition.source);
+            if let Some(codec) = rendition.codec {
+                output = output
+                    .stream_codec_opts(StreamSpecifier::TypeIndex(StreamType::Audio, i), codec);
+            }
+        }
+        for (i, rendition) in self.subtitles.into_iter().enumerate() {
+            let codec = rendition
+                .codec
+                .unwrap_or_else(|| CodecOptions::new(Codec::new("webvtt")));
+            output = output
+                .map(rendition.source)
+                .stream_codec_opts(StreamSpecifier::TypeIndex(StreamType::Subtitle, i), codec);
+        }
+
+        if let Some(encryption) = self.encryption {
+            let mut info = tempfile::Builder::new()
+                .prefix("hls-keyinfo-")
+                .suffix(".txt")
+                .tempfile()?;
+            info.write_all(encryption.key_info().as_bytes())?;
+            info.flush()?;
+            let path = info.into_temp_path();
+
+            output = output
+                .option("hls_key_info_file", path.to_string_lossy())
+                .keep_temp_file(path)
+                .write_on_start(encryption.key_file, encryption.key.to_vec());
+        }
+
+        Ok(output)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn value<'a>(args: &'a [String], key: &str) -> Option<&'a str> {
+        args.iter()
+            .position(|a| a == key)
+            .map(|i| args[i + 1].as_str())
+    }
+
+    fn ladder() -> HlsPackage {
+        HlsPackage::new("out")
+            .variant(
+                HlsVariant::new("1080p", StreamMap::video_from(0))
+                    .codec(CodecOptions::new(Codec::h264()).bitrate("5000k")),
+            )
+            .variant(
+                HlsVariant::new("720p", StreamMap::video_from(0))
+                    .codec(CodecOptions::new(Codec::h264()).bitrate("2800k")),
+            )
+            .audio(
+                HlsRendition::new("audio_en", StreamMap::audio_from(0))
+                    .language("en")
+                    .default(true),
+            )
+            .audio(HlsRendition::new("audio_de", StreamMap::audio_from(1)).language("de"))
+    }
+
+    #[test]
+    fn test_var_stream_map() {
+        assert_eq!(
+            ladder().var_stream_map(),
+            "v:0,agroup:audio,name:1080p v:1,agroup:audio,name:720p \
+             a:0,agroup:audio,language:en,name:audio_en,default:yes \
+             a:1,agroup:audio,language:de,name:audio_de"
+        );
+
+        let subs = ladder()
+            .subtitle(HlsRendition::new("subs_en", StreamMap::subtitle_from(2)).language("en"));
+        let map = subs.var_stream_map();
+        assert!(map.starts_with("v:0,agroup:audio,sgroup:subs,name:1080p"));
+        assert!(map.ends_with("s:0,sgroup:subs,language:en,name:subs_en"));
+
+        let audio_only =
+            HlsPackage::new("out").audio(HlsRendition::new("aac", StreamMap::audio_from(0)));
+        assert_eq!(audio_only.var_stream_map(), "a:0,name:aac");
+    }
+
+    #[test]
+    fn test_into_output() {
+        let output = ladder()
+            .segment_type(HlsSegmentType::Fmp4)
+            .flag(HlsFlag::IndependentSegments)
+            .flag(HlsFlag::ProgramDateTime)
+            .into_output()
+            .unwrap();
+        let args = output.build_args();
+
+        assert_eq!(value(&args, "-f"), Some("hls"));
+        assert_eq!(value(&args, "-hls_segment_type"), Some("fmp4"));
+        assert_eq!(value(&args, "-hls_fmp4_init_filename"), Some("init.mp4"));
+        assert_eq!(value(&args, "-hls_playlist_type"), Some("vod"));
+        assert_eq!(value(&args, "-hls_list_size"), Some("0"));
+        assert_eq!(value(&args, "-master_pl_name"), Some("master.m3u8"));
+        assert_eq!(
+            value(&args, "-hls_flags"),
+            Some("independent_segments+program_date_time")
+        );
+        assert_eq!(
+            value(&args, "-hls_segment_filename").map(PathBuf::from),
+            Some(Path::new("out").join("%v/segment_%05d.m4s"))
+        );
+        assert_eq!(value(&args, "-b:v:1"), Some("2800k"));
+        assert_eq!(args.iter().filter(|a| *a == "-map").count(), 4);
+        assert_eq!(
+            args.last().map(PathBuf::from),
+            Some(Path::new("out").join("%v/index.m3u8"))
+        );
+    }
+
+    #[test]
+    fn test_live_playlist() {
+        let args = HlsPackage::new("live")
+            .variant(HlsVariant::new("main", StreamMap::video_from(0)))
+            .playlist_template("index.m3u8")
+            .segment_template("seg_%d.ts")
+            .playlist_type(HlsPlaylistType::Live)
+            .list_size(6)
+            .flag(HlsFlag::DeleteSegments)
+            .into_output()
+            .unwrap()
+            .build_args();
+
+        assert_eq!(value(&args, "-hls_playlist_type"), None);
+        assert_eq!(value(&args, "-hls_list_size"), Some("6"));
+        assert_eq!(value(&args, "-hls_flags"), Some("delete_segments"));
+    }
+
+    #[test]
+    fn test_validation() {
+        assert!(HlsPackage::new("out").into_output().is_err());
+        assert!(
+            ladder()
+                .flag(HlsFlag::DeleteSegments)
+                .into_output()
+                .is_err()
+        );
+        assert!(
+            ladder()
+                .playlist_template("index.m3u8")
+                .into_output()
+                .is_err()
+        );
+        assert!(
+            ladder()
+                .audio(HlsRendition::new("audio_en", StreamMap::audio_from(1)))
+                .into_output()
+                .is_err()
+        );
+        assert!(
+            ladder()
+                .audio(HlsRendition::new("fr", StreamMap::audio_from(1)).default(true))
+                .into_output()
+                .is_err()
+        );
+        assert!(
+            HlsPackage::new("out")
+                .variant(HlsVariant::new("hd 1080", StreamMap::video_from(0)))
+                .into_output()
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_encryption_key_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let key_file = dir.path().join("keys/k1.key");
+        let encryption =
+            HlsEncryption::with_key([7; 16], "https://keys.example.com/k1", &key_file).iv([1; 16]);
+        let output = HlsPackage::new(dir.path().join("out"))
+            .variant(HlsVariant::new("main", StreamMap::video_from(0)))
+            .encryption(encryption)
+            .into_output()
+            .unwrap();
+        let args = output.build_args();
+        let info_path = value(&args, "-hls_key_info_file").unwrap();
+        let info = std::fs::read_to_string(info_path).unwrap();
+        let lines: Vec<_> = info.lines().collect();
+
+        assert_eq!(lines[0], "https://keys.example.com/k1");
+        assert_eq!(Path::new(lines[1]), key_file);
+        assert_eq!(lines[2], "01".repeat(16));
+        assert_eq!(output.temp_files().len(), 1);
+
+        // Nothing is written until the job starts
+        assert!(!key_file.exists());
+        for file in output.startup_files() {
+            file.write().unwrap();
+        }
+        assert_eq!(std::fs::read(&key_file).unwrap(), vec![7; 16]);
+    }
+
+    #[test]
+    fn test_key_file_outside_package() {
+        for key_file in ["out/enc.key", "out/keys/../enc.key", "./out/enc.key"] {
+            let result = ladder()
+                .encryption(HlsEncryption::with_key([7; 16], "enc.key", key_file))
+                .into_output();
+            assert!(result.is_err(), "{key_file}");
+        }
+        assert!(
+            ladder()
+                .encryption(HlsEncryption::with_key(
+                    [7; 16],
+                    "../enc.key",
+                    "out/../enc.key"
+                ))
+                .into_output()
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn test_generated_keys_differ() {
+        let a = HlsEncryption::generate("k", "k.key").unwrap();
+        let b = HlsEncryption::generate("k", "k.key").unwrap();
+        assert_ne!(a.key(), b.key());
+        assert!(!format!("{a:?}").contains(&hex(a.key())));
+    }
+}
@@ -1,6 +1,13 @@
 use ffmpeg_common::{CommandBuilder, Duration, MediaPath, PixelFormat, Result, Size, Error};
 use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::io::Write;
+use std::path::{self, Path};
+use std::sync::Arc;
 use std::time::Duration as StdDuration;
+use tempfile::TempPath;
+
+use crate::filter::{AudioFilter, FilterGraph, GraphOutput, InputStream, PadSource, VideoFilter};
 
 /// Input specification for FFmpeg
 #[derive(Debug, Clone)]
@@ -39,6 +46,8 @@ pub struct Input {
     buffer_size: Option<Size>,
     /// Discard threshold
     discard_threshold: Option<StdDuration>,
+    /// Temporary files the input reads, removed when the last clone is dropped
+    temp_files: Vec<Arc<TempPath>>,
 }
 
 impl Input {
@@ -62,9 +71,15 @@ impl Input {
             hwaccel_device: None,
             buffer_size: None,
             discard_threshold: None,
+            temp_files: Vec::new(),
         }
     }
 
+    /// Get the source path or URL
+    pub fn source(&self) -> &MediaPath {
+        &self.source
+    }
+
     /// Force input format
     pub fn format(mut self, format: impl Into<String>) -> Self {
         self.format = Some(format.into());
@@ -161,6 +176,17 @@ impl Input {
         self
     }
 
+    /// Keep a temporary file alive for as long as this input
+    pub(crate) fn keep_temp_file(mut self, path: TempPath) -> Self {
+        self.temp_files.push(Arc::new(path));
+        self
+    }
+
+    /// Temporary files this input depends on
+    pub(crate) fn temp_files(&self) -> &[Arc<TempPath>] {
+        &self.temp_files
+    }
+
     /// Build command line arguments for this input
     pub fn build_args(&self) -> Vec<String> {
         let mut cmd = CommandBuilder::new();
@@ -410,33 +436,125 @@ impl StreamInput {
     }
 }
 
+/// Clip in a [`ConcatInput`], optionally trimmed
+#[derive(Debug, Clone)]
+pub struct ConcatClip {
+    /// Clip path or URL
+    path: MediaPath,
+    /// Start reading at this position
+    inpoint: Option<Duration>,
+    /// Stop reading at this position
+    outpoint: Option<Duration>,
+    /// Duration the clip takes in the joined output
+    duration: Option<Duration>,
+}
+
+impl ConcatClip {
+    /// Create a clip from a path
+    pub fn new(path: impl Into<MediaPath>) -> Self {
+        Self {
+            path: path.into(),
+            inpoint: None,
+            outpoint: None,
+            duration: None,
+        }
+    }
+
+    /// Start the clip at this position
+    pub fn inpoint(mut self, position: Duration) -> Self {
+        self.inpoint = Some(position);
+        self
+    }
+
+    /// End the clip at this position
+    pub fn outpoint(mut self, position: Duration) -> Self {
+        self.outpoint = Some(position);
+        self
+    }
+
+    /// Set the clip duration
+    pub fn duration(mut self, duration: Duration) -> Self {
+        self.duration = Some(duration);
+        self
+    }
+
+    /// Length to read, from the duration or the in and out points
+    fn read_duration(&self) -> Result<Option<Duration>> {
+        if self.duration.is_some() {
+            return Ok(self.duration);
+        }
+        let Some(outpoint) = self.outpoint else {
+            return Ok(None);
+        };
+        let inpoint = StdDuration::from(self.inpoint.unwrap_or(Duration::from_secs(0)));
+        StdDuration::from(outpoint)
+            .checked_sub(inpoint)
+            .map(|length| Some(length.into()))
+            .ok_or_else(|| {
+                Error::InvalidArgument(format!(
+                    "outpoint {outpoint} is before inpoint of clip '{}'",
+                    self.path
+                ))
+            })
+    }
+}
+
+/// Video normalization applied to every clip before the concat filter
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+struct VideoNormalization {
+    size: Option<(u32, u32)>,
+    fps: Option<f64>,
+}
+
 /// Builder for concatenating multiple inputs
+///
+/// With the demuxer, clips must share codecs and parameters and are joined
+/// without re-encoding through an ffconcat list file. Otherwise the clips
+/// are decoded and joined with the `concat` filter, which can normalize
+/// mismatched clips.
 #[derive(Debug, Clone)]
 pub struct ConcatInput {
-    /// List of input paths
-    inputs: Vec<MediaPath>,
+    /// Clips in playback order
+    clips: Vec<ConcatClip>,
     /// Use concat demuxer instead of filter
     use_demuxer: bool,
+    /// Whether the clips have video
+    video: bool,
+    /// Whether the clips have audio
+    audio: bool,
+    /// Scale, pad and frame rate conversion for the concat filter
+    video_normalization: Option<VideoNormalization>,
+    /// Sample rate and channel layout conversion for the concat filter
+    audio_normalization: Option<(u32, String)>,
 }
 
 impl ConcatInput {
     /// Create a new concat input
     pub fn new() -> Self {
         Self {
-            inputs: Vec::new(),
+            clips: Vec::new(),
             use_demuxer: false,
+            video: true,
+            audio: true,
+            video_normalization: None,
+            audio_normalization: None,
         }
     }
 
     /// Add an input file
-    pub fn add_input(mut self, path: impl Into<MediaPath>) -> Self {
-        self.inputs.push(path.into());
-        self
+    pub fn add_input(self, path: impl Into<MediaPath>) -> Self {
+        self.add_clip(ConcatClip::new(path))
     }
 
     /// Add multiple input files
     pub fn add_inputs(mut self, paths: impl IntoIterator<Item = impl Into<MediaPath>>) -> Self {
-        self.inputs.extend(paths.into_iter().map(Into::into));
+        self.clips.extend(paths.into_iter().map(ConcatClip::new));
+        self
+    }
+
+    /// Add a clip with in and out points
+    pub fn add_clip(mut self, clip: ConcatClip) -> Self {
+        self.clips.push(clip);
         self
     }
 
@@ -446,30 +564,205 @@ impl ConcatInput {
         self
     }
 
-    /// Create inputs for FFmpeg
-    pub fn into_inputs(self) -> Result<Vec<Input>> {
-        if self.inputs.is_empty() {
+    /// Whether to join the video streams (default: true)
+    pub fn video(mut self, enable: bool) -> Self {
+        self.video = enable;
+        self
+    }
+
+    /// Whether to join the audio streams (default: true)
+    pub fn audio(mut self, enable: bool) -> Self {
+        self.audio = enable;
+        self
+    }
+
+    /// Fit every clip into `width`x`height`, letterboxing as needed
+    pub fn normalize_size(mut self, width: u32, height: u32) -> Self {
+        self.video_normalization
+            .get_or_insert_with(VideoNormalization::default)
+            .size = Some((width, height));
+        self
+    }
+
+    /// Convert every clip to the same frame rate
+    pub fn normalize_fps(mut self, fps: f64) -> Self {
+        self.video_normalization
+            .get_or_insert_with(VideoNormalization::default)
+            .fps = Some(fps);
+        self
+    }
+
+    /// Resample every clip to the same sample rate and channel layout
+    pub fn normalize_audio(mut self, sample_rate: u32, channel_layout: impl Into<String>) -> Self {
+        self.audio_normalization = Some((sample_rate, channel_layout.into()));
+        self
+    }
+
+    /// Whether the clips are joined with the concat demuxer
+    pub fn is_demuxer(&self) -> bool {
+        self.use_demuxer
+    }
+
+    fn check_clips(&self) -> Result<()> {
+        if self.clips.is_empty() {
             return Err(Error::InvalidArgument(
                 "No inputs provided for concatenation".to_string(),
             ));
         }
+        Ok(())
+    }
+
+    /// Contents of the ffconcat list file used in demuxer mode
+    ///
+    /// Relative paths are made absolute, since the demuxer resolves them
+    /// against the list file's directory.
+    pub fn to_ffconcat(&self) -> Result<String> {
+        self.check_clips()?;
+
+        let mut list = String::from("ffconcat version 1.0\n");
+        for clip in &self.clips {
+            let file = if clip.path.is_url() {
+                clip.path.as_str().to_string()
+            } else {
+                path::absolute(clip.path.path())?
+                    .to_string_lossy()
+                    .into_owned()
+            };
+            // Inside single quotes only `'` needs care: close, escape, reopen
+            let _ = writeln!(list, "file '{}'", file.replace('\'', r"'\''"));
+
+            if let Some(inpoint) = clip.inpoint {
+                let _ = writeln!(list, "inpoint {}", inpoint.to_ffmpeg_format());
+            }
+            if let Some(outpoint) = clip.outpoint {
+                let _ = writeln!(list, "outpoint {}", outpoint.to_ffmpeg_format());
+            }
+            if let Some(duration) = clip.duration {
+                let _ = writeln!(list, "duration {}", duration.to_ffmpeg_format());
+            }
+        }
+        Ok(list)
+    }
+
+    /// Create inputs for FFmpeg
+    ///
+    /// In demuxer mode this writes the list file to a temporary file that is
+    /// removed once the returned input and the process using it are dropped.
+    /// In filter mode it returns one trimmed input per clip, to be combined
+    /// with [`ConcatInput::filter_graph`].
+    pub fn into_inputs(self) -> Result<Vec<Input>> {
+        self.check_clips()?;
 
         if self.use_demuxer {
-            // In a real implementation, you would write a temporary file list.
-            // For this example, we'll use the `concat:` protocol which works for
-            // specific container formats like MPEG-TS.
-            let concat_string = self
-                .inputs
+            let mut file = tempfile::Builder::new()
+                .prefix("ffconcat-")
+                .suffix(".txt")
+                .tempfile()?;
+            file.write_all(self.to_ffconcat()?.as_bytes())?;
+            file.flush()?;
+            let path = file.into_temp_path();
+
+            let input = Input::new(MediaPath::from_path(Path::new(&*path)))
+                .format("concat")
+                .option("safe", "0");
+            Ok(vec![input.keep_temp_file(path)])
+        } else {
+            self.clips
                 .iter()
-                .map(|p| p.as_str())
-                .collect::<Vec<_>>()
-                .join("|");
+                .map(|clip| {
+                    let mut input = Input::new(clip.path.clone());
+                    if let Some(inpoint) = clip.inpoint {
+                        input = input.seek(inpoint);
+                    }
+                    if let Some(duration) = clip.read_duration()? {
+                        input = input.duration(duration);
+                    }
+                    Ok(input)
+                })
+                .collect()
+        }
+    }
 
-            Ok(vec![Input::new(format!("concat:{}", concat_string))])
-        } else {
-            // Return individual inputs for filter-based concatenation
-            Ok(self.inputs.into_iter().map(Input::new).collect())
+    /// Build the `concat` filter graph for the inputs returned by
+    /// [`ConcatInput::into_inputs`], the first of which has index
+    /// `first_input`
+    ///
+    /// Returns the graph and its outputs: video first, then audio.
+    pub fn filter_graph(&self, first_input: usize) -> Result<(FilterGraph, Vec<GraphOutput>)> {
+        self.check_clips()?;
+        if !self.video && !self.audio {
+            return Err(Error::InvalidArgument(
+                "Concatenation needs video or audio".to_string(),
+            ));
         }
+
+        let mut graph = FilterGraph::new();
+        let concat = graph.add(
+            VideoFilter::new("concat")
+                .param("n", self.clips.len())
+                .param("v", u8::from(self.video))
+                .param("a", u8::from(self.audio)),
+        );
+        let pads_per_clip = usize::from(self.video) + usize::from(self.audio);
+
+        for clip in 0..self.clips.len() {
+            let input = first_input + clip;
+            let mut pad = clip * pads_per_clip;
+
+            if self.video {
+                let mut source: PadSource = InputStream::video(input).into();
+                if let Some(normalization) = self.video_normalization {
+                    for filter in normalization.filters() {
+                        source = graph.chain(source, filter).output(0).into();
+                    }
+                }
+                graph.connect(source, concat.input(pad));
+                pad += 1;
+            }
+
+            if self.audio {
+                let mut source: PadSource = InputStream::audio(input).into();
+                if let Some((sample_rate, layout)) = &self.audio_normalization {
+                    let filters = [
+                        AudioFilter::aresample(*sample_rate),
+                        AudioFilter::new("aformat").param("channel_layouts", layout),
+                    ];
+                    for filter in filters {
+                        source = graph.chain(source, filter).output(0).into();
+                    }
+                }
+                graph.connect(source, concat.input(pad));
+            }
+        }
+
+        let mut outputs = Vec::new();
+        if self.video {
+            outputs.push(graph.output(concat.output(0), "concat_v"));
+        }
+        if self.audio {
+            outputs.push(graph.output(concat.output(usize::from(self.video)), "concat_a"));
+        }
+        Ok((graph, outputs))
+    }
+}
+
+impl VideoNormalization {
+    fn filters(self) -> Vec<VideoFilter> {
+        let mut filters = Vec::new();
+        if let Some((width, height)) = self.size {
+            filters.extend([
+                VideoFilter::new("scale")
+                    .param("w", width)
+                    .param("h", height)
+                    .param("force_original_aspect_ratio", "decrease"),
+                VideoFilter::pad(width, height),
+                VideoFilter::new("setsar").param("r", 1),
+            ]);
+        }
+        if let Some(fps) = self.fps {
+            filters.push(VideoFilter::fps(fps));
+        }
+        filters
     }
 }
 
@@ -533,4 +826,87 @@ mod tests {
         let inputs = concat.into_inputs().unwrap();
         assert_eq!(inputs.len(), 3);
     }
+
+    #[test]
+    fn test_concat_demuxer_list() {
+        let concat = ConcatInput::new()
+            .use_demuxer(true)
+            .add_input("/media/it's here.mp4")
+            .add_clip(
+                ConcatClip::new("/media/b.mp4")
+                    .inpoint(Duration::from_secs(10))
+                    .outpoint(Duration::from_millis(12500)),
+            );
+
+        assert_eq!(
+            concat.to_ffconcat().unwrap(),
+            "ffconcat version 1.0\n\
+             file '/media/it'\\''s here.mp4'\n\
+             file '/media/b.mp4'\ninpoint 00:00:10\noutpoint 00:00:12.500\n"
+        );
+
+        let inputs = concat.into_inputs().unwrap();
+        assert_eq!(inputs.len(), 1);
+        let args = inputs[0].build_args();
+        assert_eq!(&args[..4], ["-f", "concat", "-safe", "0"]);
+
+        let list = inputs[0].source.path().clone();
+        assert!(
+            std::fs::read_to_string(&list)
+                .unwrap()
+                .contains("inpoint 00:00:10")
+        );
+        let clone = inputs[0].clone();
+        drop(inputs);
+        assert!(list.exists());
+        drop(clone);
+        assert!(!list.exists());
+    }
+
+    #[test]
+    fn test_concat_filter_graph() {
+        let concat = ConcatInput::new()
+            .add_input("a.mp4")
+            .add_clip(
+                ConcatClip::new("b.mov")
+                    .inpoint(Duration::from_secs(2))
+                    .outpoint(Duration::from_secs(5)),
+            )
+            .normalize_size(1280, 720)
+            .normalize_fps(30.0)
+            .normalize_audio(48000, "stereo");
+
+        let inputs = concat.clone().into_inputs().unwrap();
+        let args = inputs[1].build_args();
+        assert!(args.windows(2).any(|w| w == ["-ss", "00:00:02"]));
+        assert!(args.windows(2).any(|w| w == ["-t", "00:00:03"]));
+
+        let (graph, outputs) = concat.filter_graph(1).unwrap();
+        graph.validate().unwrap();
+        assert_eq!(outputs.len(), 2);
+        assert_eq!(outputs[1].label(), "concat_a");
+
+        let built = graph.build();
+        assert!(built.contains("[1:v]scale=w=1280:h=720:force_original_aspect_ratio=decrease[v"));
+        assert!(built.contains("[2:a]aresample=sample_rate=48000[a"));
+        assert!(built.contains("concat=n=2:v=1:a=1[concat_v][concat_a]"));
+
+        let (graph, outputs) = ConcatInput::new()
+            .add_inputs(["a.mp4", "b.mp4"])
+            .audio(false)
+            .filter_graph(0)
+            .unwrap();
+        assert_eq!(graph.build(), "[0:v][1:v]concat=n=2:v=1:a=0[concat_v]");
+        assert_eq!(outputs.len(), 1);
+
+        // Frame rate normalization does not need a target size
+        let (graph, _) = ConcatInput::new()
+            .add_inputs(["a.mp4", "b.mp4"])
+            .normalize_fps(25.0)
+            .filter_graph(0)
+            .unwrap();
+        let built = graph.build();
+        assert!(built.starts_with("[0:v]fps="));
+        assert!(!built.contains("scale="));
+    }
 }
@@ -0,0 +1,566 @@
+//! Source-aware adaptive bitrate ladders
+//!
+//! An [`AbrLadder`] keeps the rungs that fit the source without upscaling,
+//! gives each an even size and a bitrate scaled by pixel count, frame rate
+//! and codec, and encodes them all in one process: the source is decoded
+//! once, `split` into one `scale` branch per rung, and every encoder gets
+//! the same keyframe interval so segments line up across renditions.
+//!
+//! ```no_run
+//! use rust_ffmpeg::hls::{HlsPackage, HlsRendition};
+//! use rust_ffmpeg::ladder::{AbrLadder, SourceVideo};
+//! use rust_ffmpeg::filter::InputStream;
+//! use rust_ffmpeg::{FFmpegBuilder, Input, StreamMap};
+//!
+//! # async fn example() -> ffmpeg_common::Result<()> {
+//! let source = SourceVideo::probe("master.mov").await?;
+//! let ladder = AbrLadder::new(source).build(InputStream::video(0))?;
+//!
+//! let package = ladder
+//!     .hls_variants()
+//!     .into_iter()
+//!     .fold(HlsPackage::new("out"), HlsPackage::variant)
+//!     .audio(HlsRendition::new("audio", StreamMap::audio_from(0)));
+//!
+//! FFmpegBuilder::new()?
+//!     .input(Input::new("master.mov"))
+//!     .filter_graph(ladder.graph.clone())
+//!     .output(package.into_output()?)
+//!     .run()
+//!     .await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use ffmpeg_common::process::{Process, ProcessConfig, find_executable};
+use ffmpeg_common::{Codec, CommandBuilder, Error, MediaPath, Result, utils};
+use serde::Deserialize;
+use std::path::PathBuf;
+
+use crate::codec::CodecOptions;
+use crate::dash::DashStream;
+use crate::filter::{FilterGraph, GraphOutput, PadSource, VideoFilter};
+use crate::hls::HlsVariant;
+use crate::output::Output;
+use crate::stream::StreamMap;
+
+/// Default rung heights, largest first
+pub const DEFAULT_HEIGHTS: [u32; 7] = [2160, 1440, 1080, 720, 480, 360, 240];
+
+/// H.264 bitrate of a 1080p30 rung, in kbit/s, that other rungs scale from
+pub const REFERENCE_BITRATE: u32 = 5000;
+
+const REFERENCE_PIXELS: f64 = 1920.0 * 1080.0;
+
+/// Display size and frame rate of the source video
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SourceVideo {
+    /// Display width (after sample aspect ratio and rotation)
+    pub width: u32,
+    /// Display height (after rotation)
+    pub height: u32,
+    /// Frames per second
+    pub frame_rate: f64,
+}
+
+#[derive(Deserialize)]
+struct ProbeOutput {
+    #[serde(default)]
+    streams: Vec<ProbeStream>,
+}
+
+#[derive(Deserialize)]
+struct ProbeStream {
+    codec_type: Option<String>,
+    width: Option<u32>,
+    height: Option<u32>,
+    sample_aspect_ratio: Option<String>,
+    avg_frame_rate: Option<String>,
+    r_frame_rate: Option<String>,
+    #[serde(default)]
+    side_data_list: Vec<serde_json::Value>,
+    #[serde(default)]
+    tags: std::collections::HashMap<String, String>,
+}
+
+impl SourceVideo {
+    /// Describe the source explicitly
+    pub fn new(width: u32, height: u32, frame_rate: f64) -> Self {
+        Self {
+            width,
+            height,
+            frame_rate,
+        }
+    }
+
+    /// Parse the first video stream of `ffprobe -show_streams -of json`
+    pub fn parse_probe(json: &str) -> Result<Self> {
+        let probe: ProbeOutput = serde_json::from_str(json)
+            .map_err(|e| Error::ParseError(format!("Invalid ffprobe output: {e}")))?;
+        let stream = probe
+            .streams
+            .iter()
+            .find(|s| s.codec_type.as_deref() == Some("video"))
+            .ok_or_else(|| Error::ParseError("No video stream in ffprobe output".to_string()))?;
+
+        let (Some(mut width), Some(mut height)) = (stream.width, stream.height) else {
+            return Err(Error::ParseError(
+                "Video stream has no dimensions".to_string(),
+            ));
+        };
+
+        if let Some((num, den)) = stream
+            .sample_aspect_ratio
+            .as_deref()
+            .and_then(|sar| sar.split_once(':'))
+            .and_then(|(n, d)| Some((n.parse::<u32>().ok()?, d.parse::<u32>().ok()?)))
+            && num > 0
+            && den > 0
+            && num != den
+        {
+            width = even(f64::from(width) * f64::from(num) / f64::from(den));
+        }
+
+        let rotation = stream
+            .side_data_list
+            .iter()
+            .find_map(|data| data.get("rotation").and_then(serde_json::Value::as_i64))
+            .or_else(|| stream.tags.get("rotate").and_then(|r| r.parse().ok()))
+            .unwrap_or(0);
+        if rotation.rem_euclid(180) == 90 {
+            std::mem::swap(&mut width, &mut height);
+        }
+
+        // avg_frame_rate is 0/0 for some containers; fall back to r_frame_rate
+        let frame_rate = [&stream.avg_frame_rate, &stream.r_frame_rate]
+            .into_iter()
+            .flatten()
+            .filter_map(|rate| utils::parse_framerate(rate).ok())
+            .find(|fps| fps.is_finite() && *fps > 0.0)
+            .ok_or_else(|| Error::ParseError("Video stream has no frame rate".to_string()))?;
+
+        Ok(Self::new(width, height, frame_rate))
+    }
+
+    /// Probe the first video stream of `input` with `ffprobe` from `PATH`
+    pub async fn probe(input: impl Into<MediaPath>) -> Result<Self> {
+        Self::probe_with(find_executable("ffprobe")?, input).await
+    }
+
+    /// Probe the first video stream of `input` with the given `ffprobe`
+    pub async fn probe_with(
+        executable: impl Into<PathBuf>,
+        input: impl Into<MediaPath>,
+    ) -> Result<Self> {
+        let args = CommandBuilder::new()
+            .option("-v", "error")
+            .option("-select_streams", "v:0")
+            .flag("-show_streams")
+            .option("-of", "json")
+            .arg(input.into().as_str())
+            .build();
+        let config = ProcessConfig::new(executable)
+            .capture_stdout(true)
+            .capture_stderr(true);
+        let output = Process::spawn(config, args)
+            .await?
+            .wait()
+            .await?
+            .into_result()?;
+        Self::parse_probe(&output.stdout_str().unwrap_or_default())
+    }
+}
+
+/// Round to the nearest even value, at least 2
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+fn even(value: f64) -> u32 {
+    ((value / 2.0).round() as u32).max(1) * 2
+}
+
+/// Rung of the ladder
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rung {
+    /// Name such as `720p`
+    pub name: String,
+    /// Width in pixels (even)
+    pub width: u32,
+    /// Height in pixels (even)
+    pub height: u32,
+    /// Target bitrate in kbit/s
+    pub bitrate: u32,
+    /// Peak bitrate in kbit/s
+    pub max_bitrate: u32,
+    /// Rate-control buffer in kbit
+    pub buffer_size: u32,
+}
+
+/// Adaptive bitrate ladder for one source
+#[derive(Debug, Clone)]
+pub struct AbrLadder {
+    source: SourceVideo,
+    codec: Codec,
+    heights: Vec<u32>,
+    reference_bitrate: u32,
+    gop_seconds: f64,
+}
+
+impl AbrLadder {
+    /// Create an H.264 ladder with the default rungs and 2 s GOPs
+    pub fn new(source: SourceVideo) -> Self {
+        Self {
+            source,
+            codec: Codec::new("libx264"),
+            heights: DEFAULT_HEIGHTS.to_vec(),
+            reference_bitrate: REFERENCE_BITRATE,
+            gop_seconds: 2.0,
+        }
+    }
+
+    /// Video encoder; bitrates are scaled by its efficiency relative to H.264
+    pub fn codec(mut self, codec: Codec) -> Self {
+        self.codec = codec;
+        self
+    }
+
+    /// Candidate rung heights
+    pub fn heights(mut self, heights: impl IntoIterator<Item = u32>) -> Self {
+        self.heights = heights.into_iter().collect();
+        self
+    }
+
+    /// H.264 bitrate of a 1080p30 rung, in kbit/s
+    pub fn reference_bitrate(mut self, kbps: u32) -> Self {
+        self.reference_bitrate = kbps;
+        self
+    }
+
+    /// Keyframe interval shared by all rungs, in seconds
+    pub fn gop_duration(mut self, seconds: f64) -> Self {
+        self.gop_seconds = seconds;
+        self
+    }
+
+    /// Bitrate relative to H.264 for the same quality
+    fn codec_efficiency(&self) -> f64 {
+        let name = self.codec.as_str();
+        if name.contains("265") || name.contains("hevc") {
+            0.65
+        } else if name.contains("vp9") {
+            0.7
+        } else if name.contains("av1") {
+            0.55
+        } else {
+            1.0
+        }
+    }
+
+    /// Rungs that fit the source, largest first
+    ///
+    /// Rungs taller than the source are dropped; a source smaller than every
+    /// rung gets a single rung at its own size.
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    pub fn rungs(&self) -> Vec<Rung> {
+        let source = self.source;
+        let mut heights: Vec<u32> = self
+            .heights
+            .iter()
+            .map(|&h| even(f64::from(h)))
+            .filter(|&h| h <= source.height)
+            .collect();
+        heights.sort_unstable_by(|a, b| b.cmp(a));
+        heights.dedup();
+        if heights.is_empty() {
+            heights.push((source.height & !1).max(2));
+        }
+
+        let aspect = f64::from(source.width) / f64::from(source.height);
+        let motion = (source.frame_rate / 30.0).sqrt();
+        heights
+            .into_iter()
+            .map(|height| {
+                let width = even(f64::from(height) * aspect).min((source.width & !1).max(2));
+                let pixels = f64::from(width) * f64::from(height);
+                let kbps = f64::from(self.reference_bitrate)
+                    * (pixels / REFERENCE_PIXELS).powf(0.75)
+                    * motion
+                    * self.codec_efficiency();
+                let bitrate = (((kbps / 50.0).round() as u32) * 50).max(100);
+                Rung {
+                    name: format!("{height}p"),
+                    width,
+                    height,
+                    bitrate,
+                    max_bitrate: bitrate * 107 / 100,
+                    buffer_size: bitrate * 3 / 2,
+                }
+            })
+            .collect()
+    }
+
+    /// Keyframe interval in frames
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    pub fn gop_size(&self) -> u32 {
+        ((self.gop_seconds * self.source.frame_rate).round() as u32).max(1)
+    }
+
+    /// Encoder settings for a rung, with keyframes pinned to the GOP grid
+    pub fn codec_options(&self, rung: &Rung) -> CodecOptions {
+        let gop = self.gop_size();
+        let mut options = CodecOptions::new(self.codec.clone())
+            .bitrate(format!("{}k", rung.bitrate))
+            .option("maxrate", format!("{}k", rung.max_bitrate))
+            .option("bufsize", format!("{}k", rung.buffer_size))
+            .gop_size(gop)
+            .option("keyint_min", gop.to_string())
+            .option(
+                "force_key_frames",
+                format!("expr:gte(t,n_forced*{})", self.gop_seconds),
+            );
+        // Scene-cut keyframes would break alignment between rungs
+        match self.codec.as_str() {
+            "libx264" | "h264" => options = options.option("sc_threshold", "0"),
+            "libx265" | "hevc" | "h265" => {
+                options = options.option("x265-params", "scenecut=0:open-gop=0");
+            }
+            _ => {}
+        }
+        options
+    }
+
+    /// Add the split and scale branches to `graph`, one output per rung
+    /// labeled `{name}_{rung}`
+    pub fn add_to(
+        &self,
+        graph: &mut FilterGraph,
+        source: impl Into<PadSource>,
+        name: &str,
+    ) -> Result<Vec<LadderRendition>> {
+        if self.source.width == 0 || self.source.height == 0 {
+            return Err(Error::InvalidArgument(
+                "Source video has no dimensions".to_string(),
+            ));
+        }
+        if !self.source.frame_rate.is_finite() || self.source.frame_rate <= 0.0 {
+            return Err(Error::InvalidArgument(format!(
+                "Invalid source frame rate {}",
+                self.source.frame_rate
+            )));
+        }
+        if !self.gop_seconds.is_finite() || self.gop_seconds <= 0.0 {
+            return Err(Error::InvalidArgument(format!(
+                "Invalid GOP duration {}",
+                self.gop_seconds
+            )));
+        }
+
+        let rungs = self.rungs();
+        let source = source.into();
+        let branches: Vec<PadSource> = if rungs.len() == 1 {
+            vec![source]
+        } else {
+            let split = graph.chain(
+                source,
+                VideoFilter::new("split").param("outputs", rungs.len()),
+            );
+            (0..rungs.len()).map(|i| split.output(i).into()).collect()
+        };
+
+        Ok(rungs
+            .into_iter()
+            .zip(branches)
+            .map(|(rung, branch)| {
+                let scaled = graph.chain(
+                    branch,
+                    VideoFilter::new("scale")
+                        .param("w", rung.width)
+                        .param("h", rung.height),
+                );
+                let square =
+                    graph.chain(scaled.output(0), VideoFilter::new("setsar").param("r", 1));
+                let output = graph.output(square.output(0), format!("{name}_{}", rung.name));
+                let codec = self.codec_options(&rung);
+                LadderRendition {
+                    rung,
+                    output,
+                    codec,
+                }
+            })
+            .collect())
+    }
+
+    /// Build a graph holding only the ladder, with outputs labeled `ladder_{rung}`
+    pub fn build(&self, source: impl Into<PadSource>) -> Result<LadderGraph> {
+        let mut graph = FilterGraph::new();
+        let renditions = self.add_to(&mut graph, source, "ladder")?;
+        Ok(LadderGraph { graph, renditions })
+    }
+}
+
+/// Encoded rendition of a ladder rung
+#[derive(Debug, Clone)]
+pub struct LadderRendition {
+    /// The rung
+    pub rung: Rung,
+    /// Scaled graph output feeding the encoder
+    pub output: GraphOutput,
+    /// Encoder settings
+    pub codec: CodecOptions,
+}
+
+impl LadderRendition {
+    /// Map of the scaled stream
+    pub fn map(&self) -> StreamMap {
+        StreamMap::from(&self.output)
+    }
+}
+
+/// Filter graph of a ladder with its renditions
+#[derive(Debug, Clone)]
+pub struct LadderGraph {
+    /// Graph to pass to [`FFmpegBuilder::filter_graph`](crate::FFmpegBuilder::filter_graph)
+    pub graph: FilterGraph,
+    /// Renditions, largest first
+    pub renditions: Vec<LadderRendition>,
+}
+
+impl LadderGraph {
+    /// One HLS variant per rung
+    pub fn hls_variants(&self) -> Vec<HlsVariant> {
+        self.renditions
+            .iter()
+            .map(|r| HlsVariant::new(&r.rung.name, r.map()).codec(r.codec.clone()))
+            .collect()
+    }
+
+    /// One DASH video representation per rung
+    pub fn dash_streams(&self) -> Vec<DashStream> {
+        self.renditions
+            .iter()
+            .map(|r| DashStream::video(r.map()).codec(r.codec.clone()))
+            .collect()
+    }
+
+    /// One file per rung, at the path returned by `path`
+    pub fn outputs(&self, path: impl Fn(&Rung) -> PathBuf) -> Vec<Output> {
+        self.renditions
+            .iter()
+            .map(|r| {
+                Output::new(MediaPath::from_path(path(&r.rung)))
+                    .map(r.map())
+                    .video_codec_opts(r.codec.clone())
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filter::InputStream;
+
+    #[test]
+    fn test_rungs_skip_upscaling() {
+        let rungs = AbrLadder::new(SourceVideo::new(1280, 720, 30.0)).rungs();
+        let sizes: Vec<_> = rungs.iter().map(|r| (r.width, r.height)).collect();
+        assert_eq!(sizes, [(1280, 720), (854, 480), (640, 360), (426, 240)]);
+        assert_eq!(rungs[0].name, "720p");
+        assert!(rungs.iter().all(|r| r.width % 2 == 0 && r.height % 2 == 0));
+        assert!(rungs.windows(2).all(|w| w[0].bitrate > w[1].bitrate));
+
+        // Smaller than every rung: keep the source size
+        let tiny = AbrLadder::new(SourceVideo::new(320, 181, 25.0)).rungs();
+        assert_eq!(tiny.len(), 1);
+        assert_eq!((tiny[0].width, tiny[0].height), (318, 180));
+    }
+
+    #[test]
+    fn test_bitrate_scaling() {
+        let source = SourceVideo::new(1920, 1080, 30.0);
+        let h264 = AbrLadder::new(source).heights([1080]).rungs();
+        assert_eq!(h264[0].bitrate, REFERENCE_BITRATE);
+        assert_eq!(h264[0].max_bitrate, 5350);
+        assert_eq!(h264[0].buffer_size, 7500);
+
+        let hevc = AbrLadder::new(source)
+            .codec(Codec::new("libx265"))
+            .heights([1080])
+            .rungs();
+        assert!(hevc[0].bitrate < h264[0].bitrate);
+
+        let fast = AbrLadder::new(SourceVideo::new(1920, 1080, 60.0))
+            .heights([1080])
+            .rungs();
+        assert!(fast[0].bitrate > h264[0].bitrate);
+    }
+
+    #[test]
+    fn test_single_process_graph() {
+        let ladder = AbrLadder::new(SourceVideo::new(1920, 1080, 25.0))
+            .heights([1080, 720, 360])
+            .build(InputStream::video(0))
+            .unwrap();
+        ladder.graph.validate().unwrap();
+
+        let built = ladder.graph.build();
+        assert!(built.starts_with("[0:v]split=outputs=3"));
+        assert!(built.contains("scale=w=1280:h=720[v3];[v3]setsar=r=1[ladder_720p]"));
+        let labels: Vec<_> = ladder.renditions.iter().map(|r| r.output.label()).collect();
+        assert_eq!(labels, ["ladder_1080p", "ladder_720p", "ladder_360p"]);
+
+        let args = ladder.renditions[1].codec.build_args("v:1");
+        let value = |key: &str| {
+            args.iter()
+                .position(|a| a == key)
+                .map(|i| args[i + 1].as_str())
+        };
+        assert_eq!(value("-g:v:1"), Some("50"));
+        assert_eq!(value("-keyint_min:v:1"), Some("50"));
+        assert_eq!(value("-sc_threshold:v:1"), Some("0"));
+        assert_eq!(
+            value("-force_key_frames:v:1"),
+            Some("expr:gte(t,n_forced*2)")
+        );
+    }
+
+    #[test]
+    fn test_single_rung_skips_split() {
+        let ladder = AbrLadder::new(SourceVideo::new(640, 360, 30.0))
+            .heights([360])
+            .build(InputStream::video(0))
+            .unwrap();
+        assert!(!ladder.graph.build().contains("split"));
+        assert_eq!(ladder.hls_variants().len(), 1);
+        assert_eq!(
+            ladder.outputs(|r| PathBuf::from(format!("{}.mp4", r.name)))[0]
+                .build_args()
+                .last()
+                .map(String::as_str),
+            Some("360p.mp4")
+        );
+    }
+
+    #[test]
+    fn test_parse_probe() {
+        let json = r#"{"streams": [{
+            "codec_type": "video", "width": 1920, "height": 1080,
+            "sample_aspect_ratio": "1:1", "avg_frame_rate": "30000/1001",
+            "r_frame_rate": "30000/1001",
+            "side_data_list": [{"side_data_type": "Display Matrix", "rotation": -90}]
+        }]}"#;
+        let source = SourceVideo::parse_probe(json).unwrap();
+        assert_eq!((source.width, source.height), (1080, 1920));
+        assert!((source.frame_rate - 29.97).abs() < 0.01);
+
+        let anamorphic = r#"{"streams": [{
+            "codec_type": "video", "width": 720, "height": 576,
+            "sample_aspect_ratio": "16:11", "avg_frame_rate": "0/0", "r_frame_rate": "25/1"
+        }]}"#;
+        let source = SourceVideo::parse_probe(anamorphic).unwrap();
+        assert_eq!(
+            (source.width, source.height, source.frame_rate),
+            (1048, 576, 25.0)
+        );
+
+        assert!(SourceVideo::parse_probe(r#"{"streams": []}"#).is_err());
+    }
+}
@@ -56,13 +56,26 @@
 #![allow(clippy::module_name_repetitions)]
 #![allow(clippy::must_use_candidate)]
 
+pub mod atomic;
 pub mod builder;
 pub mod codec;
+pub mod compat;
+pub mod dash;
+pub mod detect;
 pub mod filter;
 pub mod format;
+pub mod hls;
 pub mod input;
+pub mod ladder;
+pub mod loudness;
+pub mod metadata;
 pub mod output;
+pub mod preview;
+pub mod quality;
+pub mod segment;
 pub mod stream;
+pub mod subtitle;
+pub mod tee;
 
 // Re-export main types
 pub use builder::{FFmpegBuilder, FFmpegProcess};
@@ -70,7 +83,7 @@ pub use codec::CodecOptions;
 pub use filter::{AudioFilter, FilterGraph, VideoFilter};
 pub use format::FormatOptions;
 pub use input::{ConcatInput, DeviceInput, Input, StreamInput};
-pub use output::{ImageSequenceOutput, MultiOutput, Output};
+pub use output::{Attachment, ImageSequenceOutput, MultiOutput, Output};
 pub use stream::{StreamDisposition, StreamMap, StreamMetadata, StreamSelection};
 
 // Re-export from common
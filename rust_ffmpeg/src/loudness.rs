@@ -0,0 +1,448 @@
+//! Two-pass EBU R128 loudness normalization
+//!
+//! The first pass runs `loudnorm` in measurement mode and reads the JSON
+//! report FFmpeg prints on stderr; the second pass feeds those values back
+//! so `loudnorm` can apply a single linear gain instead of dynamic
+//! compression.
+//!
+//! ```no_run
+//! use rust_ffmpeg::loudness::{LoudnessNormalization, LoudnessTarget};
+//! use rust_ffmpeg::{FFmpegBuilder, Input, Output, StreamMap};
+//!
+//! # async fn example() -> ffmpeg_common::Result<()> {
+//! let normalization = LoudnessNormalization::new(LoudnessTarget::ebu_r128()).tracks([0, 1]);
+//! let measurements = normalization.measure(Input::new("show.mxf")).await?;
+//!
+//! let (graph, outputs) = normalization.normalization_graph(0, &measurements)?;
+//! let mut output = Output::new("show_r128.mxf").map(StreamMap::video_from(0));
+//! for track in &outputs {
+//!     output = output.map(track.into());
+//! }
+//! FFmpegBuilder::new()?
+//!     .input(Input::new("show.mxf"))
+//!     .filter_graph(graph)
+//!     .output(output)
+//!     .run()
+//!     .await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use ffmpeg_common::{Error, LogLevel, Result};
+use serde::{Deserialize, Deserializer};
+
+use crate::builder::FFmpegBuilder;
+use crate::filter::{AudioFilter, FilterGraph, GraphOutput, InputStream};
+use crate::input::Input;
+use crate::output::Output;
+use crate::stream::StreamMap;
+
+/// Loudness a program is normalized to
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LoudnessTarget {
+    /// Integrated loudness in LUFS
+    pub integrated: f64,
+    /// Maximum true peak in dBTP
+    pub true_peak: f64,
+    /// Loudness range in LU
+    pub loudness_range: f64,
+}
+
+impl LoudnessTarget {
+    /// Custom target
+    pub fn new(integrated: f64, true_peak: f64, loudness_range: f64) -> Self {
+        Self {
+            integrated,
+            true_peak,
+            loudness_range,
+        }
+    }
+
+    /// EBU R128 broadcast delivery: -23 LUFS, -1 dBTP
+    pub fn ebu_r128() -> Self {
+        Self::new(-23.0, -1.0, 7.0)
+    }
+
+    /// ATSC A/85 broadcast delivery: -24 LKFS, -2 dBTP
+    pub fn atsc_a85() -> Self {
+        Self::new(-24.0, -2.0, 7.0)
+    }
+
+    /// Typical streaming and podcast target: -16 LUFS, -1.5 dBTP
+    pub fn streaming() -> Self {
+        Self::new(-16.0, -1.5, 11.0)
+    }
+
+    fn loudnorm(&self) -> AudioFilter {
+        AudioFilter::new("loudnorm")
+            .param("I", self.integrated)
+            .param("TP", self.true_peak)
+            .param("LRA", self.loudness_range)
+    }
+}
+
+impl Default for LoudnessTarget {
+    fn default() -> Self {
+        Self::ebu_r128()
+    }
+}
+
+/// `loudnorm` prints every number as a JSON string
+fn number_string<'de, D: Deserializer<'de>>(deserializer: D) -> std::result::Result<f64, D::Error> {
+    let value = String::deserialize(deserializer)?;
+    value.trim().parse().map_err(serde::de::Error::custom)
+}
+
+/// Report of a `loudnorm` measurement pass
+///
+/// Silent tracks report `-inf` for the loudness values.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct LoudnessMeasurement {
+    /// Integrated loudness of the input in LUFS
+    #[serde(rename = "input_i", deserialize_with = "number_string")]
+    pub input_integrated: f64,
+    /// True peak of the input in dBTP
+    #[serde(rename = "input_tp", deserialize_with = "number_string")]
+    pub input_true_peak: f64,
+    /// Loudness range of the input in LU
+    #[serde(rename = "input_lra", deserialize_with = "number_string")]
+    pub input_loudness_range: f64,
+    /// Gating threshold of the input in LUFS
+    #[serde(rename = "input_thresh", deserialize_with = "number_string")]
+    pub input_threshold: f64,
+    /// Integrated loudness after one-pass normalization
+    #[serde(rename = "output_i", deserialize_with = "number_string")]
+    pub output_integrated: f64,
+    /// True peak after one-pass normalization
+    #[serde(rename = "output_tp", deserialize_with = "number_string")]
+    pub output_true_peak: f64,
+    /// Loudness range after one-pass normalization
+    #[serde(rename = "output_lra", deserialize_with = "number_string")]
+    pub output_loudness_range: f64,
+    /// Gating threshold after one-pass normalization
+    #[serde(rename = "output_thresh", deserialize_with = "number_string")]
+    pub output_threshold: f64,
+    /// `dynamic` or `linear`
+    pub normalization_type: String,
+    /// Offset gain for the second pass, in LU
+    #[serde(deserialize_with = "number_string")]
+    pub target_offset: f64,
+}
+
+impl LoudnessMeasurement {
+    /// Parse the single `loudnorm` report in FFmpeg's stderr
+    pub fn parse(stderr: &str) -> Result<Self> {
+        let mut measurements = Self::parse_all(stderr)?;
+        if measurements.len() > 1 {
+            return Err(Error::ParseError(format!(
+                "Expected one loudnorm report, found {}",
+                measurements.len()
+            )));
+        }
+        Ok(measurements.remove(0))
+    }
+
+    /// Parse every `loudnorm` report in FFmpeg's stderr, ordered by filter
+    /// instance (`Parsed_loudnorm_N`)
+    pub fn parse_all(stderr: &str) -> Result<Vec<Self>> {
+        const MARKER: &str = "[Parsed_loudnorm_";
+
+        let mut reports = Vec::new();
+        let mut rest = stderr;
+        while let Some(start) = rest.find(MARKER) {
+            rest = &rest[start + MARKER.len()..];
+            let instance: usize = rest
+                .split(|c: char| !c.is_ascii_digit())
+                .next()
+                .and_then(|digits| digits.parse().ok())
+                .ok_or_else(|| Error::ParseError("Malformed loudnorm report header".to_string()))?;
+
+            // Other log lines of the same instance carry the prefix too;
+            // only the one followed by a JSON object is a report
+            let line_end = rest.find('\n').unwrap_or(rest.len());
+            if !rest[line_end..].trim_start().starts_with('{') {
+                continue;
+            }
+            let body = &rest[line_end..];
+            let open = body.find('{').unwrap_or_default();
+            let close = body.find('}').ok_or_else(|| {
+                Error::ParseError(format!(
+                    "Unterminated loudnorm report for instance {instance}"
+                ))
+            })?;
+            let measurement: Self = serde_json::from_str(&body[open..=close]).map_err(|e| {
+                Error::ParseError(format!(
+                    "Invalid loudnorm report for instance {instance}: {e}"
+                ))
+            })?;
+            reports.push((instance, measurement));
+            rest = &body[close..];
+        }
+
+        if reports.is_empty() {
+            return Err(Error::ParseError(
+                "No loudnorm report found; was print_format=json set and the log level at least info?"
+                    .to_string(),
+            ));
+        }
+        reports.sort_by_key(|(instance, _)| *instance);
+        Ok(reports
+            .into_iter()
+            .map(|(_, measurement)| measurement)
+            .collect())
+    }
+
+    /// Second-pass filter reaching `target` with a linear gain
+    ///
+    /// Fails for silent tracks, which have no measurable loudness.
+    pub fn normalize_filter(&self, target: &LoudnessTarget) -> Result<AudioFilter> {
+        let measured = [
+            self.input_integrated,
+            self.input_true_peak,
+            self.input_loudness_range,
+            self.input_threshold,
+        ];
+        if !measured.iter().all(|value| value.is_finite()) {
+            return Err(Error::InvalidArgument(
+                "Track is silent and cannot be loudness-normalized".to_string(),
+            ));
+        }
+        Ok(target
+            .loudnorm()
+            .param("measured_I", self.input_integrated)
+            .param("measured_TP", self.input_true_peak)
+            .param("measured_LRA", self.input_loudness_range)
+            .param("measured_thresh", self.input_threshold)
+            .param("offset", self.target_offset)
+            .param("linear", "true")
+            .param("print_format", "summary"))
+    }
+}
+
+/// Two-pass `loudnorm` over one or more audio tracks of an input
+#[derive(Debug, Clone, PartialEq)]
+pub struct LoudnessNormalization {
+    target: LoudnessTarget,
+    tracks: Vec<usize>,
+    sample_rate: u32,
+}
+
+impl LoudnessNormalization {
+    /// Normalize the first audio track to `target`
+    pub fn new(target: LoudnessTarget) -> Self {
+        Self {
+            target,
+            tracks: Vec::new(),
+            sample_rate: 48000,
+        }
+    }
+
+    /// Normalize these audio tracks (`a:N`), each measured on its own
+    pub fn tracks(mut self, tracks: impl IntoIterator<Item = usize>) -> Self {
+        self.tracks = tracks.into_iter().collect();
+        self
+    }
+
+    /// Output sample rate; `loudnorm` itself always outputs 192 kHz
+    pub fn sample_rate(mut self, sample_rate: u32) -> Self {
+        self.sample_rate = sample_rate;
+        self
+    }
+
+    fn track_indices(&self) -> Vec<usize> {
+        if self.tracks.is_empty() {
+            vec![0]
+        } else {
+            self.tracks.clone()
+        }
+    }
+
+    /// Add the measurement pass to `builder`: one `loudnorm` per track,
+    /// decoded to a null output
+    ///
+    /// `input` is added after the inputs `builder` already has.
+    pub fn measurement_pass(&self, builder: FFmpegBuilder, input: Input) -> FFmpegBuilder {
+        let input_index = builder.input_count();
+        let mut graph = FilterGraph::new();
+        let mut output = Output::new("-").format("null");
+        for track in self.track_indices() {
+            let measure = self.target.loudnorm().param("print_format", "json");
+            let node = graph.chain(InputStream::audio(input_index).index(track), measure);
+            let label = graph.output(node.output(0), format!("measure{track}"));
+            output = output.map(StreamMap::from(label));
+        }
+
+        builder
+            .input(input)
+            .filter_graph(graph)
+            .output(output)
+            .log_level(LogLevel::Info)
+    }
+
+    /// Run the measurement pass and return one measurement per track
+    pub async fn measure(&self, input: Input) -> Result<Vec<LoudnessMeasurement>> {
+        let result = self
+            .measurement_pass(FFmpegBuilder::new()?, input)
+            .run()
+            .await?;
+        let stderr = result.stderr_str().unwrap_or_default();
+        let measurements = LoudnessMeasurement::parse_all(&stderr)?;
+
+        let expected = self.track_indices().len();
+        if measurements.len() != expected {
+            return Err(Error::ParseError(format!(
+                "Expected {expected} loudnorm report(s), found {}",
+                measurements.len()
+            )));
+        }
+        Ok(measurements)
+    }
+
+    /// Add the second pass for input `input_index` to an existing graph
+    ///
+    /// Outputs are named `loudnorm{track}`, in track order.
+    pub fn add_to(
+        &self,
+        graph: &mut FilterGraph,
+        input_index: usize,
+        measurements: &[LoudnessMeasurement],
+    ) -> Result<Vec<GraphOutput>> {
+        let tracks = self.track_indices();
+        if measurements.len() != tracks.len() {
+            return Err(Error::InvalidArgument(format!(
+                "{} track(s) to normalize but {} measurement(s)",
+                tracks.len(),
+                measurements.len()
+            )));
+        }
+
+        tracks
+            .into_iter()
+            .zip(measurements)
+            .map(|(track, measurement)| {
+                let loudnorm = measurement.normalize_filter(&self.target)?;
+                let node = graph.chain(InputStream::audio(input_index).index(track), loudnorm);
+                let node = graph.chain(node.output(0), AudioFilter::aresample(self.sample_rate));
+                Ok(graph.output(node.output(0), format!("loudnorm{track}")))
+            })
+            .collect()
+    }
+
+    /// Build a graph holding only the second pass
+    pub fn normalization_graph(
+        &self,
+        input_index: usize,
+        measurements: &[LoudnessMeasurement],
+    ) -> Result<(FilterGraph, Vec<GraphOutput>)> {
+        let mut graph = FilterGraph::new();
+        let outputs = self.add_to(&mut graph, input_index, measurements)?;
+        Ok((graph, outputs))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STDERR: &str = r#"Input #0, mov,mp4,m4a,3gp,3g2,mj2, from 'show.mov':
+[Parsed_loudnorm_1 @ 0x5581c0a4b2c0]
+{
+	"input_i" : "-inf",
+	"input_tp" : "-inf",
+	"input_lra" : "0.00",
+	"input_thresh" : "-inf",
+	"output_i" : "-inf",
+	"output_tp" : "-inf",
+	"output_lra" : "0.00",
+	"output_thresh" : "-inf",
+	"normalization_type" : "dynamic",
+	"target_offset" : "inf"
+}
+[Parsed_loudnorm_0 @ 0x5581c0a4a900]
+{
+	"input_i" : "-27.61",
+	"input_tp" : "-4.47",
+	"input_lra" : "18.06",
+	"input_thresh" : "-39.20",
+	"output_i" : "-23.02",
+	"output_tp" : "-1.00",
+	"output_lra" : "7.50",
+	"output_thresh" : "-33.50",
+	"normalization_type" : "dynamic",
+	"target_offset" : "0.02"
+}
+[out#0/null @ 0x5581c0a3f240] video:0KiB audio:35156KiB"#;
+
+    #[test]
+    fn test_parse_measurements() {
+        let measurements = LoudnessMeasurement::parse_all(STDERR).unwrap();
+        assert_eq!(measurements.len(), 2);
+        assert!((measurements[0].input_integrated + 27.61).abs() < 1e-9);
+        assert_eq!(measurements[0].normalization_type, "dynamic");
+        assert!(measurements[1].input_integrated.is_infinite());
+
+        assert!(LoudnessMeasurement::parse(STDERR).is_err());
+        assert!(LoudnessMeasurement::parse("no report here").is_err());
+    }
+
+    #[test]
+    fn test_second_pass() {
+        let measurements = LoudnessMeasurement::parse_all(STDERR).unwrap();
+        let filter = measurements[0]
+            .normalize_filter(&LoudnessTarget::ebu_r128())
+            .unwrap();
+        assert_eq!(
+            filter.to_string(),
+            "loudnorm=I=-23:TP=-1:LRA=7:measured_I=-27.61:measured_TP=-4.47:\
+             measured_LRA=18.06:measured_thresh=-39.2:offset=0.02:linear=true:print_format=summary"
+        );
+        assert!(
+            measurements[1]
+                .normalize_filter(&LoudnessTarget::ebu_r128())
+                .is_err()
+        );
+
+        let normalization = LoudnessNormalization::new(LoudnessTarget::atsc_a85()).tracks([2]);
+        let (graph, outputs) = normalization
+            .normalization_graph(0, &measurements[..1])
+            .unwrap();
+        graph.validate().unwrap();
+        assert_eq!(outputs[0].label(), "loudnorm2");
+        assert!(graph.build().starts_with("[0:a:2]loudnorm=I=-24:TP=-2:"));
+        assert!(
+            graph
+                .build()
+                .ends_with("aresample=sample_rate=48000[loudnorm2]")
+        );
+
+        assert!(normalization.normalization_graph(0, &measurements).is_err());
+    }
+
+    #[test]
+    fn test_measurement_pass() {
+        let normalization = LoudnessNormalization::new(LoudnessTarget::ebu_r128()).tracks([0, 1]);
+        let args = normalization
+            .measurement_pass(
+                FFmpegBuilder::with_executable("ffmpeg"),
+                Input::new("show.mov"),
+            )
+            .build_args()
+            .unwrap();
+        let graph = &args[args.iter().position(|a| a == "-filter_complex").unwrap() + 1];
+        assert_eq!(
+            graph,
+            "[0:a:0]loudnorm=I=-23:TP=-1:LRA=7:print_format=json[measure0];\
+             [0:a:1]loudnorm=I=-23:TP=-1:LRA=7:print_format=json[measure1]"
+        );
+        assert!(args.ends_with(&["-f".to_string(), "null".to_string(), "-".to_string()]));
+
+        let args = LoudnessNormalization::new(LoudnessTarget::ebu_r128())
+            .measurement_pass(
+                FFmpegBuilder::with_executable("ffmpeg").input_path("intro.wav"),
+                Input::new("show.mov"),
+            )
+            .build_args()
+            .unwrap();
+        assert!(args.iter().any(|a| a.starts_with("[1:a:0]loudnorm=")));
+    }
+}
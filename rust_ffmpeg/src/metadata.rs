@@ -0,0 +1,315 @@
+//! Chapters and global metadata written through FFMETADATA files
+//!
+//! [`FfMetadata`] renders an escaped `;FFMETADATA1` file. Added with
+//! [`FFmpegBuilder::ffmetadata`](crate::FFmpegBuilder::ffmetadata), it
+//! becomes an extra input whose tags and chapters are mapped into every
+//! output that does not choose its own source with
+//! [`Output::map_metadata`](crate::Output::map_metadata) or
+//! [`Output::map_chapters`](crate::Output::map_chapters).
+//!
+//! ```no_run
+//! use rust_ffmpeg::metadata::{Chapter, FfMetadata};
+//! use rust_ffmpeg::{Duration, FFmpegBuilder, Input, Output};
+//!
+//! # async fn example() -> ffmpeg_common::Result<()> {
+//! let metadata = FfMetadata::new()
+//!     .tag("title", "Lecture 4: Fourier series")
+//!     .tag("artist", "Faculty of Physics")
+//!     .chapter(Chapter::new(Duration::from_secs(0), Duration::from_secs(600)).title("Introduction"))
+//!     .chapter(Chapter::new(Duration::from_secs(600), Duration::from_secs(2700)).title("Derivation"));
+//!
+//! FFmpegBuilder::new()?
+//!     .input(Input::new("lecture.wav"))
+//!     .ffmetadata(metadata)?
+//!     .output(Output::new("lecture.m4a"))
+//!     .run()
+//!     .await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use ffmpeg_common::{Duration, Error, MediaPath, Result};
+use std::fmt::{self, Write as _};
+use std::io::Write as _;
+use std::path::Path;
+use std::time::Duration as StdDuration;
+
+use crate::input::Input;
+
+/// Where an output takes its global metadata or chapters from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MetadataSource {
+    /// Copy from the input with this index
+    Input(usize),
+    /// Write none
+    Strip,
+}
+
+impl fmt::Display for MetadataSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Input(index) => write!(f, "{index}"),
+            Self::Strip => write!(f, "-1"),
+        }
+    }
+}
+
+/// Chapter of an FFMETADATA file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Chapter {
+    start: i64,
+    end: i64,
+    time_base: (u32, u32),
+    title: Option<String>,
+    tags: Vec<(String, String)>,
+}
+
+impl Chapter {
+    /// Create a chapter in milliseconds (time base 1/1000)
+    #[allow(clippy::cast_possible_truncation, clippy::cast_possible_wrap)]
+    pub fn new(start: Duration, end: Duration) -> Self {
+        Self::with_time_base(start.as_millis() as i64, end.as_millis() as i64, 1, 1000)
+    }
+
+    /// Create a chapter with `start` and `end` counted in units of
+    /// `num/den` seconds
+    pub fn with_time_base(start: i64, end: i64, num: u32, den: u32) -> Self {
+        Self {
+            start,
+            end,
+            time_base: (num, den),
+            title: None,
+            tags: Vec::new(),
+        }
+    }
+
+    /// Chapter title
+    pub fn title(mut self, title: impl Into<String>) -> Self {
+        self.title = Some(title.into());
+        self
+    }
+
+    /// Additional chapter tag
+    pub fn tag(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.tags.push((key.into(), value.into()));
+        self
+    }
+
+    /// Start time
+    pub fn start(&self) -> StdDuration {
+        self.to_duration(self.start)
+    }
+
+    /// End time
+    pub fn end(&self) -> StdDuration {
+        self.to_duration(self.end)
+    }
+
+    #[allow(clippy::cast_precision_loss)]
+    fn to_duration(&self, ts: i64) -> StdDuration {
+        let (num, den) = self.time_base;
+        StdDuration::from_secs_f64((ts as f64 * f64::from(num) / f64::from(den)).max(0.0))
+    }
+
+    fn validate(&self, index: usize) -> Result<()> {
+        let (num, den) = self.time_base;
+        if num == 0 || den == 0 {
+            return Err(Error::InvalidArgument(format!(
+                "Chapter #{index} has an invalid time base {num}/{den}"
+            )));
+        }
+        if self.start < 0 || self.end <= self.start {
+            return Err(Error::InvalidArgument(format!(
+                "Chapter #{index} must end after it starts: {} to {}",
+                self.start, self.end
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Escape the characters FFMETADATA treats specially
+fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if matches!(c, '=' | ';' | '#' | '\\' | '\n') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+fn write_tag(file: &mut String, key: &str, value: &str) {
+    let _ = writeln!(file, "{}={}", escape(key), escape(value));
+}
+
+/// Contents of an FFMETADATA file
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FfMetadata {
+    tags: Vec<(String, String)>,
+    chapters: Vec<Chapter>,
+}
+
+impl FfMetadata {
+    /// Create empty metadata
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Global tag
+    pub fn tag(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.tags.push((key.into(), value.into()));
+        self
+    }
+
+    /// Add a chapter
+    pub fn chapter(mut self, chapter: Chapter) -> Self {
+        self.chapters.push(chapter);
+        self
+    }
+
+    /// Add chapters
+    pub fn chapters(mut self, chapters: impl IntoIterator<Item = Chapter>) -> Self {
+        self.chapters.extend(chapters);
+        self
+    }
+
+    /// The `;FFMETADATA1` file
+    ///
+    /// ```
+    /// use rust_ffmpeg::metadata::{Chapter, FfMetadata};
+    /// use rust_ffmpeg::Duration;
+    ///
+    /// let file = FfMetadata::new()
+    ///     .tag("title", "Q&A; part=2")
+    ///     .chapter(Chapter::new(Duration::from_secs(0), Duration::from_secs(90)).title("Intro"))
+    ///     .to_ffmetadata()
+    ///     .unwrap();
+    /// assert_eq!(
+    ///     file,
+    ///     ";FFMETADATA1\ntitle=Q&A\\; part\\=2\n\n[CHAPTER]\nTIMEBASE=1/1000\nSTART=0\nEND=90000\ntitle=Intro\n"
+    /// );
+    /// ```
+    pub fn to_ffmetadata(&self) -> Result<String> {
+        for (index, chapter) in self.chapters.iter().enumerate() {
+            chapter.validate(index)?;
+        }
+        for pair in self.chapters.windows(2) {
+            if pair[1].start() < pair[0].end() {
+                return Err(Error::InvalidArgument(format!(
+                    "Chapters overlap or are out of order at {:?}",
+                    pair[1].start()
+                )));
+            }
+        }
+
+        let mut file = String::from(";FFMETADATA1\n");
+        for (key, value) in &self.tags {
+            write_tag(&mut file, key, value);
+        }
+        for chapter in &self.chapters {
+            let (num, den) = chapter.time_base;
+            let _ = write!(
+                file,
+                "\n[CHAPTER]\nTIMEBASE={num}/{den}\nSTART={}\nEND={}\n",
+                chapter.start, chapter.end
+            );
+            if let Some(ref title) = chapter.title {
+                write_tag(&mut file, "title", title);
+            }
+            for (key, value) in &chapter.tags {
+                write_tag(&mut file, key, value);
+            }
+        }
+        Ok(file)
+    }
+
+    /// Write the file to a temporary path and return it as an input
+    ///
+    /// The file is removed once the input and the process using it are
+    /// dropped.
+    pub fn into_input(self) -> Result<Input> {
+        let mut file = tempfile::Builder::new()
+            .prefix("ffmetadata-")
+            .suffix(".txt")
+            .tempfile()?;
+        file.write_all(self.to_ffmetadata()?.as_bytes())?;
+        file.flush()?;
+        let path = file.into_temp_path();
+
+        let input = Input::new(MediaPath::from_path(Path::new(&*path))).format("ffmetadata");
+        Ok(input.keep_temp_file(path))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_escaping_and_layout() {
+        let file = FfMetadata::new()
+            .tag("comment", "line one\nline #2 \\ end")
+            .chapter(
+                Chapter::with_time_base(0, 441_000, 1, 44_100)
+                    .title("Chapter 1")
+                    .tag("language", "eng"),
+            )
+            .chapter(Chapter::with_time_base(441_000, 882_000, 1, 44_100))
+            .to_ffmetadata()
+            .unwrap();
+        assert!(file.starts_with(";FFMETADATA1\ncomment=line one\\\nline \\#2 \\\\ end\n"));
+        assert!(file.contains(
+            "[CHAPTER]\nTIMEBASE=1/44100\nSTART=0\nEND=441000\ntitle=Chapter 1\nlanguage=eng\n"
+        ));
+        assert_eq!(file.matches("[CHAPTER]").count(), 2);
+    }
+
+    #[test]
+    fn test_validation() {
+        let chapter =
+            |start, end| Chapter::new(Duration::from_secs(start), Duration::from_secs(end));
+        assert!(
+            FfMetadata::new()
+                .chapter(chapter(5, 5))
+                .to_ffmetadata()
+                .is_err()
+        );
+        assert!(
+            FfMetadata::new()
+                .chapters([chapter(0, 60), chapter(30, 90)])
+                .to_ffmetadata()
+                .is_err()
+        );
+        assert!(
+            FfMetadata::new()
+                .chapter(Chapter::with_time_base(0, 10, 1, 0))
+                .to_ffmetadata()
+                .is_err()
+        );
+        // Chapters in different time bases are compared in seconds
+        assert!(
+            FfMetadata::new()
+                .chapters([
+                    chapter(0, 1),
+                    Chapter::with_time_base(90_000, 180_000, 1, 90_000)
+                ])
+                .to_ffmetadata()
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn test_into_input() {
+        let input = FfMetadata::new().tag("title", "x").into_input().unwrap();
+        let args = input.build_args();
+        assert_eq!(args[..2], ["-f", "ffmetadata"]);
+        let path = args.last().unwrap();
+        assert!(
+            std::fs::read_to_string(path)
+                .unwrap()
+                .starts_with(";FFMETADATA1\n")
+        );
+    }
+}
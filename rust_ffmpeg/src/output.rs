@@ -1,11 +1,19 @@
 use ffmpeg_common::{
-    Codec, CommandBuilder, Duration, MediaPath, PixelFormat, Result, SampleFormat, Size,
+    Codec, CommandBuilder, Duration, Error, MediaPath, PixelFormat, Result, SampleFormat, Size,
+    StreamSpecifier, StreamType, utils,
 };
 use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use std::time::Duration as StdDuration;
+use tempfile::TempPath;
 
 use crate::codec::CodecOptions;
+use crate::filter::{AudioFilter, VideoFilter};
 use crate::format::FormatOptions;
+use crate::metadata::MetadataSource;
+use crate::stream::{StreamDisposition, StreamMap, StreamSelection};
+use crate::subtitle;
 
 /// Output specification for FFmpeg
 #[derive(Debug, Clone)]
@@ -14,12 +22,20 @@ pub struct Output {
     destination: MediaPath,
     /// Format options
     format_options: FormatOptions,
+    /// Stream mappings for this output
+    maps: Vec<StreamMap>,
     /// Video codec options
     video_codec: Option<CodecOptions>,
     /// Audio codec options
     audio_codec: Option<CodecOptions>,
     /// Subtitle codec options
     subtitle_codec: Option<CodecOptions>,
+    /// Codec options for individual streams (e.g. `a:1`)
+    stream_codecs: Vec<(StreamSpecifier, CodecOptions)>,
+    /// Video filter chain for this output
+    video_filters: Vec<VideoFilter>,
+    /// Audio filter chain for this output
+    audio_filters: Vec<AudioFilter>,
     /// Duration limit
     duration: Option<Duration>,
     /// File size limit
@@ -30,6 +46,10 @@ pub struct Output {
     metadata: HashMap<String, String>,
     /// Stream metadata
     stream_metadata: HashMap<String, HashMap<String, String>>,
+    /// Source of global metadata
+    metadata_source: Option<MetadataSource>,
+    /// Source of chapters
+    chapters_source: Option<MetadataSource>,
     /// Movflags for MP4
     movflags: Option<String>,
     /// Preset
@@ -50,6 +70,102 @@ pub struct Output {
     avoid_negative_ts: Option<String>,
     /// Start time
     start_time: Option<Duration>,
+    /// Stream dispositions
+    dispositions: Vec<(StreamSpecifier, StreamDisposition)>,
+    /// Cover art image
+    cover_art: Option<PathBuf>,
+    /// Input index of the cover art image, assigned by the builder
+    cover_input: Option<usize>,
+    /// Files attached to a Matroska output
+    attachments: Vec<Attachment>,
+    /// Write to a hidden file and rename it into place on success
+    atomic: bool,
+    /// Temporary files (e.g. key info files) that must outlive the process
+    temp_files: Vec<Arc<TempPath>>,
+    /// Files written just before the process starts
+    startup_files: Vec<StartupFile>,
+}
+
+/// File written just before FFmpeg starts, such as an HLS content key
+#[derive(Clone, PartialEq, Eq)]
+pub(crate) struct StartupFile {
+    path: PathBuf,
+    contents: Vec<u8>,
+}
+
+impl std::fmt::Debug for StartupFile {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("StartupFile")
+            .field("path", &self.path)
+            .finish_non_exhaustive()
+    }
+}
+
+impl StartupFile {
+    /// Write the file, creating its parent directories
+    pub(crate) fn write(&self) -> Result<()> {
+        if let Some(parent) = self.path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&self.path, &self.contents)?;
+        Ok(())
+    }
+}
+
+/// File attached to a Matroska output
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Attachment {
+    path: PathBuf,
+    mimetype: String,
+    filename: Option<String>,
+}
+
+impl Attachment {
+    /// Attach `path` with the given MIME type
+    pub fn new(path: impl Into<PathBuf>, mimetype: impl Into<String>) -> Self {
+        Self {
+            path: path.into(),
+            mimetype: mimetype.into(),
+            filename: None,
+        }
+    }
+
+    /// Attach a font, with the MIME type taken from its extension
+    pub fn font(path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        let mimetype = match utils::get_extension(&path).as_deref() {
+            Some("ttf" | "ttc") => "application/x-truetype-font",
+            Some("otf") => "application/vnd.ms-opentype",
+            Some("woff") => "font/woff",
+            Some("woff2") => "font/woff2",
+            _ => "application/octet-stream",
+        };
+        Self::new(path, mimetype)
+    }
+
+    /// Name stored in the container instead of the file's own name
+    pub fn filename(mut self, name: impl Into<String>) -> Self {
+        self.filename = Some(name.into());
+        self
+    }
+}
+
+/// Containers that can embed cover art
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CoverContainer {
+    Mp3,
+    Mp4,
+    Flac,
+    Matroska,
+}
+
+/// MIME type of a cover art image
+fn image_mimetype(path: &Path) -> Option<&'static str> {
+    match utils::get_extension(path)?.as_str() {
+        "jpg" | "jpeg" => Some("image/jpeg"),
+        "png" => Some("image/png"),
+        _ => None,
+    }
 }
 
 impl Output {
@@ -58,14 +174,20 @@ impl Output {
         Self {
             destination: destination.into(),
             format_options: FormatOptions::new(),
+            maps: Vec::new(),
             video_codec: None,
             audio_codec: None,
             subtitle_codec: None,
+            stream_codecs: Vec::new(),
+            video_filters: Vec::new(),
+            audio_filters: Vec::new(),
             duration: None,
             file_size_limit: None,
             frames: None,
             metadata: HashMap::new(),
             stream_metadata: HashMap::new(),
+            metadata_source: None,
+            chapters_source: None,
             movflags: None,
             preset: None,
             tune: None,
@@ -76,6 +198,13 @@ impl Output {
             copy_timestamps: false,
             avoid_negative_ts: None,
             start_time: None,
+            dispositions: Vec::new(),
+            cover_art: None,
+            cover_input: None,
+            attachments: Vec::new(),
+            atomic: false,
+            temp_files: Vec::new(),
+            startup_files: Vec::new(),
         }
     }
 
@@ -85,6 +214,37 @@ impl Output {
         self
     }
 
+    /// Map streams into this output
+    ///
+    /// Maps belong to the output they are declared on; an output without any
+    /// maps falls back to the maps set on the builder, or to FFmpeg's default
+    /// stream selection.
+    pub fn map(mut self, map: StreamMap) -> Self {
+        self.maps.push(map);
+        self
+    }
+
+    /// Map a labeled output of the complex filter graph (`[vout]`)
+    pub fn map_label(self, label: impl Into<String>) -> Self {
+        self.map(StreamMap::label(label))
+    }
+
+    /// Map the streams of an input matched by a selection
+    pub fn select_streams(mut self, input_index: usize, selection: &StreamSelection) -> Self {
+        self.maps.extend(selection.to_maps(input_index));
+        self
+    }
+
+    /// Get the stream mappings of this output
+    pub fn maps(&self) -> &[StreamMap] {
+        &self.maps
+    }
+
+    /// Get the destination path or URL
+    pub fn destination(&self) -> &MediaPath {
+        &self.destination
+    }
+
     /// Set video codec
     pub fn video_codec(mut self, codec: Codec) -> Self {
         self.video_codec = Some(CodecOptions::new(codec));
@@ -115,6 +275,106 @@ impl Output {
         self
     }
 
+    /// Mux subtitles as soft subtitles, with the codec the container needs
+    ///
+    /// Fails if the container cannot hold subtitles or is unknown.
+    pub fn soft_subtitles(self) -> Result<Self> {
+        self.soft_subtitles_from(None)
+    }
+
+    /// Like [`Output::soft_subtitles`], copying the stream when its codec
+    /// (e.g. `subrip` or `mov_text`) already suits the container
+    pub fn soft_subtitles_from(self, source_codec: Option<&str>) -> Result<Self> {
+        let Some(format) = self.container_format() else {
+            return Err(Error::InvalidArgument(format!(
+                "cannot determine the container of '{}'; set a format",
+                self.destination
+            )));
+        };
+        let codec = subtitle::soft_subtitle_codec(&format, source_codec)?;
+        Ok(self.subtitle_codec(codec))
+    }
+
+    /// Container format: the explicit format, or one guessed from the
+    /// destination's extension
+    pub fn container_format(&self) -> Option<String> {
+        self.format_options
+            .format_name()
+            .or_else(|| utils::guess_format_from_extension(self.destination.path()))
+            .map(ToString::to_string)
+    }
+
+    /// Encoders set on this output with the type of stream they encode;
+    /// per-stream codecs whose specifier names no type are left out
+    pub fn codecs(&self) -> Vec<(StreamType, &Codec)> {
+        let typed = [
+            (StreamType::Video, &self.video_codec),
+            (StreamType::Audio, &self.audio_codec),
+            (StreamType::Subtitle, &self.subtitle_codec),
+        ];
+        let mut codecs: Vec<_> = typed
+            .into_iter()
+            .filter_map(|(stream_type, options)| Some((stream_type, options.as_ref()?.codec())))
+            .collect();
+        for (stream, options) in &self.stream_codecs {
+            if let StreamSpecifier::Type(stream_type) | StreamSpecifier::TypeIndex(stream_type, _) =
+                stream
+            {
+                codecs.push((*stream_type, options.codec()));
+            }
+        }
+        codecs
+    }
+
+    /// Format options with `movflags` and custom options folded in, as the
+    /// muxer sees them
+    pub fn muxer_options(&self) -> FormatOptions {
+        let mut options = self.format_options.clone();
+        if let Some(ref flags) = self.movflags {
+            options = options.option("movflags", flags);
+        }
+        for (key, value) in &self.options {
+            options = options.option(key, value);
+        }
+        options
+    }
+
+    /// Set the codec for a single stream (e.g. `StreamSpecifier::TypeIndex(StreamType::Audio, 1)`)
+    pub fn stream_codec(self, stream: StreamSpecifier, codec: Codec) -> Self {
+        self.stream_codec_opts(stream, CodecOptions::new(codec))
+    }
+
+    /// Set codec options for a single stream, emitted as `-c:a:1`, `-b:a:1`, ...
+    pub fn stream_codec_opts(mut self, stream: StreamSpecifier, options: CodecOptions) -> Self {
+        self.stream_codecs.retain(|(spec, _)| *spec != stream);
+        self.stream_codecs.push((stream, options));
+        self
+    }
+
+    /// Add a video filter to this output's chain
+    pub fn video_filter(mut self, filter: VideoFilter) -> Self {
+        self.video_filters.push(filter);
+        self
+    }
+
+    /// Add several video filters to this output's chain
+    pub fn video_filters(mut self, filters: impl IntoIterator<Item = VideoFilter>) -> Self {
+        self.video_filters.extend(filters);
+        self
+    }
+
+    /// Add an audio filter to this output's chain
+    pub fn audio_filter(mut self, filter: AudioFilter) -> Self {
+        self.audio_filters.push(filter);
+        self
+    }
+
+    /// Add several audio filters to this output's chain
+    pub fn audio_filters(mut self, filters: impl IntoIterator<Item = AudioFilter>) -> Self {
+        self.audio_filters.extend(filters);
+        self
+    }
+
     /// Copy all codecs
     pub fn copy_codecs(self) -> Self {
         self.video_codec(Codec::copy())
@@ -161,6 +421,65 @@ impl Output {
         self
     }
 
+    /// Take global metadata from an input, or write none
+    ///
+    /// Tags set with [`Output::metadata`] are applied on top.
+    pub fn map_metadata(mut self, source: MetadataSource) -> Self {
+        self.metadata_source = Some(source);
+        self
+    }
+
+    /// Take chapters from an input, or write none
+    pub fn map_chapters(mut self, source: MetadataSource) -> Self {
+        self.chapters_source = Some(source);
+        self
+    }
+
+    /// Drop the global metadata of the inputs
+    pub fn strip_metadata(self) -> Self {
+        self.map_metadata(MetadataSource::Strip)
+    }
+
+    /// Drop the chapters of the inputs
+    pub fn strip_chapters(self) -> Self {
+        self.map_chapters(MetadataSource::Strip)
+    }
+
+    /// Set the disposition of the output streams matching `stream`
+    ///
+    /// An empty disposition clears the flags copied from the input.
+    pub fn disposition(mut self, stream: StreamSpecifier, disposition: StreamDisposition) -> Self {
+        self.dispositions.retain(|(s, _)| *s != stream);
+        self.dispositions.push((stream, disposition));
+        self
+    }
+
+    /// Embed a JPEG or PNG image as cover art
+    ///
+    /// MP3, M4A and FLAC outputs get an `attached_pic` video stream, read
+    /// from an extra input the builder adds; it should be the output's only
+    /// video stream. Its audio must be mapped explicitly, on the output or
+    /// the builder. Matroska outputs get a `cover.jpg`/`cover.png`
+    /// attachment.
+    pub fn cover_art(mut self, image: impl Into<PathBuf>) -> Self {
+        self.cover_art = Some(image.into());
+        self
+    }
+
+    /// Attach a file to a Matroska output
+    ///
+    /// Attachment metadata is addressed by position, so the output should
+    /// not also map attachment streams from an input.
+    pub fn attach(mut self, attachment: Attachment) -> Self {
+        self.attachments.push(attachment);
+        self
+    }
+
+    /// Attach a font to a Matroska output, e.g. for ASS subtitles
+    pub fn attach_font(self, path: impl Into<PathBuf>) -> Self {
+        self.attach(Attachment::font(path))
+    }
+
     /// Set movflags for MP4
     pub fn movflags(mut self, flags: impl Into<String>) -> Self {
         self.movflags = Some(flags.into());
@@ -234,7 +553,10 @@ impl Output {
             .option("keyint_min", "25")
     }
 
-    /// Configure for HLS output
+    /// Configure for single-playlist HLS output
+    ///
+    /// See [`HlsPackage`](crate::hls::HlsPackage) for variant streams,
+    /// rendition groups and encryption.
     pub fn for_hls(self, segment_duration: u32) -> Self {
         self.format("hls")
             .option("hls_time", segment_duration.to_string())
@@ -242,10 +564,214 @@ impl Output {
             .option("hls_segment_filename", "segment_%03d.ts")
     }
 
+    /// Write to a hidden file next to the destination and rename it into
+    /// place only when FFmpeg succeeds
+    ///
+    /// A failed, timed out or killed run removes the partial file. An
+    /// existing destination is only replaced with
+    /// [`FFmpegBuilder::overwrite`](crate::FFmpegBuilder::overwrite). HLS,
+    /// DASH and segment outputs publish their playlist last; see
+    /// [`crate::atomic`].
+    pub fn atomic(mut self, enable: bool) -> Self {
+        self.atomic = enable;
+        self
+    }
+
+    /// Whether this output is written atomically
+    pub fn is_atomic(&self) -> bool {
+        self.atomic
+    }
+
+    /// Value of a custom option
+    pub(crate) fn option_value(&self, key: &str) -> Option<&str> {
+        self.options.get(key).map(String::as_str)
+    }
+
+    /// Write to another destination, keeping every other setting
+    pub(crate) fn with_destination(mut self, destination: MediaPath) -> Self {
+        self.destination = destination;
+        self
+    }
+
+    /// Keep a temporary file alive for as long as this output is in use
+    pub(crate) fn keep_temp_file(mut self, path: TempPath) -> Self {
+        self.temp_files.push(Arc::new(path));
+        self
+    }
+
+    /// Temporary files referenced by this output's options
+    pub(crate) fn temp_files(&self) -> &[Arc<TempPath>] {
+        &self.temp_files
+    }
+
+    /// Write `contents` to `path` when the command starts rather than now
+    pub(crate) fn write_on_start(mut self, path: PathBuf, contents: Vec<u8>) -> Self {
+        self.startup_files.push(StartupFile { path, contents });
+        self
+    }
+
+    /// Files to write before the command starts
+    pub(crate) fn startup_files(&self) -> &[StartupFile] {
+        &self.startup_files
+    }
+
+    /// Use the given maps if this output does not declare its own
+    pub(crate) fn with_default_maps(mut self, maps: &[StreamMap]) -> Self {
+        if self.maps.is_empty() {
+            self.maps = maps.to_vec();
+        }
+        self
+    }
+
+    /// Use `source` for metadata and chapters unless this output sets its own
+    pub(crate) fn with_default_metadata(mut self, source: Option<MetadataSource>) -> Self {
+        self.metadata_source = self.metadata_source.or(source);
+        self.chapters_source = self.chapters_source.or(source);
+        self
+    }
+
+    /// Inputs read for global metadata and chapters
+    pub(crate) fn metadata_inputs(&self) -> impl Iterator<Item = usize> + '_ {
+        [self.metadata_source, self.chapters_source]
+            .into_iter()
+            .filter_map(|source| match source {
+                Some(MetadataSource::Input(index)) => Some(index),
+                _ => None,
+            })
+    }
+
+    fn cover_container(&self) -> Option<CoverContainer> {
+        match self.container_format()?.as_str() {
+            "mp3" => Some(CoverContainer::Mp3),
+            "mp4" | "ipod" | "mov" => Some(CoverContainer::Mp4),
+            "flac" => Some(CoverContainer::Flac),
+            "matroska" => Some(CoverContainer::Matroska),
+            _ => None,
+        }
+    }
+
+    /// Check that cover art and attachments suit the container
+    pub(crate) fn validate_attachments(&self) -> Result<()> {
+        let container = self.cover_container();
+        if let Some(ref image) = self.cover_art {
+            if container.is_none() {
+                return Err(Error::InvalidArgument(format!(
+                    "Cover art needs an MP3, M4A, FLAC or Matroska output: {}",
+                    self.destination
+                )));
+            }
+            if image_mimetype(image).is_none() {
+                return Err(Error::InvalidArgument(format!(
+                    "Cover art must be a JPEG or PNG image: {}",
+                    image.display()
+                )));
+            }
+        }
+        if !self.attachments.is_empty() && container != Some(CoverContainer::Matroska) {
+            return Err(Error::InvalidArgument(format!(
+                "Attachments need a Matroska output: {}",
+                self.destination
+            )));
+        }
+        Ok(())
+    }
+
+    /// Cover art image the builder must add as an input
+    pub(crate) fn cover_art_input(&self) -> Option<&Path> {
+        match self.cover_container() {
+            Some(CoverContainer::Matroska) | None => None,
+            Some(_) => self.cover_art.as_deref(),
+        }
+    }
+
+    /// Map the cover art image read by input `index` as an attached picture
+    ///
+    /// The output's other streams must already be mapped; the builder
+    /// rejects cover art on outputs without maps.
+    pub(crate) fn with_cover_input(mut self, index: usize) -> Self {
+        let video = StreamSpecifier::Type(StreamType::Video);
+        self.maps.push(StreamMap::video_from(index));
+        // The image is stored as is, whatever video codec was set
+        self.video_codec = None;
+        self = self.stream_codec_opts(video.clone(), CodecOptions::new(Codec::copy()));
+        if !self.dispositions.iter().any(|(s, _)| *s == video) {
+            self = self.disposition(video, StreamDisposition::new().set_attached_pic());
+        }
+        if self.cover_container() == Some(CoverContainer::Mp3) {
+            // ID3v2.4 pictures are not shown by many players
+            self = self
+                .option("id3v2_version", "3")
+                .stream_metadata("v", "title", "Album cover")
+                .stream_metadata("v", "comment", "Cover (front)");
+        }
+        self.cover_input = Some(index);
+        self
+    }
+
+    /// Attachments, with the cover art of a Matroska output first
+    fn all_attachments(&self) -> Vec<Attachment> {
+        let mut attachments = Vec::new();
+        if let Some(ref image) = self.cover_art
+            && self.cover_container() == Some(CoverContainer::Matroska)
+        {
+            let extension = match utils::get_extension(image).as_deref() {
+                Some("png") => "png",
+                _ => "jpg",
+            };
+            attachments.push(
+                Attachment::new(image.clone(), image_mimetype(image).unwrap_or_default())
+                    .filename(format!("cover.{extension}")),
+            );
+        }
+        attachments.extend(self.attachments.iter().cloned());
+        attachments
+    }
+
+    /// `-attach` options with the metadata of each attachment stream
+    fn attachment_args(&self) -> Vec<String> {
+        let mut cmd = CommandBuilder::new();
+        for (index, attachment) in self.all_attachments().iter().enumerate() {
+            cmd = cmd.option("-attach", attachment.path.to_string_lossy());
+            cmd = cmd.option(
+                format!("-metadata:s:t:{index}"),
+                format!("mimetype={}", attachment.mimetype),
+            );
+            if let Some(ref filename) = attachment.filename {
+                cmd = cmd.option(
+                    format!("-metadata:s:t:{index}"),
+                    format!("filename={filename}"),
+                );
+            }
+        }
+        cmd.build()
+    }
+
+    /// Prepend filters shared by every output to this output's own chains
+    pub(crate) fn with_shared_filters(
+        mut self,
+        video_filters: &[VideoFilter],
+        audio_filters: &[AudioFilter],
+    ) -> Self {
+        if !video_filters.is_empty() {
+            self.video_filters
+                .splice(0..0, video_filters.iter().cloned());
+        }
+        if !audio_filters.is_empty() {
+            self.audio_filters
+                .splice(0..0, audio_filters.iter().cloned());
+        }
+        self
+    }
+
     /// Build command line arguments
     pub fn build_args(&self) -> Vec<String> {
         let mut cmd = CommandBuilder::new();
 
+        // Stream mappings
+        for map in &self.maps {
+            cmd = cmd.option("-map", map.to_string());
+        }
+
         // Format options
         cmd = cmd.args(self.format_options.build_args());
 
@@ -264,6 +790,32 @@ impl Output {
             cmd = cmd.args(codec.build_args("s"));
         }
 
+        // Per-stream codecs, after the type-wide ones so they take precedence
+        for (stream, codec) in &self.stream_codecs {
+            cmd = cmd.args(codec.build_args(&stream.to_string()));
+        }
+
+        // Filters
+        if !self.video_filters.is_empty() {
+            let filter_str = self
+                .video_filters
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join(",");
+            cmd = cmd.option("-vf", filter_str);
+        }
+
+        if !self.audio_filters.is_empty() {
+            let filter_str = self
+                .audio_filters
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join(",");
+            cmd = cmd.option("-af", filter_str);
+        }
+
         // Duration and limits
         if let Some(duration) = self.duration {
             cmd = cmd.option("-t", duration.to_ffmpeg_format());
@@ -278,6 +830,14 @@ impl Output {
         }
 
         // Metadata
+        if let Some(source) = self.metadata_source {
+            cmd = cmd.option("-map_metadata", source.to_string());
+        }
+
+        if let Some(source) = self.chapters_source {
+            cmd = cmd.option("-map_chapters", source.to_string());
+        }
+
         for (key, value) in &self.metadata {
             cmd = cmd.option("-metadata", format!("{}={}", key, value));
         }
@@ -292,6 +852,15 @@ impl Output {
             }
         }
 
+        // Dispositions
+        for (stream, disposition) in &self.dispositions {
+            let flags = disposition.to_string();
+            let flags = if flags.is_empty() { "0" } else { &flags };
+            cmd = cmd.option(format!("-disposition:{stream}"), flags);
+        }
+
+        cmd = cmd.args(self.attachment_args());
+
         // MP4 specific
         if let Some(ref flags) = self.movflags {
             cmd = cmd.option("-movflags", flags);
@@ -365,6 +934,9 @@ impl MultiOutput {
     }
 
     /// Create adaptive streaming outputs (multiple qualities)
+    ///
+    /// See [`AbrLadder`](crate::ladder::AbrLadder) for a ladder fitted to the
+    /// source and encoded in a single pass.
     pub fn adaptive_streaming(base_path: impl AsRef<str>) -> Self {
         let base = base_path.as_ref();
 
@@ -540,6 +1112,188 @@ mod tests {
         assert!(args.iter().any(|arg| arg.contains("frag_keyframe")));
     }
 
+    #[test]
+    fn test_per_output_filters_and_stream_codecs() {
+        let output = Output::new("output.mkv")
+            .video_filter(VideoFilter::scale(1280, 720))
+            .video_filter(VideoFilter::hflip())
+            .audio_filter(AudioFilter::volume(0.5))
+            .audio_codec(Codec::aac())
+            .stream_codec_opts(
+                StreamSpecifier::TypeIndex(ffmpeg_common::StreamType::Audio, 1),
+                CodecOptions::new(Codec::opus()).bitrate("96k"),
+            );
+
+        let args = output.build_args();
+        let vf = args.iter().position(|a| a == "-vf").unwrap();
+        assert_eq!(args[vf + 1], "scale=w=1280:h=720,hflip");
+        let af = args.iter().position(|a| a == "-af").unwrap();
+        assert_eq!(args[af + 1], "volume=volume=0.5");
+
+        let c = args.iter().position(|a| a == "-c:a:1").unwrap();
+        assert_eq!(args[c + 1], "opus");
+        assert!(c > args.iter().position(|a| a == "-c:a").unwrap());
+        assert!(args.contains(&"-b:a:1".to_string()));
+
+        let shared = output.with_shared_filters(&[VideoFilter::deinterlace()], &[]);
+        let args = shared.build_args();
+        let vf = args.iter().position(|a| a == "-vf").unwrap();
+        assert_eq!(args[vf + 1], "yadif,scale=w=1280:h=720,hflip");
+    }
+
+    #[test]
+    fn test_soft_subtitles() {
+        let args = Output::new("movie.mp4")
+            .soft_subtitles()
+            .unwrap()
+            .build_args();
+        assert!(args.windows(2).any(|w| w == ["-c:s", "mov_text"]));
+
+        let output = Output::new("movie.mkv")
+            .soft_subtitles_from(Some("subrip"))
+            .unwrap();
+        assert!(
+            output
+                .build_args()
+                .windows(2)
+                .any(|w| w == ["-c:s", "copy"])
+        );
+
+        let output = Output::new("pipe:1")
+            .format("webm")
+            .soft_subtitles()
+            .unwrap();
+        assert_eq!(output.container_format().as_deref(), Some("webm"));
+        assert!(
+            output
+                .build_args()
+                .windows(2)
+                .any(|w| w == ["-c:s", "webvtt"])
+        );
+
+        assert!(Output::new("movie.avi").soft_subtitles().is_err());
+        assert!(Output::new("pipe:1").soft_subtitles().is_err());
+    }
+
+    #[test]
+    fn test_output_maps() {
+        let output = Output::new("proxy.mp4")
+            .map_label("[vout]")
+            .select_streams(0, &StreamSelection::by_language("eng"))
+            .video_codec(Codec::h264());
+
+        let args = output.build_args();
+        assert_eq!(&args[..4], &["-map", "[vout]", "-map", "0:m:language:eng"]);
+
+        let defaulted = Output::new("master.mov").with_default_maps(&[StreamMap::from_input(0)]);
+        assert_eq!(&defaulted.build_args()[..2], &["-map", "0"]);
+        let own = output.with_default_maps(&[StreamMap::from_input(0)]);
+        assert_eq!(own.maps().len(), 2);
+    }
+
+    #[test]
+    fn test_dispositions_and_attachments() {
+        let args = Output::new("episode.mkv")
+            .disposition(
+                StreamSpecifier::TypeIndex(StreamType::Subtitle, 1),
+                StreamDisposition::new().set_default().set_forced(),
+            )
+            .disposition(
+                StreamSpecifier::TypeIndex(StreamType::Audio, 1),
+                StreamDisposition::new(),
+            )
+            .cover_art("art/front.JPEG")
+            .attach_font("fonts/Title.otf")
+            .attach(Attachment::new("notes.txt", "text/plain").filename("readme.txt"))
+            .build_args();
+        assert!(
+            args.windows(2)
+                .any(|w| w == ["-disposition:s:1", "default+forced"])
+        );
+        assert!(args.windows(2).any(|w| w == ["-disposition:a:1", "0"]));
+
+        let attached: Vec<_> = args
+            .windows(2)
+            .filter(|w| w[0] == "-attach" || w[0].starts_with("-metadata:s:t"))
+            .map(|w| w.join(" "))
+            .collect();
+        assert_eq!(
+            attached,
+            [
+                "-attach art/front.JPEG",
+                "-metadata:s:t:0 mimetype=image/jpeg",
+                "-metadata:s:t:0 filename=cover.jpg",
+                "-attach fonts/Title.otf",
+                "-metadata:s:t:1 mimetype=application/vnd.ms-opentype",
+                "-attach notes.txt",
+                "-metadata:s:t:2 mimetype=text/plain",
+                "-metadata:s:t:2 filename=readme.txt",
+            ]
+        );
+        assert!(
+            Output::new("episode.mkv")
+                .cover_art("a.jpg")
+                .cover_art_input()
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_cover_art() {
+        let output = Output::new("track.mp3")
+            .map(StreamMap::specific(
+                0,
+                StreamSpecifier::TypeIndex(StreamType::Audio, 0),
+            ))
+            .video_codec(Codec::new("mjpeg"))
+            .stream_codec_opts(
+                StreamSpecifier::Type(StreamType::Video),
+                CodecOptions::new(Codec::new("png")),
+            )
+            .cover_art("cover.png");
+        assert!(output.validate_attachments().is_ok());
+        assert_eq!(output.cover_art_input(), Some(Path::new("cover.png")));
+
+        let args = output.with_cover_input(1).build_args();
+        assert_eq!(&args[..4], &["-map", "0:a:0", "-map", "1:v"]);
+        let video_codecs: Vec<_> = args
+            .windows(2)
+            .filter(|w| w[0] == "-c:v")
+            .map(|w| w[1].as_str())
+            .collect();
+        assert_eq!(video_codecs, ["copy"]);
+        assert!(
+            args.windows(2)
+                .any(|w| w == ["-disposition:v", "attached_pic"])
+        );
+        assert!(args.windows(2).any(|w| w == ["-id3v2_version", "3"]));
+
+        // Audio-only Matroska takes the cover as an attachment
+        let mka = Output::new("album.mka").cover_art("front.jpg");
+        assert_eq!(mka.container_format().as_deref(), Some("matroska"));
+        assert!(mka.validate_attachments().is_ok());
+        assert_eq!(mka.cover_art_input(), None);
+
+        assert!(
+            Output::new("a.wav")
+                .cover_art("c.jpg")
+                .validate_attachments()
+                .is_err()
+        );
+        assert!(
+            Output::new("a.m4a")
+                .cover_art("c.gif")
+                .validate_attachments()
+                .is_err()
+        );
+        assert!(
+            Output::new("a.mp4")
+                .attach_font("f.ttf")
+                .validate_attachments()
+                .is_err()
+        );
+    }
+
     #[test]
     fn test_image_sequence() {
         let output = ImageSequenceOutput::new("frame_%04d.jpg")
@@ -0,0 +1,408 @@
+//! Sprite-sheet thumbnails with a WebVTT scrubbing track
+//!
+//! [`SpritePreview`] samples one frame every few seconds, tiles the samples
+//! into sprite sheets with the `tile` filter and writes a WebVTT track whose
+//! cues point at the matching region of a sheet (`sprite_001.jpg#xywh=...`),
+//! the format web players use for seek-bar previews.
+//!
+//! ```no_run
+//! use rust_ffmpeg::preview::SpritePreview;
+//! use rust_ffmpeg::{Duration, FFmpegBuilder, Input};
+//!
+//! # async fn example() -> ffmpeg_common::Result<()> {
+//! let files = SpritePreview::new("thumbs")
+//!     .interval(5.0)
+//!     .tile_size(160, 90)
+//!     .grid(10, 10)
+//!     .url_prefix("https://cdn.example/video/thumbs/")
+//!     .generate(
+//!         FFmpegBuilder::new()?.input(Input::new("movie.mp4")),
+//!         Duration::from_secs(5400),
+//!     )
+//!     .await?;
+//!
+//! println!("{} sheets, track at {}", files.sheets.len(), files.vtt.display());
+//! # Ok(())
+//! # }
+//! ```
+
+use ffmpeg_common::{Codec, Duration, Error, Result};
+use std::fmt::Write as _;
+use std::path::PathBuf;
+use std::time::Duration as StdDuration;
+
+use crate::builder::FFmpegBuilder;
+use crate::codec::CodecOptions;
+use crate::filter::VideoFilter;
+use crate::output::Output;
+
+/// How frames are picked for the thumbnails
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Sampling {
+    /// `fps` filter: one frame per interval on a fixed grid, duplicating
+    /// frames across gaps so thumbnail `n` always shows time `n * interval`
+    #[default]
+    Fps,
+    /// `select` filter: the first frame at least one interval after the
+    /// previously selected one; never duplicates, but drifts on gaps
+    Select,
+}
+
+/// Files written by [`SpritePreview::generate`]
+#[derive(Debug, Clone)]
+pub struct PreviewFiles {
+    /// Sprite sheets referenced by the track, in order
+    pub sheets: Vec<PathBuf>,
+    /// WebVTT thumbnail track
+    pub vtt: PathBuf,
+}
+
+/// Sprite-sheet thumbnail generator
+#[derive(Debug, Clone)]
+pub struct SpritePreview {
+    directory: PathBuf,
+    interval: f64,
+    tile_width: u32,
+    tile_height: u32,
+    columns: u32,
+    rows: u32,
+    sampling: Sampling,
+    sheet_pattern: String,
+    vtt_name: String,
+    url_prefix: String,
+    quality: Option<u8>,
+}
+
+impl SpritePreview {
+    /// Create a generator writing sheets and track into `directory`
+    pub fn new(directory: impl Into<PathBuf>) -> Self {
+        Self {
+            directory: directory.into(),
+            interval: 10.0,
+            tile_width: 160,
+            tile_height: 90,
+            columns: 5,
+            rows: 5,
+            sampling: Sampling::Fps,
+            sheet_pattern: "sprite_%03d.jpg".to_string(),
+            vtt_name: "thumbnails.vtt".to_string(),
+            url_prefix: String::new(),
+            quality: None,
+        }
+    }
+
+    /// Seconds between thumbnails (default 10)
+    pub fn interval(mut self, seconds: f64) -> Self {
+        self.interval = seconds;
+        self
+    }
+
+    /// Size of one thumbnail; frames are scaled to fit and letterboxed
+    /// (default 160x90)
+    pub fn tile_size(mut self, width: u32, height: u32) -> Self {
+        self.tile_width = width;
+        self.tile_height = height;
+        self
+    }
+
+    /// Thumbnails per sheet as columns x rows (default 5x5)
+    pub fn grid(mut self, columns: u32, rows: u32) -> Self {
+        self.columns = columns;
+        self.rows = rows;
+        self
+    }
+
+    /// Frame sampling method
+    pub fn sampling(mut self, sampling: Sampling) -> Self {
+        self.sampling = sampling;
+        self
+    }
+
+    /// Sheet file name pattern with one `%d` counter starting at 1
+    /// (default `sprite_%03d.jpg`)
+    pub fn sheet_pattern(mut self, pattern: impl Into<String>) -> Self {
+        self.sheet_pattern = pattern.into();
+        self
+    }
+
+    /// File name of the WebVTT track (default `thumbnails.vtt`)
+    pub fn vtt_name(mut self, name: impl Into<String>) -> Self {
+        self.vtt_name = name.into();
+        self
+    }
+
+    /// Prefix of the sheet URLs in the track; empty keeps them relative to
+    /// the track
+    pub fn url_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.url_prefix = prefix.into();
+        self
+    }
+
+    /// JPEG quality (2-31, lower is better)
+    pub fn quality(mut self, q: u8) -> Self {
+        self.quality = Some(q.clamp(2, 31));
+        self
+    }
+
+    /// Path of the WebVTT track
+    pub fn vtt_path(&self) -> PathBuf {
+        self.directory.join(&self.vtt_name)
+    }
+
+    /// File name of sheet `index` (0-based)
+    pub fn sheet_name(&self, index: usize) -> Result<String> {
+        let (prefix, width, suffix) = split_pattern(&self.sheet_pattern)?;
+        Ok(format!("{prefix}{:0width$}{suffix}", index + 1))
+    }
+
+    /// Number of thumbnails covering `duration`
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    pub fn thumbnail_count(&self, duration: Duration) -> usize {
+        let seconds = StdDuration::from(duration).as_secs_f64();
+        (seconds / self.interval).ceil() as usize
+    }
+
+    /// Number of sheets covering `duration`
+    pub fn sheet_count(&self, duration: Duration) -> usize {
+        self.thumbnail_count(duration).div_ceil(self.per_sheet())
+    }
+
+    fn per_sheet(&self) -> usize {
+        (self.columns * self.rows) as usize
+    }
+
+    fn validate(&self) -> Result<()> {
+        if !self.interval.is_finite() || self.interval <= 0.0 {
+            return Err(Error::InvalidArgument(format!(
+                "Thumbnail interval must be positive: {}",
+                self.interval
+            )));
+        }
+        if self.tile_width == 0
+            || self.tile_height == 0
+            || !self.tile_width.is_multiple_of(2)
+            || !self.tile_height.is_multiple_of(2)
+        {
+            return Err(Error::InvalidArgument(format!(
+                "Thumbnail size must be even and non-zero: {}x{}",
+                self.tile_width, self.tile_height
+            )));
+        }
+        if self.columns == 0 || self.rows == 0 {
+            return Err(Error::InvalidArgument(format!(
+                "Sprite grid must have at least one cell: {}x{}",
+                self.columns, self.rows
+            )));
+        }
+        split_pattern(&self.sheet_pattern)?;
+        Ok(())
+    }
+
+    /// Sampling, scaling and tiling filters
+    pub fn filters(&self) -> Vec<VideoFilter> {
+        let sample = match self.sampling {
+            Sampling::Fps => VideoFilter::new("fps").param("fps", format!("1/{}", self.interval)),
+            Sampling::Select => VideoFilter::select(format!(
+                "isnan(prev_selected_t)+gte(t-prev_selected_t,{})",
+                self.interval
+            )),
+        };
+        vec![
+            sample,
+            VideoFilter::new("scale")
+                .param("w", self.tile_width)
+                .param("h", self.tile_height)
+                .param("force_original_aspect_ratio", "decrease"),
+            VideoFilter::pad(self.tile_width, self.tile_height),
+            VideoFilter::new("setsar").param("r", 1),
+            VideoFilter::new("tile").param("layout", format!("{}x{}", self.columns, self.rows)),
+        ]
+    }
+
+    /// Output writing the sprite sheets
+    pub fn output(&self) -> Result<Output> {
+        self.validate()?;
+        let mut output = Output::new(self.directory.join(&self.sheet_pattern))
+            .format("image2")
+            .video_filters(self.filters())
+            .no_audio();
+        if self.sampling == Sampling::Select {
+            output = output.option("fps_mode", "vfr");
+        }
+        if let Some(q) = self.quality {
+            output = output.video_codec_opts(CodecOptions::new(Codec::new("mjpeg")).quality(q));
+        }
+        Ok(output)
+    }
+
+    /// WebVTT thumbnail track for media of length `duration`
+    ///
+    /// ```
+    /// use rust_ffmpeg::preview::SpritePreview;
+    /// use rust_ffmpeg::Duration;
+    ///
+    /// let vtt = SpritePreview::new("thumbs")
+    ///     .grid(2, 2)
+    ///     .webvtt(Duration::from_secs(15))
+    ///     .unwrap();
+    /// assert!(vtt.contains("00:00:10.000 --> 00:00:15.000\nsprite_001.jpg#xywh=160,0,160,90"));
+    /// ```
+    #[allow(clippy::cast_possible_truncation, clippy::cast_precision_loss)]
+    pub fn webvtt(&self, duration: Duration) -> Result<String> {
+        self.validate()?;
+        let total = StdDuration::from(duration).as_secs_f64();
+        let per_sheet = self.per_sheet();
+        let columns = self.columns as usize;
+
+        let mut vtt = String::from("WEBVTT\n");
+        for i in 0..self.thumbnail_count(duration) {
+            let start = i as f64 * self.interval;
+            let end = ((i + 1) as f64 * self.interval).min(total);
+            let cell = i % per_sheet;
+            let x = (cell % columns) as u32 * self.tile_width;
+            let y = (cell / columns) as u32 * self.tile_height;
+            let _ = write!(
+                vtt,
+                "\n{} --> {}\n{}{}#xywh={x},{y},{},{}\n",
+                cue_time(start),
+                cue_time(end),
+                self.url_prefix,
+                self.sheet_name(i / per_sheet)?,
+                self.tile_width,
+                self.tile_height,
+            );
+        }
+        Ok(vtt)
+    }
+
+    /// Write the sprite sheets and the WebVTT track
+    ///
+    /// `builder` must already have the source as its input; `duration` is
+    /// the length of that source.
+    pub async fn generate(
+        self,
+        builder: FFmpegBuilder,
+        duration: Duration,
+    ) -> Result<PreviewFiles> {
+        let output = self.output()?;
+        let vtt = self.webvtt(duration)?;
+        std::fs::create_dir_all(&self.directory)?;
+
+        builder.output(output).run().await?;
+
+        let vtt_path = self.vtt_path();
+        tokio::fs::write(&vtt_path, vtt).await?;
+        let sheets = (0..self.sheet_count(duration))
+            .map(|i| self.sheet_name(i).map(|name| self.directory.join(name)))
+            .collect::<Result<_>>()?;
+        Ok(PreviewFiles {
+            sheets,
+            vtt: vtt_path,
+        })
+    }
+}
+
+/// Split an image2 pattern into prefix, counter width and suffix
+fn split_pattern(pattern: &str) -> Result<(&str, usize, &str)> {
+    let invalid = || {
+        Error::InvalidArgument(format!(
+            "Sheet pattern needs exactly one %d counter: {pattern}"
+        ))
+    };
+    let start = pattern.find('%').ok_or_else(invalid)?;
+    let rest = &pattern[start + 1..];
+    let digits = rest
+        .find(|c: char| !c.is_ascii_digit())
+        .ok_or_else(invalid)?;
+    if !rest[digits..].starts_with('d') {
+        return Err(invalid());
+    }
+    let suffix = &rest[digits + 1..];
+    if suffix.contains('%') {
+        return Err(invalid());
+    }
+    let width = rest[..digits].parse().unwrap_or(0);
+    Ok((&pattern[..start], width, suffix))
+}
+
+/// `HH:MM:SS.mmm` cue timestamp
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+fn cue_time(seconds: f64) -> String {
+    let millis = (seconds * 1000.0).round() as u64;
+    format!(
+        "{:02}:{:02}:{:02}.{:03}",
+        millis / 3_600_000,
+        millis / 60_000 % 60,
+        millis / 1000 % 60,
+        millis % 1000
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_filters_and_output() {
+        let preview = SpritePreview::new("thumbs").interval(2.5).grid(10, 8);
+        let chain: Vec<_> = preview.filters().iter().map(ToString::to_string).collect();
+        assert_eq!(chain[0], "fps=fps=1/2.5");
+        assert_eq!(chain[4], "tile=layout=10x8");
+
+        let args = preview.output().unwrap().build_args();
+        assert!(args.contains(&"-an".to_string()));
+        assert!(args.iter().any(|a| a.ends_with("sprite_%03d.jpg")));
+
+        let args = SpritePreview::new("thumbs")
+            .sampling(Sampling::Select)
+            .output()
+            .unwrap()
+            .build_args();
+        assert!(args.iter().any(|a| a.contains("select=")));
+        assert!(args.contains(&"vfr".to_string()));
+    }
+
+    #[test]
+    fn test_webvtt_cues() {
+        let preview = SpritePreview::new("thumbs")
+            .interval(10.0)
+            .tile_size(120, 68)
+            .grid(2, 2)
+            .url_prefix("/media/");
+        let vtt = preview.webvtt(Duration::from_secs(45)).unwrap();
+        let cues: Vec<_> = vtt.split("\n\n").skip(1).collect();
+        assert_eq!(cues.len(), 5);
+        assert_eq!(
+            cues[3],
+            "00:00:30.000 --> 00:00:40.000\n/media/sprite_001.jpg#xywh=120,68,120,68"
+        );
+        assert_eq!(
+            cues[4].trim_end(),
+            "00:00:40.000 --> 00:00:45.000\n/media/sprite_002.jpg#xywh=0,0,120,68"
+        );
+        assert_eq!(preview.sheet_count(Duration::from_secs(45)), 2);
+        assert_eq!(cue_time(3725.5), "01:02:05.500");
+    }
+
+    #[test]
+    fn test_validation() {
+        assert!(SpritePreview::new("t").interval(0.0).output().is_err());
+        assert!(SpritePreview::new("t").tile_size(161, 90).output().is_err());
+        assert!(SpritePreview::new("t").grid(0, 3).output().is_err());
+        assert!(
+            SpritePreview::new("t")
+                .sheet_pattern("sheet.jpg")
+                .output()
+                .is_err()
+        );
+        assert!(
+            SpritePreview::new("t")
+                .sheet_pattern("%d_%d.jpg")
+                .output()
+                .is_err()
+        );
+
+        let preview = SpritePreview::new("t").sheet_pattern("sheet-%d.png");
+        assert_eq!(preview.sheet_name(11).unwrap(), "sheet-12.png");
+    }
+}
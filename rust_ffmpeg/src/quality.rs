@@ -0,0 +1,633 @@
+//! VMAF, PSNR and SSIM comparison against a reference
+//!
+//! The distorted video is scaled to the reference size, both are brought to
+//! a common frame rate and timestamp origin, and each metric writes a log
+//! file into a temporary directory that is parsed once FFmpeg finishes.
+//! Unless [`QualityComparison::frame_rate`] is set, the common frame rate is
+//! the reference's, probed with `ffprobe`.
+//!
+//! ```no_run
+//! use rust_ffmpeg::quality::{Metric, QualityComparison};
+//! use rust_ffmpeg::Input;
+//!
+//! # async fn example() -> ffmpeg_common::Result<()> {
+//! let report = QualityComparison::new(Input::new("master.mov"), Input::new("1080p.mp4"))
+//!     .metrics([Metric::Vmaf, Metric::Psnr])
+//!     .reference_size(1920, 1080)
+//!     .frame_rate(24.0)
+//!     .run()
+//!     .await?;
+//!
+//! let vmaf = report.vmaf().unwrap();
+//! assert!(vmaf.harmonic_mean() > 93.0 && vmaf.percentile(1.0) > 80.0);
+//! # Ok(())
+//! # }
+//! ```
+
+use ffmpeg_common::process::find_executable;
+use ffmpeg_common::{Error, LogLevel, Progress, Result, get_version};
+use std::fmt::{self, Debug};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::builder::FFmpegBuilder;
+use crate::detect::run_analysis;
+use crate::filter::{FilterGraph, InputStream, PadSource, PadType, VideoFilter, escape};
+use crate::input::Input;
+use crate::ladder::SourceVideo;
+use crate::output::Output;
+
+/// PSNR reported for identical frames, instead of infinity
+pub const PSNR_IDENTICAL: f64 = 100.0;
+
+/// Full-reference quality metric
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Metric {
+    /// Netflix VMAF, via `libvmaf`
+    Vmaf,
+    /// Peak signal-to-noise ratio in dB, average over planes
+    Psnr,
+    /// Structural similarity, all planes
+    Ssim,
+}
+
+impl Metric {
+    /// Name of the FFmpeg filter computing the metric
+    pub fn filter_name(&self) -> &'static str {
+        match self {
+            Self::Vmaf => "libvmaf",
+            Self::Psnr => "psnr",
+            Self::Ssim => "ssim",
+        }
+    }
+
+    fn log_file(self) -> &'static str {
+        match self {
+            Self::Vmaf => "vmaf.json",
+            Self::Psnr => "psnr.log",
+            Self::Ssim => "ssim.log",
+        }
+    }
+}
+
+/// Per-frame scores of one metric
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MetricSeries {
+    /// Score of each compared frame, in order
+    pub frames: Vec<f64>,
+}
+
+impl MetricSeries {
+    /// Parse a `libvmaf` JSON log (`log_fmt=json`)
+    pub fn parse_vmaf_json(log: &str) -> Result<Self> {
+        let json: serde_json::Value = serde_json::from_str(log)
+            .map_err(|e| Error::ParseError(format!("Invalid VMAF log: {e}")))?;
+        let frames = json["frames"]
+            .as_array()
+            .ok_or_else(|| Error::ParseError("VMAF log has no frames".to_string()))?
+            .iter()
+            .map(|frame| {
+                frame["metrics"]["vmaf"].as_f64().ok_or_else(|| {
+                    Error::ParseError(format!("VMAF log frame without score: {frame}"))
+                })
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self { frames })
+    }
+
+    /// Parse a `psnr` stats file, using `psnr_avg` of each frame
+    ///
+    /// Identical frames score [`PSNR_IDENTICAL`] so averages stay finite.
+    pub fn parse_psnr_log(log: &str) -> Result<Self> {
+        Self::parse_stats(log, "psnr_avg:", |value| {
+            if value == "inf" {
+                Some(PSNR_IDENTICAL)
+            } else {
+                value.parse().ok()
+            }
+        })
+    }
+
+    /// Parse an `ssim` stats file, using the `All` score of each frame
+    pub fn parse_ssim_log(log: &str) -> Result<Self> {
+        Self::parse_stats(log, "All:", |value| value.parse().ok())
+    }
+
+    /// One `key:value` score per line
+    fn parse_stats(log: &str, key: &str, parse: impl Fn(&str) -> Option<f64>) -> Result<Self> {
+        let frames = log
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| {
+                line.split_whitespace()
+                    .find_map(|field| field.strip_prefix(key))
+                    .and_then(&parse)
+                    .ok_or_else(|| Error::ParseError(format!("No {key} score in '{line}'")))
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self { frames })
+    }
+
+    /// Number of compared frames
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// Whether no frame was compared
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    #[allow(clippy::cast_precision_loss)]
+    fn count(&self) -> f64 {
+        self.frames.len() as f64
+    }
+
+    /// Arithmetic mean; `NaN` for an empty series
+    pub fn mean(&self) -> f64 {
+        self.frames.iter().sum::<f64>() / self.count()
+    }
+
+    /// Harmonic mean as pooled by VMAF, `n / Σ 1/(1+x) - 1`, which weighs
+    /// bad frames more than the mean and tolerates zero scores
+    pub fn harmonic_mean(&self) -> f64 {
+        self.count() / self.frames.iter().map(|x| 1.0 / (1.0 + x)).sum::<f64>() - 1.0
+    }
+
+    /// Lowest score
+    pub fn min(&self) -> f64 {
+        self.frames.iter().copied().fold(f64::INFINITY, f64::min)
+    }
+
+    /// Highest score
+    pub fn max(&self) -> f64 {
+        self.frames
+            .iter()
+            .copied()
+            .fold(f64::NEG_INFINITY, f64::max)
+    }
+
+    /// Score below which `percent` of the frames fall, interpolating
+    /// between frames; `percentile(1.0)` is the usual "1% low"
+    pub fn percentile(&self, percent: f64) -> f64 {
+        let mut sorted = self.frames.clone();
+        sorted.sort_by(f64::total_cmp);
+        let Some(&last) = sorted.last() else {
+            return f64::NAN;
+        };
+
+        #[allow(clippy::cast_precision_loss)]
+        let rank = percent.clamp(0.0, 100.0) / 100.0 * (sorted.len() - 1) as f64;
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        let below = rank.floor() as usize;
+        let above = sorted.get(below + 1).copied().unwrap_or(last);
+        sorted[below] + (above - sorted[below]) * rank.fract()
+    }
+}
+
+/// Scores of every requested metric
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct QualityReport {
+    metrics: Vec<(Metric, MetricSeries)>,
+}
+
+impl QualityReport {
+    /// Series of a metric, if it was requested
+    pub fn get(&self, metric: Metric) -> Option<&MetricSeries> {
+        self.metrics
+            .iter()
+            .find(|(m, _)| *m == metric)
+            .map(|(_, series)| series)
+    }
+
+    /// VMAF scores, 0 to 100
+    pub fn vmaf(&self) -> Option<&MetricSeries> {
+        self.get(Metric::Vmaf)
+    }
+
+    /// PSNR scores in dB
+    pub fn psnr(&self) -> Option<&MetricSeries> {
+        self.get(Metric::Psnr)
+    }
+
+    /// SSIM scores, 0 to 1
+    pub fn ssim(&self) -> Option<&MetricSeries> {
+        self.get(Metric::Ssim)
+    }
+}
+
+/// How the two videos are brought together, settled before the graph is built
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Alignment {
+    /// Frame rate both videos are converted to
+    frame_rate: f64,
+    /// Scale against the reference with `scale` and its `rw`/`rh` variables
+    /// (FFmpeg 7.1+) instead of the deprecated `scale2ref`
+    scale_ref_input: bool,
+}
+
+/// Compare a distorted video against its reference
+#[derive(Clone)]
+pub struct QualityComparison {
+    reference: Input,
+    distorted: Input,
+    metrics: Vec<Metric>,
+    reference_size: Option<(u32, u32)>,
+    frame_rate: Option<f64>,
+    frame_offset: i64,
+    vmaf_model: Option<String>,
+    threads: Option<u32>,
+    executable: Option<PathBuf>,
+    progress_callback: Option<Arc<dyn Fn(Progress) + Send + Sync>>,
+}
+
+impl Debug for QualityComparison {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("QualityComparison")
+            .field("reference", &self.reference)
+            .field("distorted", &self.distorted)
+            .field("metrics", &self.metrics)
+            .field("reference_size", &self.reference_size)
+            .field("frame_rate", &self.frame_rate)
+            .field("frame_offset", &self.frame_offset)
+            .field("vmaf_model", &self.vmaf_model)
+            .field("threads", &self.threads)
+            .field("executable", &self.executable)
+            .field("progress_callback", &self.progress_callback.is_some())
+            .finish()
+    }
+}
+
+impl QualityComparison {
+    /// Compare `distorted` against `reference` with VMAF
+    pub fn new(reference: Input, distorted: Input) -> Self {
+        Self {
+            reference,
+            distorted,
+            metrics: vec![Metric::Vmaf],
+            reference_size: None,
+            frame_rate: None,
+            frame_offset: 0,
+            vmaf_model: None,
+            threads: None,
+            executable: None,
+            progress_callback: None,
+        }
+    }
+
+    /// Metrics to compute
+    pub fn metrics(mut self, metrics: impl IntoIterator<Item = Metric>) -> Self {
+        self.metrics.clear();
+        for metric in metrics {
+            if !self.metrics.contains(&metric) {
+                self.metrics.push(metric);
+            }
+        }
+        self
+    }
+
+    /// Scale the distorted video to this size
+    ///
+    /// Without it the distorted video is scaled to the size of the
+    /// reference, with `scale` on FFmpeg 7.1 and later and `scale2ref`
+    /// before.
+    pub fn reference_size(mut self, width: u32, height: u32) -> Self {
+        self.reference_size = Some((width, height));
+        self
+    }
+
+    /// Convert both videos to this frame rate before comparing, instead of
+    /// the probed frame rate of the reference
+    pub fn frame_rate(mut self, fps: f64) -> Self {
+        self.frame_rate = Some(fps);
+        self
+    }
+
+    /// Frames to skip so both videos start on the same picture: positive
+    /// when the distorted video has extra leading frames, negative when the
+    /// reference has
+    pub fn frame_offset(mut self, frames: i64) -> Self {
+        self.frame_offset = frames;
+        self
+    }
+
+    /// VMAF model version, e.g. `vmaf_4k_v0.6.1`
+    pub fn vmaf_model(mut self, version: impl Into<String>) -> Self {
+        self.vmaf_model = Some(version.into());
+        self
+    }
+
+    /// Threads used by `libvmaf`
+    pub fn threads(mut self, threads: u32) -> Self {
+        self.threads = Some(threads);
+        self
+    }
+
+    /// Use a specific ffmpeg executable instead of the one on `PATH`
+    pub fn executable(mut self, path: impl Into<PathBuf>) -> Self {
+        self.executable = Some(path.into());
+        self
+    }
+
+    /// Report progress while the comparison runs
+    pub fn on_progress<F>(mut self, callback: F) -> Self
+    where
+        F: Fn(Progress) + Send + Sync + 'static,
+    {
+        self.progress_callback = Some(Arc::new(callback));
+        self
+    }
+
+    /// Align one side: drop offset frames, convert the frame rate and start
+    /// timestamps at zero
+    fn align(
+        graph: &mut FilterGraph,
+        mut source: PadSource,
+        skip: u64,
+        frame_rate: f64,
+    ) -> PadSource {
+        if skip > 0 {
+            let trim = VideoFilter::new("trim").param("start_frame", skip);
+            source = graph.chain(source, trim).output(0).into();
+        }
+        source = graph
+            .chain(source, VideoFilter::fps(frame_rate))
+            .output(0)
+            .into();
+        let setpts = VideoFilter::setpts("PTS-STARTPTS");
+        graph.chain(source, setpts).output(0).into()
+    }
+
+    /// `count` copies of a source, one per metric
+    fn fan_out(graph: &mut FilterGraph, source: PadSource, count: usize) -> Vec<PadSource> {
+        if count == 1 {
+            return vec![source];
+        }
+        let split = graph.chain(source, VideoFilter::new("split").param("outputs", count));
+        (0..count).map(|i| split.output(i).into()).collect()
+    }
+
+    fn metric_filter(&self, metric: Metric, log_dir: &Path) -> VideoFilter {
+        let log = escape::escape_value(&log_dir.join(metric.log_file()).to_string_lossy());
+        match metric {
+            Metric::Vmaf => {
+                let mut vmaf = VideoFilter::new("libvmaf")
+                    .param("log_fmt", "json")
+                    .param("log_path", log);
+                if let Some(model) = &self.vmaf_model {
+                    vmaf = vmaf.param("model", escape::escape_value(&format!("version={model}")));
+                }
+                if let Some(threads) = self.threads {
+                    vmaf = vmaf.param("n_threads", threads);
+                }
+                vmaf
+            }
+            Metric::Psnr | Metric::Ssim => {
+                VideoFilter::new(metric.filter_name()).param("stats_file", log)
+            }
+        }
+    }
+
+    /// Graph comparing input 1 (distorted) against input 0 (reference),
+    /// one output per metric
+    fn filter_graph(&self, log_dir: &Path, alignment: Alignment) -> FilterGraph {
+        let mut graph = FilterGraph::new();
+        let skip_distorted = u64::try_from(self.frame_offset).unwrap_or(0);
+        let skip_reference = u64::try_from(-self.frame_offset).unwrap_or(0);
+
+        let (distorted, reference): (PadSource, PadSource) =
+            if let Some((width, height)) = self.reference_size {
+                let scale = VideoFilter::new("scale")
+                    .param("w", width)
+                    .param("h", height)
+                    .param("flags", "bicubic");
+                let scaled = graph.chain(InputStream::video(1), scale);
+                (scaled.output(0).into(), InputStream::video(0).into())
+            } else if alignment.scale_ref_input {
+                // The second input is only read for its size, so the
+                // reference needs its own copy
+                let split = graph.chain(InputStream::video(0), VideoFilter::new("split"));
+                let scale = graph.add_with_pads(
+                    VideoFilter::new("scale")
+                        .param("w", "rw")
+                        .param("h", "rh")
+                        .param("flags", "bicubic"),
+                    &[PadType::Video; 2],
+                    &[PadType::Video],
+                );
+                graph.connect(InputStream::video(1), scale.input(0));
+                graph.connect(split.output(1), scale.input(1));
+                (scale.output(0).into(), split.output(0).into())
+            } else {
+                let scale = graph.add_with_pads(
+                    VideoFilter::new("scale2ref").param("flags", "bicubic"),
+                    &[PadType::Video; 2],
+                    &[PadType::Video; 2],
+                );
+                graph.connect(InputStream::video(1), scale.input(0));
+                graph.connect(InputStream::video(0), scale.input(1));
+                (scale.output(0).into(), scale.output(1).into())
+            };
+        let fps = alignment.frame_rate;
+        let distorted = Self::align(&mut graph, distorted, skip_distorted, fps);
+        let reference = Self::align(&mut graph, reference, skip_reference, fps);
+
+        let count = self.metrics.len();
+        let distorted = Self::fan_out(&mut graph, distorted, count);
+        let reference = Self::fan_out(&mut graph, reference, count);
+        for (i, metric) in self.metrics.iter().enumerate() {
+            // The distorted video is the main input of every metric filter
+            let node = graph.add(self.metric_filter(*metric, log_dir));
+            graph.connect(distorted[i], node.input(0));
+            graph.connect(reference[i], node.input(1));
+            graph.output(node.output(0), metric.filter_name());
+        }
+        graph
+    }
+
+    /// Settle the frame rate and scaler from the reference and the FFmpeg
+    /// version
+    async fn alignment(&self) -> Result<Alignment> {
+        let ffmpeg = match &self.executable {
+            Some(path) => path.clone(),
+            None => find_executable("ffmpeg")?,
+        };
+
+        let frame_rate = if let Some(fps) = self.frame_rate {
+            fps
+        } else {
+            // Prefer the ffprobe that sits next to the chosen ffmpeg
+            let ffprobe = ffmpeg
+                .parent()
+                .map(|dir| {
+                    dir.join("ffprobe")
+                        .with_extension(std::env::consts::EXE_EXTENSION)
+                })
+                .filter(|path| path.is_file())
+                .map_or_else(|| find_executable("ffprobe"), Ok)?;
+            SourceVideo::probe_with(ffprobe, self.reference.source().clone())
+                .await?
+                .frame_rate
+        };
+
+        // Versions that do not parse, such as development builds, keep
+        // scale2ref, which is deprecated but still available
+        let scale_ref_input = get_version(&ffmpeg.to_string_lossy())
+            .await
+            .is_ok_and(|version| version.is_at_least(7, 1, 0));
+        Ok(Alignment {
+            frame_rate,
+            scale_ref_input,
+        })
+    }
+
+    /// The comparison command, writing logs into `log_dir`
+    fn command(&self, log_dir: &Path, alignment: Alignment) -> Result<FFmpegBuilder> {
+        if self.metrics.is_empty() {
+            return Err(Error::InvalidArgument(
+                "No quality metric selected".to_string(),
+            ));
+        }
+        let builder = match &self.executable {
+            Some(path) => FFmpegBuilder::with_executable(path),
+            None => FFmpegBuilder::new()?,
+        };
+
+        let graph = self.filter_graph(log_dir, alignment);
+        let output = graph
+            .outputs()
+            .into_iter()
+            .fold(Output::new("-").format("null"), |output, label| {
+                output.map(label.into())
+            });
+        Ok(builder
+            .input(self.reference.clone())
+            .input(self.distorted.clone())
+            .filter_graph(graph)
+            .output(output)
+            .log_level(LogLevel::Info))
+    }
+
+    /// Run the comparison and parse the metric logs
+    pub async fn run(&self) -> Result<QualityReport> {
+        let alignment = self.alignment().await?;
+        let log_dir = tempfile::tempdir()?;
+        let command = self.command(log_dir.path(), alignment)?;
+        run_analysis(command, self.progress_callback.as_deref()).await?;
+
+        let mut report = QualityReport::default();
+        for metric in &self.metrics {
+            let log = tokio::fs::read_to_string(log_dir.path().join(metric.log_file())).await?;
+            let series = match metric {
+                Metric::Vmaf => MetricSeries::parse_vmaf_json(&log)?,
+                Metric::Psnr => MetricSeries::parse_psnr_log(&log)?,
+                Metric::Ssim => MetricSeries::parse_ssim_log(&log)?,
+            };
+            report.metrics.push((*metric, series));
+        }
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-9
+    }
+
+    #[test]
+    fn test_parse_logs() {
+        let vmaf = MetricSeries::parse_vmaf_json(
+            r#"{"version": "2.3.1", "frames": [
+                {"frameNum": 0, "metrics": {"integer_motion": 0.0, "vmaf": 97.5}},
+                {"frameNum": 1, "metrics": {"integer_motion": 1.2, "vmaf": 92.5}}
+            ], "pooled_metrics": {"vmaf": {"mean": 95.0}}}"#,
+        )
+        .unwrap();
+        assert_eq!(vmaf.frames, [97.5, 92.5]);
+
+        let psnr = MetricSeries::parse_psnr_log(
+            "n:1 mse_avg:0.00 mse_y:0.00 mse_u:0.00 mse_v:0.00 psnr_avg:inf psnr_y:inf psnr_u:inf psnr_v:inf\n\
+             n:2 mse_avg:1.32 mse_y:1.71 mse_u:0.54 mse_v:0.55 psnr_avg:46.93 psnr_y:45.80 psnr_u:50.80 psnr_v:50.71\n",
+        )
+        .unwrap();
+        assert_eq!(psnr.frames, [PSNR_IDENTICAL, 46.93]);
+
+        let ssim =
+            MetricSeries::parse_ssim_log("n:1 Y:0.991 U:0.996 V:0.995 All:0.993 (21.5)\n").unwrap();
+        assert_eq!(ssim.frames, [0.993]);
+
+        assert!(MetricSeries::parse_ssim_log("garbage\n").is_err());
+    }
+
+    #[test]
+    fn test_pooling() {
+        let series = MetricSeries {
+            frames: vec![90.0, 100.0, 80.0, 70.0, 60.0],
+        };
+        assert!(close(series.mean(), 80.0));
+        assert!(series.harmonic_mean() < series.mean());
+        assert!(close(series.min(), 60.0));
+        assert!(close(series.max(), 100.0));
+        assert!(close(series.percentile(50.0), 80.0));
+        assert!(close(series.percentile(10.0), 64.0));
+        assert!(close(series.percentile(100.0), 100.0));
+        assert!(MetricSeries::default().percentile(5.0).is_nan());
+    }
+
+    #[test]
+    fn test_comparison_graph() {
+        let comparison = QualityComparison::new(Input::new("ref.y4m"), Input::new("enc.mp4"))
+            .metrics([Metric::Vmaf, Metric::Psnr])
+            .reference_size(1920, 1080)
+            .frame_rate(24.0)
+            .frame_offset(-2)
+            .threads(8)
+            .executable("ffmpeg");
+        let alignment = Alignment {
+            frame_rate: 24.0,
+            scale_ref_input: true,
+        };
+        let graph = comparison.filter_graph(Path::new("/tmp/q"), alignment);
+        graph.validate().unwrap();
+
+        let built = graph.build();
+        assert!(built.contains("[1:v]scale=w=1920:h=1080:flags=bicubic"));
+        assert!(built.contains("fps=fps=24"));
+        assert!(built.contains("[0:v]trim=start_frame=2"));
+        assert!(
+            built.contains("libvmaf=log_fmt=json:log_path=/tmp/q/vmaf.json:n_threads=8[libvmaf]")
+        );
+        assert!(built.contains("psnr=stats_file=/tmp/q/psnr.log[psnr]"));
+
+        let args = comparison
+            .command(Path::new("/tmp/q"), alignment)
+            .unwrap()
+            .build_args()
+            .unwrap();
+        assert_eq!(args.iter().filter(|a| *a == "-map").count(), 2);
+
+        // Without a size the distorted video follows the reference
+        let comparison = QualityComparison::new(Input::new("ref.y4m"), Input::new("enc.mp4"))
+            .metrics([Metric::Ssim]);
+        let graph = comparison.filter_graph(Path::new("/tmp/q"), alignment);
+        graph.validate().unwrap();
+        let built = graph.build();
+        assert!(built.contains("scale=w=rw:h=rh:flags=bicubic"));
+        assert!(!built.contains("scale2ref"));
+
+        let legacy = Alignment {
+            scale_ref_input: false,
+            ..alignment
+        };
+        let graph = comparison.filter_graph(Path::new("/tmp/q"), legacy);
+        graph.validate().unwrap();
+        assert!(
+            graph
+                .build()
+                .starts_with("[1:v][0:v]scale2ref=flags=bicubic")
+        );
+    }
+}
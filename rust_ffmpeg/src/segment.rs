@@ -0,0 +1,511 @@
+//! Segment muxer output with parsed segment lists
+//!
+//! [`SegmentOutput`] turns an [`Output`] whose destination is a file name
+//! pattern into a `segment` (or `stream_segment`) muxer output. Running it
+//! through [`SegmentOutput::run`] returns the segments FFmpeg actually wrote,
+//! read back from the segment list.
+//!
+//! ```no_run
+//! use rust_ffmpeg::segment::SegmentOutput;
+//! use rust_ffmpeg::{Duration, FFmpegBuilder, Input, Output};
+//!
+//! # async fn example() -> ffmpeg_common::Result<()> {
+//! let segments = SegmentOutput::new(Output::new("recording_%Y%m%d_%H%M.mkv").copy_codecs())
+//!     .segment_time(Duration::from_secs(3600))
+//!     .at_clock_time(true)
+//!     .strftime(true)
+//!     .reset_timestamps(true)
+//!     .run(FFmpegBuilder::new()?.input(Input::new("rtsp://camera/stream")))
+//!     .await?;
+//!
+//! for segment in segments {
+//!     println!("{} {:?}-{:?}", segment.path.display(), segment.start, segment.end);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use ffmpeg_common::{Duration, Error, Result};
+use std::path::{Path, PathBuf};
+use std::time::Duration as StdDuration;
+
+use crate::builder::FFmpegBuilder;
+use crate::output::Output;
+
+/// Format of the segment list
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SegmentListType {
+    /// One file name per line
+    Flat,
+    /// `filename,start,end` lines with CSV quoting
+    #[default]
+    Csv,
+    /// HLS-style playlist with segment durations
+    M3u8,
+    /// Concat demuxer script
+    Ffconcat,
+}
+
+impl SegmentListType {
+    /// Value of `-segment_list_type`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Flat => "flat",
+            Self::Csv => "csv",
+            Self::M3u8 => "m3u8",
+            Self::Ffconcat => "ffconcat",
+        }
+    }
+}
+
+/// Segment written by the segment muxer
+#[derive(Debug, Clone, PartialEq)]
+pub struct Segment {
+    /// Path of the segment file
+    pub path: PathBuf,
+    /// Start time in the output timeline, when the list records it
+    pub start: Option<Duration>,
+    /// End time in the output timeline, when the list records it
+    pub end: Option<Duration>,
+}
+
+fn seconds(value: &str) -> Result<Duration> {
+    value
+        .trim()
+        .parse::<f64>()
+        .ok()
+        .and_then(|s| StdDuration::try_from_secs_f64(s.max(0.0)).ok())
+        .map(Duration::from)
+        .ok_or_else(|| Error::ParseError(format!("Invalid segment time '{value}'")))
+}
+
+/// Split a CSV line, honoring double-quoted fields
+fn csv_fields(line: &str) -> Vec<String> {
+    let mut fields = vec![String::new()];
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                chars.next();
+                fields.last_mut().unwrap().push('"');
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(String::new()),
+            _ => fields.last_mut().unwrap().push(c),
+        }
+    }
+    fields
+}
+
+impl Segment {
+    /// Parse a segment list; relative entries are resolved against `base_dir`
+    pub fn parse_list(
+        content: &str,
+        list_type: SegmentListType,
+        base_dir: &Path,
+    ) -> Result<Vec<Self>> {
+        let resolve = |name: &str| base_dir.join(name);
+        let mut segments = Vec::new();
+
+        match list_type {
+            SegmentListType::Flat => {
+                for line in content.lines().map(str::trim).filter(|l| !l.is_empty()) {
+                    segments.push(Self {
+                        path: resolve(line),
+                        start: None,
+                        end: None,
+                    });
+                }
+            }
+            SegmentListType::Csv => {
+                for line in content.lines().filter(|l| !l.trim().is_empty()) {
+                    let fields = csv_fields(line);
+                    let [name, start, end] = fields.as_slice() else {
+                        return Err(Error::ParseError(format!(
+                            "Invalid segment list line '{line}'"
+                        )));
+                    };
+                    segments.push(Self {
+                        path: resolve(name),
+                        start: Some(seconds(start)?),
+                        end: Some(seconds(end)?),
+                    });
+                }
+            }
+            SegmentListType::M3u8 => {
+                // Only durations are listed; starts accumulate from zero
+                let mut elapsed = StdDuration::ZERO;
+                let mut duration = None;
+                for line in content.lines().map(str::trim).filter(|l| !l.is_empty()) {
+                    if let Some(info) = line.strip_prefix("#EXTINF:") {
+                        let value = info.split(',').next().unwrap_or_default();
+                        duration = Some(StdDuration::from(seconds(value)?));
+                    } else if !line.starts_with('#') {
+                        let length = duration.take().unwrap_or_default();
+                        segments.push(Self {
+                            path: resolve(line),
+                            start: Some(elapsed.into()),
+                            end: Some((elapsed + length).into()),
+                        });
+                        elapsed += length;
+                    }
+                }
+            }
+            SegmentListType::Ffconcat => {
+                for line in content.lines().map(str::trim) {
+                    if let Some(name) = line.strip_prefix("file ") {
+                        let name = name.trim().trim_matches('\'').replace("'\\''", "'");
+                        segments.push(Self {
+                            path: resolve(&name),
+                            start: None,
+                            end: None,
+                        });
+                    }
+                }
+            }
+        }
+
+        Ok(segments)
+    }
+
+    /// Read and parse a segment list file
+    pub fn read_list(
+        path: &Path,
+        list_type: SegmentListType,
+        base_dir: &Path,
+    ) -> Result<Vec<Self>> {
+        let content = std::fs::read_to_string(path)?;
+        Self::parse_list(&content, list_type, base_dir)
+    }
+}
+
+/// Where segments are cut
+#[derive(Debug, Clone, PartialEq)]
+enum Cuts {
+    Every(Duration),
+    Times(Vec<Duration>),
+    Frames(Vec<u64>),
+}
+
+/// Output split into segments by the `segment` muxer
+#[derive(Debug, Clone)]
+pub struct SegmentOutput {
+    output: Output,
+    streaming: bool,
+    cuts: Option<Cuts>,
+    reset_timestamps: bool,
+    segment_format: Option<String>,
+    strftime: bool,
+    at_clock_time: bool,
+    start_number: Option<u32>,
+    list: Option<(PathBuf, SegmentListType)>,
+}
+
+impl SegmentOutput {
+    /// Segment `output`, whose destination is the segment name pattern
+    /// (`part_%03d.mp4`, or a strftime pattern with [`strftime`](Self::strftime))
+    pub fn new(output: Output) -> Self {
+        Self {
+            output,
+            streaming: false,
+            cuts: None,
+            reset_timestamps: false,
+            segment_format: None,
+            strftime: false,
+            at_clock_time: false,
+            start_number: None,
+            list: None,
+        }
+    }
+
+    /// Use the `stream_segment` muxer, for formats that cannot seek back
+    /// to finish a file (e.g. MPEG-TS over a live feed)
+    pub fn streaming(mut self, enable: bool) -> Self {
+        self.streaming = enable;
+        self
+    }
+
+    /// Cut a segment every `duration` (at the next keyframe)
+    pub fn segment_time(mut self, duration: impl Into<Duration>) -> Self {
+        self.cuts = Some(Cuts::Every(duration.into()));
+        self
+    }
+
+    /// Cut at the given times
+    pub fn segment_times(mut self, times: impl IntoIterator<Item = Duration>) -> Self {
+        self.cuts = Some(Cuts::Times(times.into_iter().collect()));
+        self
+    }
+
+    /// Cut at the given frame numbers
+    pub fn segment_frames(mut self, frames: impl IntoIterator<Item = u64>) -> Self {
+        self.cuts = Some(Cuts::Frames(frames.into_iter().collect()));
+        self
+    }
+
+    /// Start every segment's timestamps at zero
+    pub fn reset_timestamps(mut self, enable: bool) -> Self {
+        self.reset_timestamps = enable;
+        self
+    }
+
+    /// Container of the segments, instead of guessing it from the pattern
+    pub fn segment_format(mut self, format: impl Into<String>) -> Self {
+        self.segment_format = Some(format.into());
+        self
+    }
+
+    /// Expand the pattern with `strftime` at the start of each segment
+    pub fn strftime(mut self, enable: bool) -> Self {
+        self.strftime = enable;
+        self
+    }
+
+    /// Align cuts to the wall clock (e.g. on the hour with a 1 h segment time)
+    pub fn at_clock_time(mut self, enable: bool) -> Self {
+        self.at_clock_time = enable;
+        self
+    }
+
+    /// Number of the first segment
+    pub fn start_number(mut self, number: u32) -> Self {
+        self.start_number = Some(number);
+        self
+    }
+
+    /// Write a segment list
+    pub fn list(mut self, path: impl Into<PathBuf>, list_type: SegmentListType) -> Self {
+        self.list = Some((path.into(), list_type));
+        self
+    }
+
+    fn validate(&self) -> Result<()> {
+        let pattern = self.output.destination().as_str();
+        if !self.strftime && !pattern.contains('%') {
+            return Err(Error::InvalidArgument(format!(
+                "Segment pattern '{pattern}' has no %d placeholder"
+            )));
+        }
+        match &self.cuts {
+            Some(Cuts::Every(duration)) if StdDuration::from(*duration).is_zero() => Err(
+                Error::InvalidArgument("Segment time must be positive".to_string()),
+            ),
+            Some(Cuts::Times(times))
+                if times.is_empty()
+                    || !times.is_sorted_by(|a, b| a.as_millis() < b.as_millis()) =>
+            {
+                Err(Error::InvalidArgument(
+                    "Segment times must be non-empty and increasing".to_string(),
+                ))
+            }
+            Some(Cuts::Frames(frames))
+                if frames.is_empty() || !frames.is_sorted_by(|a, b| a < b) =>
+            {
+                Err(Error::InvalidArgument(
+                    "Segment frames must be non-empty and increasing".to_string(),
+                ))
+            }
+            _ if self.at_clock_time && !matches!(self.cuts, Some(Cuts::Every(_))) => Err(
+                Error::InvalidArgument("Clock-aligned segments need a segment time".to_string()),
+            ),
+            _ => Ok(()),
+        }
+    }
+
+    /// Build the segment muxer output
+    pub fn into_output(self) -> Result<Output> {
+        self.validate()?;
+
+        let muxer = if self.streaming {
+            "stream_segment"
+        } else {
+            "segment"
+        };
+        let mut output = self.output.format(muxer);
+
+        output = match self.cuts {
+            Some(Cuts::Every(duration)) => {
+                output.option("segment_time", duration.to_ffmpeg_format())
+            }
+            Some(Cuts::Times(times)) => {
+                let times: Vec<_> = times.iter().map(Duration::to_ffmpeg_format).collect();
+                output.option("segment_times", times.join(","))
+            }
+            Some(Cuts::Frames(frames)) => {
+                let frames: Vec<_> = frames.iter().map(ToString::to_string).collect();
+                output.option("segment_frames", frames.join(","))
+            }
+            None => output,
+        };
+        if self.reset_timestamps {
+            output = output.option("reset_timestamps", "1");
+        }
+        if let Some(format) = self.segment_format {
+            output = output.option("segment_format", format);
+        }
+        if self.strftime {
+            output = output.option("strftime", "1");
+        }
+        if self.at_clock_time {
+            output = output.option("segment_atclocktime", "1");
+        }
+        if let Some(number) = self.start_number {
+            output = output.option("segment_start_number", number.to_string());
+        }
+        if let Some((path, list_type)) = self.list {
+            output = output
+                .option("segment_list", path.to_string_lossy())
+                .option("segment_list_type", list_type.as_str());
+        }
+        Ok(output)
+    }
+
+    /// Run `builder` with this output added and return the segments written
+    ///
+    /// Without a configured list, a temporary CSV list is used so that start
+    /// and end times are available.
+    pub async fn run(mut self, builder: FFmpegBuilder) -> Result<Vec<Segment>> {
+        let base_dir = self
+            .output
+            .destination()
+            .path()
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_default();
+
+        // Kept alive until the list has been read
+        let mut temp_dir = None;
+        let (list_path, list_type) = if let Some(list) = self.list.clone() {
+            list
+        } else {
+            let dir = tempfile::tempdir()?;
+            let list = (dir.path().join("segments.csv"), SegmentListType::Csv);
+            temp_dir = Some(dir);
+            self.list = Some(list.clone());
+            list
+        };
+
+        builder.output(self.into_output()?).run().await?;
+
+        let segments = Segment::read_list(&list_path, list_type, &base_dir);
+        drop(temp_dir);
+        segments
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn value<'a>(args: &'a [String], key: &str) -> Option<&'a str> {
+        args.iter()
+            .position(|a| a == key)
+            .map(|i| args[i + 1].as_str())
+    }
+
+    #[test]
+    fn test_segment_output_args() {
+        let args = SegmentOutput::new(Output::new("parts/part_%03d.mp4").copy_codecs())
+            .segment_times([Duration::from_secs(60), Duration::from_millis(90_500)])
+            .reset_timestamps(true)
+            .segment_format("mp4")
+            .list("parts/list.csv", SegmentListType::Csv)
+            .into_output()
+            .unwrap()
+            .build_args();
+
+        assert_eq!(value(&args, "-f"), Some("segment"));
+        assert_eq!(
+            value(&args, "-segment_times"),
+            Some("00:01:00,00:01:30.500")
+        );
+        assert_eq!(value(&args, "-reset_timestamps"), Some("1"));
+        assert_eq!(value(&args, "-segment_format"), Some("mp4"));
+        assert_eq!(value(&args, "-segment_list"), Some("parts/list.csv"));
+        assert_eq!(value(&args, "-segment_list_type"), Some("csv"));
+        assert_eq!(args.last().map(String::as_str), Some("parts/part_%03d.mp4"));
+    }
+
+    #[test]
+    fn test_hourly_strftime() {
+        let args = SegmentOutput::new(Output::new("rec_%Y%m%d_%H.ts"))
+            .streaming(true)
+            .segment_time(Duration::from_secs(3600))
+            .at_clock_time(true)
+            .strftime(true)
+            .into_output()
+            .unwrap()
+            .build_args();
+
+        assert_eq!(value(&args, "-f"), Some("stream_segment"));
+        assert_eq!(value(&args, "-segment_time"), Some("01:00:00"));
+        assert_eq!(value(&args, "-segment_atclocktime"), Some("1"));
+        assert_eq!(value(&args, "-strftime"), Some("1"));
+    }
+
+    #[test]
+    fn test_validation() {
+        let output = || Output::new("part_%03d.mkv");
+        assert!(
+            SegmentOutput::new(Output::new("part.mkv"))
+                .into_output()
+                .is_err()
+        );
+        assert!(
+            SegmentOutput::new(output())
+                .segment_frames([])
+                .into_output()
+                .is_err()
+        );
+        assert!(
+            SegmentOutput::new(output())
+                .segment_frames([100, 50])
+                .into_output()
+                .is_err()
+        );
+        assert!(
+            SegmentOutput::new(output())
+                .at_clock_time(true)
+                .into_output()
+                .is_err()
+        );
+        assert!(
+            SegmentOutput::new(output())
+                .segment_frames([250, 500])
+                .into_output()
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn test_parse_csv_list() {
+        let list = "part_000.mp4,0.000000,60.040000\n\"odd, name.mp4\",60.040000,90.500000\n";
+        let segments = Segment::parse_list(list, SegmentListType::Csv, Path::new("out")).unwrap();
+        assert_eq!(segments.len(), 2);
+        assert_eq!(segments[0].path, Path::new("out/part_000.mp4"));
+        assert_eq!(segments[0].end, Some(Duration::from_millis(60_040)));
+        assert_eq!(segments[1].path, Path::new("out/odd, name.mp4"));
+        assert_eq!(segments[1].start, Some(Duration::from_millis(60_040)));
+
+        assert!(Segment::parse_list("broken", SegmentListType::Csv, Path::new("")).is_err());
+    }
+
+    #[test]
+    fn test_parse_other_lists() {
+        let m3u8 = "#EXTM3U\n#EXT-X-VERSION:3\n#EXTINF:10.000000,\nseg0.ts\n#EXTINF:4.500000,\nseg1.ts\n#EXT-X-ENDLIST\n";
+        let segments = Segment::parse_list(m3u8, SegmentListType::M3u8, Path::new("")).unwrap();
+        assert_eq!(segments[1].start, Some(Duration::from_secs(10)));
+        assert_eq!(segments[1].end, Some(Duration::from_millis(14_500)));
+
+        let flat =
+            Segment::parse_list("a.mkv\nb.mkv\n", SegmentListType::Flat, Path::new("")).unwrap();
+        assert_eq!(flat.len(), 2);
+        assert_eq!(flat[0].start, None);
+
+        let concat = "ffconcat version 1.0\nfile a.mkv\nfile 'it'\\''s.mkv'\n";
+        let segments =
+            Segment::parse_list(concat, SegmentListType::Ffconcat, Path::new("")).unwrap();
+        assert_eq!(segments[1].path, Path::new("it's.mkv"));
+    }
+}
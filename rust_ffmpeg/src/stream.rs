@@ -5,19 +5,28 @@ use std::fmt;
 /// Stream mapping configuration
 #[derive(Debug, Clone)]
 pub struct StreamMap {
-    /// Input file index
-    input_index: usize,
+    /// Where the mapped streams come from
+    source: MapSource,
     /// Stream specifier
     stream_spec: Option<StreamSpecifier>,
     /// Whether this is a negative mapping (exclude)
     negative: bool,
 }
 
+/// Source of a stream mapping
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum MapSource {
+    /// Input file by index
+    Input(usize),
+    /// Labeled output of a complex filter graph
+    Label(String),
+}
+
 impl StreamMap {
     /// Map all streams from an input
     pub fn from_input(input_index: usize) -> Self {
         Self {
-            input_index,
+            source: MapSource::Input(input_index),
             stream_spec: None,
             negative: false,
         }
@@ -26,12 +35,29 @@ impl StreamMap {
     /// Map a specific stream
     pub fn specific(input_index: usize, stream_spec: StreamSpecifier) -> Self {
         Self {
-            input_index,
+            source: MapSource::Input(input_index),
             stream_spec: Some(stream_spec),
             negative: false,
         }
     }
 
+    /// Map a labeled output of a complex filter graph (`[vout]`)
+    ///
+    /// The label may be given with or without the surrounding brackets.
+    pub fn label(label: impl Into<String>) -> Self {
+        let label = label.into();
+        let label = label
+            .strip_prefix('[')
+            .and_then(|l| l.strip_suffix(']'))
+            .map_or(label.clone(), str::to_string);
+
+        Self {
+            source: MapSource::Label(label),
+            stream_spec: None,
+            negative: false,
+        }
+    }
+
     /// Map video streams from input
     pub fn video_from(input_index: usize) -> Self {
         Self::specific(input_index, StreamSpecifier::Type(StreamType::Video))
@@ -53,11 +79,35 @@ impl StreamMap {
     }
 
     /// Exclude this mapping (negative map)
+    ///
+    /// Only streams from an input can be excluded; the builder rejects
+    /// negative maps of filter graph outputs.
     pub fn exclude(mut self) -> Self {
         self.negative = true;
         self
     }
 
+    /// Input file index, if this maps streams from an input
+    pub fn input_index(&self) -> Option<usize> {
+        match self.source {
+            MapSource::Input(index) => Some(index),
+            MapSource::Label(_) => None,
+        }
+    }
+
+    /// Filter graph label (without brackets), if this maps a filter output
+    pub fn filter_label(&self) -> Option<&str> {
+        match self.source {
+            MapSource::Input(_) => None,
+            MapSource::Label(ref label) => Some(label),
+        }
+    }
+
+    /// Whether this is a negative mapping
+    pub fn is_negative(&self) -> bool {
+        self.negative
+    }
+
     /// Convert to command line format
     pub fn to_string(&self) -> String {
         let mut result = String::new();
@@ -66,7 +116,15 @@ impl StreamMap {
             result.push('-');
         }
 
-        result.push_str(&self.input_index.to_string());
+        match self.source {
+            MapSource::Input(index) => result.push_str(&index.to_string()),
+            MapSource::Label(ref label) => {
+                result.push('[');
+                result.push_str(label);
+                result.push(']');
+                return result;
+            }
+        }
 
         if let Some(ref spec) = self.stream_spec {
             result.push(':');
@@ -251,6 +309,12 @@ impl StreamDisposition {
         self
     }
 
+    /// Set as attached picture (cover art)
+    pub fn set_attached_pic(mut self) -> Self {
+        self.attached_pic = true;
+        self
+    }
+
     /// Build disposition string
     pub fn to_string(&self) -> String {
         let mut parts = Vec::new();
@@ -428,6 +492,11 @@ mod tests {
 
         let map = StreamMap::stream_index(0, 2).exclude();
         assert_eq!(map.to_string(), "-0:2");
+
+        let map = StreamMap::label("[vout]");
+        assert_eq!(map.to_string(), "[vout]");
+        assert_eq!(map.filter_label(), Some("vout"));
+        assert_eq!(map.input_index(), None);
     }
 
     #[test]
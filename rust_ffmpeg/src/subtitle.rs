@@ -0,0 +1,385 @@
+//! Subtitle burn-in and soft-subtitle muxing
+
+use ffmpeg_common::{Codec, Error, Result, utils};
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use crate::filter::{TextPosition, VideoFilter, escape};
+
+/// Bitmap subtitle codecs, which cannot be converted to text formats
+const BITMAP_CODECS: [&str; 4] = ["hdmv_pgs_subtitle", "dvd_subtitle", "dvb_subtitle", "xsub"];
+
+/// Pick the subtitle codec for muxing soft subtitles into a container
+///
+/// `format` is a muxer name as returned by
+/// [`utils::guess_format_from_extension`]. `source_codec` is the codec of the
+/// subtitle stream being muxed, if known, so streams already in a suitable
+/// format are copied.
+pub fn soft_subtitle_codec(format: &str, source_codec: Option<&str>) -> Result<Codec> {
+    let bitmap = source_codec.is_some_and(|codec| BITMAP_CODECS.contains(&codec));
+    let target = match format {
+        "mp4" | "mov" | "ipod" | "3gp" => "mov_text",
+        "webm" => "webvtt",
+        "matroska" => {
+            return Ok(match source_codec {
+                None | Some("subrip" | "srt" | "ass" | "ssa" | "webvtt") => Codec::copy(),
+                Some(codec) if BITMAP_CODECS.contains(&codec) => Codec::copy(),
+                Some(_) => Codec::new("srt"),
+            });
+        }
+        _ => {
+            return Err(Error::Unsupported(format!(
+                "container '{format}' cannot hold soft subtitles; burn them in instead"
+            )));
+        }
+    };
+
+    if bitmap {
+        return Err(Error::Unsupported(format!(
+            "bitmap subtitles cannot be converted to {target} for '{format}'; \
+             burn them in or use MKV"
+        )));
+    }
+    Ok(if source_codec == Some(target) {
+        Codec::copy()
+    } else {
+        Codec::new(target)
+    })
+}
+
+/// Colour in ASS style syntax (`&HAABBGGRR`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AssColor {
+    red: u8,
+    green: u8,
+    blue: u8,
+    alpha: u8,
+}
+
+impl AssColor {
+    /// Opaque colour
+    pub fn rgb(red: u8, green: u8, blue: u8) -> Self {
+        Self {
+            red,
+            green,
+            blue,
+            alpha: 0,
+        }
+    }
+
+    /// Set transparency, from 0 (opaque) to 255 (invisible)
+    pub fn transparency(mut self, alpha: u8) -> Self {
+        self.alpha = alpha;
+        self
+    }
+}
+
+impl fmt::Display for AssColor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "&H{:02X}{:02X}{:02X}{:02X}",
+            self.alpha, self.blue, self.green, self.red
+        )
+    }
+}
+
+/// ASS border style
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BorderStyle {
+    /// Outline and drop shadow
+    Outline = 1,
+    /// Opaque box behind the text
+    OpaqueBox = 3,
+}
+
+/// Typed `force_style` for the `subtitles` filter
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AssStyle {
+    fields: Vec<(&'static str, String)>,
+}
+
+impl AssStyle {
+    /// Create an empty style override
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn set(mut self, key: &'static str, value: impl ToString) -> Self {
+        self.fields.retain(|(k, _)| *k != key);
+        self.fields.push((key, value.to_string()));
+        self
+    }
+
+    /// Font family
+    pub fn font_name(self, name: &str) -> Self {
+        self.set("FontName", name)
+    }
+
+    /// Font size
+    pub fn font_size(self, size: u32) -> Self {
+        self.set("FontSize", size)
+    }
+
+    /// Text colour
+    pub fn primary_colour(self, colour: AssColor) -> Self {
+        self.set("PrimaryColour", colour)
+    }
+
+    /// Outline colour
+    pub fn outline_colour(self, colour: AssColor) -> Self {
+        self.set("OutlineColour", colour)
+    }
+
+    /// Shadow or box colour
+    pub fn back_colour(self, colour: AssColor) -> Self {
+        self.set("BackColour", colour)
+    }
+
+    /// Bold text
+    pub fn bold(self, bold: bool) -> Self {
+        self.set("Bold", if bold { -1 } else { 0 })
+    }
+
+    /// Italic text
+    pub fn italic(self, italic: bool) -> Self {
+        self.set("Italic", if italic { -1 } else { 0 })
+    }
+
+    /// Border style
+    pub fn border_style(self, style: BorderStyle) -> Self {
+        self.set("BorderStyle", style as u8)
+    }
+
+    /// Outline width in pixels
+    pub fn outline(self, width: f64) -> Self {
+        self.set("Outline", width)
+    }
+
+    /// Shadow depth in pixels
+    pub fn shadow(self, depth: f64) -> Self {
+        self.set("Shadow", depth)
+    }
+
+    /// Placement on screen
+    pub fn alignment(self, position: TextPosition) -> Self {
+        // Numpad layout
+        let alignment = match position {
+            TextPosition::BottomLeft => 1,
+            TextPosition::BottomCenter => 2,
+            TextPosition::BottomRight => 3,
+            TextPosition::Center => 5,
+            TextPosition::TopLeft => 7,
+            TextPosition::TopCenter => 8,
+            TextPosition::TopRight => 9,
+        };
+        self.set("Alignment", alignment)
+    }
+
+    /// Vertical margin in script pixels
+    pub fn margin_v(self, margin: u32) -> Self {
+        self.set("MarginV", margin)
+    }
+
+    /// Left and right margins in script pixels
+    pub fn margin_h(self, margin: u32) -> Self {
+        self.set("MarginL", margin).set("MarginR", margin)
+    }
+
+    /// Whether no fields are overridden
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty()
+    }
+}
+
+impl fmt::Display for AssStyle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let fields: Vec<String> = self
+            .fields
+            .iter()
+            .map(|(key, value)| format!("{key}={value}"))
+            .collect();
+        write!(f, "{}", fields.join(","))
+    }
+}
+
+/// Builder for burning subtitles into the video
+///
+/// Uses the `ass` filter for plain `.ass`/`.ssa` files and the `subtitles`
+/// filter otherwise, e.g. for SRT files, streams inside a media file or
+/// when a style override is set.
+///
+/// ```
+/// use rust_ffmpeg::subtitle::{AssStyle, SubtitleBurnIn};
+///
+/// let filter = SubtitleBurnIn::new("subs/movie: director's cut.srt")
+///     .force_style(AssStyle::new().font_name("Arial").font_size(28))
+///     .build();
+///
+/// assert_eq!(
+///     filter.to_string(),
+///     r"subtitles=filename=subs/movie\\: director\\\'s cut.srt:force_style=FontName=Arial\,FontSize=28"
+/// );
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct SubtitleBurnIn {
+    path: PathBuf,
+    stream_index: Option<usize>,
+    style: Option<AssStyle>,
+    charenc: Option<String>,
+    fonts_dir: Option<PathBuf>,
+    original_size: Option<(u32, u32)>,
+}
+
+impl SubtitleBurnIn {
+    /// Burn in subtitles from a subtitle file or a media file with subtitle
+    /// streams
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            stream_index: None,
+            style: None,
+            charenc: None,
+            fonts_dir: None,
+            original_size: None,
+        }
+    }
+
+    /// Use the n-th subtitle stream of the file (`si`)
+    pub fn stream_index(mut self, index: usize) -> Self {
+        self.stream_index = Some(index);
+        self
+    }
+
+    /// Override the style of all events (`force_style`)
+    pub fn force_style(mut self, style: AssStyle) -> Self {
+        self.style = Some(style);
+        self
+    }
+
+    /// Character encoding of a text subtitle file, e.g. `CP1252`
+    pub fn charenc(mut self, encoding: impl Into<String>) -> Self {
+        self.charenc = Some(encoding.into());
+        self
+    }
+
+    /// Directory with additional fonts
+    pub fn fonts_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.fonts_dir = Some(dir.into());
+        self
+    }
+
+    /// Size the subtitles were authored for, to keep proportions when the
+    /// video has been scaled
+    pub fn original_size(mut self, width: u32, height: u32) -> Self {
+        self.original_size = Some((width, height));
+        self
+    }
+
+    fn is_ass_file(&self) -> bool {
+        matches!(
+            utils::get_extension(&self.path).as_deref(),
+            Some("ass" | "ssa")
+        )
+    }
+
+    /// Build the filter
+    pub fn build(self) -> VideoFilter {
+        let path_value = |path: &Path| escape::escape_value(&path.to_string_lossy());
+        let subtitles_only = self.stream_index.is_some()
+            || self.style.as_ref().is_some_and(|style| !style.is_empty())
+            || self.charenc.is_some();
+
+        let name = if self.is_ass_file() && !subtitles_only {
+            "ass"
+        } else {
+            "subtitles"
+        };
+        let mut filter = VideoFilter::new(name).param("filename", path_value(&self.path));
+
+        if let Some((width, height)) = self.original_size {
+            filter = filter.param("original_size", format!("{width}x{height}"));
+        }
+        if let Some(dir) = &self.fonts_dir {
+            filter = filter.param("fontsdir", path_value(dir));
+        }
+        if let Some(encoding) = &self.charenc {
+            filter = filter.param("charenc", escape::escape_value(encoding));
+        }
+        if let Some(index) = self.stream_index {
+            filter = filter.param("si", index);
+        }
+        if let Some(style) = self.style.filter(|style| !style.is_empty()) {
+            filter = filter.param("force_style", escape::escape_value(&style.to_string()));
+        }
+        filter
+    }
+}
+
+impl From<SubtitleBurnIn> for VideoFilter {
+    fn from(burn_in: SubtitleBurnIn) -> Self {
+        burn_in.build()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_soft_subtitle_codec() {
+        let codec = |format, source| soft_subtitle_codec(format, source).unwrap().to_string();
+        assert_eq!(codec("mp4", None), "mov_text");
+        assert_eq!(codec("mp4", Some("mov_text")), "copy");
+        assert_eq!(codec("webm", Some("subrip")), "webvtt");
+        assert_eq!(codec("matroska", Some("ass")), "copy");
+        assert_eq!(codec("matroska", Some("hdmv_pgs_subtitle")), "copy");
+        assert_eq!(codec("matroska", Some("mov_text")), "srt");
+
+        assert!(soft_subtitle_codec("mp4", Some("dvd_subtitle")).is_err());
+        assert!(soft_subtitle_codec("avi", None).is_err());
+    }
+
+    #[test]
+    fn test_burn_in() {
+        let filter = SubtitleBurnIn::new("styled.ass").build();
+        assert_eq!(filter.to_string(), "ass=filename=styled.ass");
+
+        let filter = SubtitleBurnIn::new(r"C:\media\movie.mkv")
+            .stream_index(1)
+            .charenc("CP1252")
+            .build();
+        assert_eq!(filter.name(), "subtitles");
+        assert_eq!(
+            filter.option("filename").as_deref(),
+            Some(r"C:\media\movie.mkv")
+        );
+        assert_eq!(filter.option("si").as_deref(), Some("1"));
+
+        // Round-trips through the graph parser
+        let parsed = VideoFilter::parse(&filter.to_string()).unwrap();
+        assert_eq!(parsed, filter);
+    }
+
+    #[test]
+    fn test_force_style() {
+        let style = AssStyle::new()
+            .font_size(24)
+            .primary_colour(AssColor::rgb(255, 204, 0))
+            .back_colour(AssColor::rgb(0, 0, 0).transparency(0x80))
+            .border_style(BorderStyle::OpaqueBox)
+            .alignment(TextPosition::TopCenter)
+            .bold(true);
+        assert_eq!(
+            style.to_string(),
+            "FontSize=24,PrimaryColour=&H0000CCFF,BackColour=&H80000000,\
+             BorderStyle=3,Alignment=8,Bold=-1"
+        );
+
+        let filter = SubtitleBurnIn::new("movie.ass")
+            .force_style(style.clone())
+            .build();
+        assert_eq!(filter.name(), "subtitles");
+        assert_eq!(filter.option("force_style"), Some(style.to_string()));
+    }
+}
@@ -0,0 +1,324 @@
+//! One encode written to several destinations with the `tee` muxer
+//!
+//! Every slave of a [`TeeOutput`] receives the same encoded packets, so the
+//! codecs, maps and filters are set once on the output returned by
+//! [`TeeOutput::into_output`], while each slave keeps its own container,
+//! muxer options, stream selection and bitstream filters.
+//!
+//! ```no_run
+//! use rust_ffmpeg::tee::{TeeOutput, TeeSlave};
+//! use rust_ffmpeg::format::formats::Mkv;
+//! use rust_ffmpeg::{FFmpegBuilder, Input, Output};
+//! use ffmpeg_common::Codec;
+//!
+//! # async fn example() -> ffmpeg_common::Result<()> {
+//! let output = TeeOutput::new()
+//!     .slave(TeeSlave::new("archive.mkv").format_options(Mkv::standard()))
+//!     .slave(TeeSlave::from_output(&Output::new("rtmp://a.example/live/key").format("flv")).ignore_failure(true))
+//!     .slave(TeeSlave::from_output(&Output::new("rtmp://b.example/live/key").format("flv")).ignore_failure(true))
+//!     .into_output()?
+//!     .video_codec(Codec::h264())
+//!     .audio_codec(Codec::aac());
+//!
+//! FFmpegBuilder::new()?
+//!     .input(Input::new("rtsp://studio/program"))
+//!     .output(output)
+//!     .run()
+//!     .await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use ffmpeg_common::{Error, MediaPath, Result, StreamSpecifier};
+use std::collections::BTreeMap;
+
+use crate::format::FormatOptions;
+use crate::output::Output;
+
+/// Escape `chars` and backslashes with a backslash
+fn escape(value: &str, chars: &[char]) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if c == '\\' || c == '\'' || chars.contains(&c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Destination of a [`TeeOutput`]
+#[derive(Debug, Clone)]
+pub struct TeeSlave {
+    destination: MediaPath,
+    format: FormatOptions,
+    select: Vec<StreamSpecifier>,
+    ignore_failure: bool,
+    use_fifo: bool,
+    bsfs: BTreeMap<String, Vec<String>>,
+}
+
+impl TeeSlave {
+    /// Create a slave writing to `destination`
+    pub fn new(destination: impl Into<MediaPath>) -> Self {
+        Self {
+            destination: destination.into(),
+            format: FormatOptions::new(),
+            select: Vec::new(),
+            ignore_failure: false,
+            use_fifo: false,
+            bsfs: BTreeMap::new(),
+        }
+    }
+
+    /// Create a slave from an output's destination and muxer options
+    ///
+    /// Codec, map and filter settings of `output` are not used: they belong
+    /// on the tee output itself.
+    pub fn from_output(output: &Output) -> Self {
+        Self::new(output.destination().clone()).format_options(output.muxer_options())
+    }
+
+    /// Container format and muxer options
+    pub fn format_options(mut self, format: FormatOptions) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Container format
+    pub fn format(mut self, format: impl Into<String>) -> Self {
+        self.format = self.format.format(format);
+        self
+    }
+
+    /// Muxer option
+    pub fn option(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.format = self.format.option(key, value);
+        self
+    }
+
+    /// Only write the matching output streams
+    pub fn select(mut self, streams: impl IntoIterator<Item = StreamSpecifier>) -> Self {
+        self.select = streams.into_iter().collect();
+        self
+    }
+
+    /// Keep the other slaves running when this one fails
+    pub fn ignore_failure(mut self, ignore: bool) -> Self {
+        self.ignore_failure = ignore;
+        self
+    }
+
+    /// Buffer this slave through the `fifo` muxer so a slow destination
+    /// does not stall the others
+    pub fn use_fifo(mut self, enable: bool) -> Self {
+        self.use_fifo = enable;
+        self
+    }
+
+    /// Apply a bitstream filter to the matching streams (all streams when
+    /// `stream` is `None`)
+    pub fn bitstream_filter(
+        mut self,
+        stream: Option<StreamSpecifier>,
+        filter: impl Into<String>,
+    ) -> Self {
+        let key = match stream {
+            Some(spec) => format!("bsfs/{spec}"),
+            None => "bsfs".to_string(),
+        };
+        self.bsfs.entry(key).or_default().push(filter.into());
+        self
+    }
+
+    /// Slave specification, `[options]destination`, escaped for the tee
+    /// muxer
+    pub fn to_spec(&self) -> String {
+        let mut options = Vec::new();
+        if let Some(format) = self.format.format_name() {
+            options.push(("f".to_string(), format.to_string()));
+        }
+        if !self.select.is_empty() {
+            let select: Vec<_> = self.select.iter().map(ToString::to_string).collect();
+            options.push(("select".to_string(), select.join(",")));
+        }
+        if self.ignore_failure {
+            options.push(("onfail".to_string(), "ignore".to_string()));
+        }
+        if self.use_fifo {
+            options.push(("use_fifo".to_string(), "1".to_string()));
+        }
+        for (key, filters) in &self.bsfs {
+            options.push((key.clone(), filters.join(",")));
+        }
+        let mut muxer_options: Vec<_> = self.format.options().iter().collect();
+        muxer_options.sort();
+        for (key, value) in muxer_options {
+            options.push((key.clone(), value.clone()));
+        }
+
+        // Values are unescaped twice: once when splitting slaves on `|`, once
+        // when splitting options on `:` and `]`
+        let options: Vec<_> = options
+            .iter()
+            .map(|(key, value)| format!("{key}={}", escape(value, &[':', ']'])))
+            .collect();
+        let destination = self.destination.as_str();
+        let escaped = escape(destination, &['|', '[', ']']);
+        // A leading `[` would be read as an option block, so keep an empty one
+        if options.is_empty() && !destination.starts_with('[') {
+            return escaped;
+        }
+        let block = escape(&format!("[{}]", options.join(":")), &['|']);
+        format!("{block}{escaped}")
+    }
+}
+
+/// Output written to several destinations by the `tee` muxer
+#[derive(Debug, Clone, Default)]
+pub struct TeeOutput {
+    slaves: Vec<TeeSlave>,
+}
+
+impl TeeOutput {
+    /// Create an empty tee output
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a destination
+    pub fn slave(mut self, slave: TeeSlave) -> Self {
+        self.slaves.push(slave);
+        self
+    }
+
+    /// Add a destination from an output's destination and muxer options
+    pub fn output(self, output: &Output) -> Self {
+        self.slave(TeeSlave::from_output(output))
+    }
+
+    /// The `tee` muxer destination string
+    pub fn to_tee_string(&self) -> String {
+        self.slaves
+            .iter()
+            .map(TeeSlave::to_spec)
+            .collect::<Vec<_>>()
+            .join("|")
+    }
+
+    /// Build the tee output; set codecs, maps and filters on the result
+    ///
+    /// Encoders are asked for global headers, which MP4, FLV and Matroska
+    /// slaves need when they share one encode.
+    pub fn into_output(self) -> Result<Output> {
+        if self.slaves.is_empty() {
+            return Err(Error::InvalidArgument(
+                "A tee output needs at least one destination".to_string(),
+            ));
+        }
+        if let Some(slave) = self
+            .slaves
+            .iter()
+            .find(|s| s.destination.as_str().is_empty())
+        {
+            return Err(Error::InvalidArgument(format!(
+                "Tee destination is empty or not valid UTF-8: {}",
+                slave.destination.path().display()
+            )));
+        }
+
+        let mut flags = vec!["global_header".to_string()];
+        for flag in self.slaves.iter().flat_map(|s| s.format.flags()) {
+            if !flags.contains(flag) {
+                flags.push(flag.clone());
+            }
+        }
+
+        Ok(Output::new(MediaPath::from_url(self.to_tee_string()))
+            .format("tee")
+            .option("flags", format!("+{}", flags.join("+"))))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::format::formats::Mp4;
+    use ffmpeg_common::{Codec, StreamType};
+
+    #[test]
+    fn test_slave_spec() {
+        let slave = TeeSlave::new("rtmp://live.example/app/key")
+            .format("flv")
+            .select([
+                StreamSpecifier::TypeIndex(StreamType::Video, 0),
+                StreamSpecifier::Type(StreamType::Audio),
+            ])
+            .ignore_failure(true)
+            .bitstream_filter(
+                Some(StreamSpecifier::Type(StreamType::Audio)),
+                "aac_adtstoasc",
+            );
+        assert_eq!(
+            slave.to_spec(),
+            r"[f=flv:select=v\\:0,a:onfail=ignore:bsfs/a=aac_adtstoasc]rtmp://live.example/app/key"
+        );
+    }
+
+    #[test]
+    fn test_escaping() {
+        let slave = TeeSlave::new("srt://host:9000?streamid=a|b[1]").format("mpegts");
+        assert_eq!(
+            slave.to_spec(),
+            r"[f=mpegts]srt://host:9000?streamid=a\|b\[1\]"
+        );
+
+        assert_eq!(TeeSlave::new("plain.ts").to_spec(), "plain.ts");
+        assert_eq!(TeeSlave::new("[odd].ts").to_spec(), r"[]\[odd\].ts");
+
+        let slave = TeeSlave::new("out.mp4").option("metadata", "it's");
+        assert_eq!(slave.to_spec(), r"[metadata=it\\\'s]out.mp4");
+    }
+
+    #[test]
+    fn test_from_output() {
+        let output = Output::new("archive.mp4")
+            .format("mp4")
+            .faststart()
+            .video_codec(Codec::h264());
+        let slave = TeeSlave::from_output(&output);
+        assert_eq!(slave.to_spec(), "[f=mp4:movflags=faststart]archive.mp4");
+
+        let slave = TeeSlave::new("frag.mp4").format_options(Mp4::fragmented());
+        assert!(
+            slave
+                .to_spec()
+                .starts_with("[f=mp4:frag_duration=1000000:movflags=")
+        );
+    }
+
+    #[test]
+    fn test_into_output() {
+        assert!(TeeOutput::new().into_output().is_err());
+
+        let args = TeeOutput::new()
+            .slave(TeeSlave::new("archive.mkv").format("matroska"))
+            .output(&Output::new("rtmp://a/live").format("flv"))
+            .into_output()
+            .unwrap()
+            .video_codec(Codec::h264())
+            .build_args();
+
+        let value = |key: &str| {
+            args.iter()
+                .position(|a| a == key)
+                .map(|i| args[i + 1].as_str())
+        };
+        assert_eq!(value("-f"), Some("tee"));
+        assert_eq!(value("-flags"), Some("+global_header"));
+        assert_eq!(
+            args.last().map(String::as_str),
+            Some("[f=matroska]archive.mkv|[f=flv]rtmp://a/live")
+        );
+    }
+}